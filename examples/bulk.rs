@@ -0,0 +1,82 @@
+//! Measures how many transitions per second `process_all` gets through,
+//! against firing the same triggers one at a time with `fire`.
+//! Run with `cargo run --release --example bulk`.
+use std::time::Instant;
+
+use stateless_rs::{StateMachine, StateMachineBuilder};
+use strum_macros::EnumIter;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, EnumIter)]
+enum State {
+    Idle,
+    Running,
+    Paused,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+enum Trigger {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+    Tick,
+}
+
+const TRIGGERS: usize = 10_000_000;
+
+fn build() -> eyre::Result<StateMachine<'static, State, Trigger, u64>> {
+    let mut builder = StateMachineBuilder::new(State::Idle);
+    builder
+        .config(State::Idle)
+        .permit(Trigger::Start, State::Running);
+    builder
+        .config(State::Running)
+        .permit(Trigger::Pause, State::Paused)
+        .permit(Trigger::Stop, State::Idle)
+        .internal_transition(Trigger::Tick, |_, ticks: &mut u64| *ticks += 1);
+    builder
+        .config(State::Paused)
+        .permit(Trigger::Resume, State::Running)
+        .permit(Trigger::Stop, State::Idle);
+    builder.on_transitioned(|_| ());
+    Ok(builder.build(0)?)
+}
+
+fn main() -> eyre::Result<()> {
+    let cycle = [
+        Trigger::Start,
+        Trigger::Tick,
+        Trigger::Pause,
+        Trigger::Resume,
+        Trigger::Tick,
+        Trigger::Stop,
+    ];
+    let triggers = || cycle.iter().copied().cycle().take(TRIGGERS);
+
+    let mut machine = build()?;
+    let started = Instant::now();
+    for trigger in triggers() {
+        machine.fire(trigger)?;
+    }
+    let one_at_a_time = started.elapsed();
+
+    let mut machine = build()?;
+    let started = Instant::now();
+    let summary = machine.process_all(triggers());
+    let bulk = started.elapsed();
+
+    println!("{:?}", summary);
+    let per_second = |elapsed: std::time::Duration| TRIGGERS as f64 / elapsed.as_secs_f64() / 1e6;
+    println!(
+        "fire:        {TRIGGERS} triggers in {:?} ({:.1}M per second)",
+        one_at_a_time,
+        per_second(one_at_a_time)
+    );
+    println!(
+        "process_all: {} triggers in {:?} ({:.1}M per second)",
+        summary.fired,
+        bulk,
+        per_second(bulk)
+    );
+    Ok(())
+}
@@ -1,12 +1,12 @@
+//! Example of using the statemachine to power a phonecall
 use std::fmt::Display;
 use std::sync::MutexGuard;
 use std::time::{Duration, Instant};
 
-///! Example of using the statemachine to power a phonecall
 use stateless_rs::{StateMachine, StateMachineBuilder};
 use strum_macros::EnumIter;
 
-type PhoneStateMachine = StateMachine<State, Trigger, PhoneState>;
+type PhoneStateMachine = StateMachine<'static, State, Trigger, PhoneState>;
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 enum Trigger {
@@ -40,7 +40,7 @@ fn build_statemachine(state: PhoneState) -> eyre::Result<PhoneStateMachine> {
 
     // NB: Specifying the PhoneState type here is needed to infer the closure
     // for on_entry and on_exit
-    let mut builder: StateMachineBuilder<_, _, PhoneState> =
+    let mut builder: StateMachineBuilder<'static, _, _, PhoneState> =
         StateMachineBuilder::new(State::OffHook);
     builder
         .config(State::OffHook)
@@ -71,7 +71,9 @@ fn build_statemachine(state: PhoneState) -> eyre::Result<PhoneStateMachine> {
         // TODO: parameters
         println!(
             "on_transitioned: {:?} -> {:?} via {:?}",
-            t.source, t.destination, t.trigger
+            t.source,
+            t.destination,
+            t.trigger.expect("fired by a trigger")
         )
     });
 
@@ -141,7 +143,7 @@ impl Phone {
         })
     }
 
-    fn state(&self) -> MutexGuard<PhoneState> {
+    fn state(&self) -> MutexGuard<'_, PhoneState> {
         self.statemachine.object()
     }
 
@@ -170,7 +172,7 @@ impl Phone {
 
     fn call_duration(&self) -> Duration {
         let duration = self.state().call_duration;
-        duration.unwrap_or(Duration::default())
+        duration.unwrap_or_default()
     }
 }
 
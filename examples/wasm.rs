@@ -0,0 +1,88 @@
+//! A fetch state machine for a web UI, built as a `cdylib`:
+//!
+//! ```sh
+//! cargo build --example wasm --target wasm32-unknown-unknown --release
+//! ```
+//!
+//! JavaScript loads the module with `WebAssembly.instantiate`, calls `fire`
+//! from event handlers and renders whatever `state` returns.  States and
+//! triggers cross the boundary as their index, so the example needs nothing
+//! beyond the crate itself; with wasm-bindgen the same functions can take
+//! and return strings instead.
+
+use std::cell::RefCell;
+
+use stateless_rs::{StateMachine, StateMachineBuilder};
+use strum_macros::EnumIter;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, EnumIter)]
+enum State {
+    Idle,
+    Loading,
+    Loaded,
+    Failed,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+enum Trigger {
+    Fetch,
+    Succeed,
+    Fail,
+    Reset,
+}
+
+const TRIGGERS: [Trigger; 4] = [
+    Trigger::Fetch,
+    Trigger::Succeed,
+    Trigger::Fail,
+    Trigger::Reset,
+];
+
+thread_local! {
+    // The browser runs the module on one thread, so the machine needs
+    // neither `Send` nor a lock
+    static MACHINE: RefCell<StateMachine<'static, State, Trigger, ()>> = RefCell::new(build());
+}
+
+fn build() -> StateMachine<'static, State, Trigger, ()> {
+    let mut builder = StateMachineBuilder::new(State::Idle);
+    builder
+        .config(State::Idle)
+        .permit(Trigger::Fetch, State::Loading);
+    builder
+        .config(State::Loading)
+        .permit(Trigger::Succeed, State::Loaded)
+        .permit(Trigger::Fail, State::Failed);
+    builder
+        .config(State::Loaded)
+        .permit(Trigger::Reset, State::Idle);
+    builder
+        .config(State::Failed)
+        .permit(Trigger::Fetch, State::Loading)
+        .permit(Trigger::Reset, State::Idle);
+    builder.build(()).expect("the machine is valid")
+}
+
+/// The index of the current state
+#[no_mangle]
+pub extern "C" fn state() -> u32 {
+    MACHINE.with(|machine| machine.borrow().state() as u32)
+}
+
+/// Fire the trigger with index `trigger`, returning whether it was accepted
+#[no_mangle]
+pub extern "C" fn fire(trigger: u32) -> bool {
+    let Some(&trigger) = TRIGGERS.get(trigger as usize) else {
+        return false;
+    };
+    MACHINE.with(|machine| machine.borrow_mut().fire(trigger).is_ok())
+}
+
+/// Whether the trigger with index `trigger` would be accepted, for enabling
+/// buttons
+#[no_mangle]
+pub extern "C" fn can_fire(trigger: u32) -> bool {
+    TRIGGERS
+        .get(trigger as usize)
+        .is_some_and(|&trigger| MACHINE.with(|machine| machine.borrow().can_fire(trigger)))
+}
@@ -0,0 +1,119 @@
+use crate::Transition;
+use alloc::string::String;
+
+/// Describes a registered entry or exit action.  Actions run in ascending
+/// `priority`, and actions with the same priority run in registration order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionInfo {
+    pub priority: i32,
+    /// From [`EntryAction::description`] or [`ExitAction::description`] for
+    /// action objects, and just "action" for closures
+    pub description: String,
+}
+
+impl ActionInfo {
+    pub(crate) fn new(description: impl Into<String>, priority: i32) -> Self {
+        Self {
+            priority,
+            description: description.into(),
+        }
+    }
+}
+
+/// An entry action implemented on a named type rather than a closure, so it
+/// can carry its own state and be tested on its own.  Register it with
+/// [`crate::StateConfig::on_entry_obj`].
+///
+/// ## Example
+/// ```
+/// # use stateless_rs::{EntryAction, StateMachineBuilder, Transition};
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// # enum State { Off, On }
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// # enum Trigger { Switch }
+/// struct CountEntries;
+///
+/// impl EntryAction<State, Trigger, u32> for CountEntries {
+///     fn on_entry(&mut self, _transition: &Transition<State, Trigger>, count: &mut u32) {
+///         *count += 1;
+///     }
+/// }
+///
+/// # fn main() -> Result<(), stateless_rs::StateMachineError<State, Trigger>> {
+/// let mut builder = StateMachineBuilder::new(State::Off);
+/// builder
+///     .config(State::Off)
+///     .permit(Trigger::Switch, State::On);
+/// builder.config(State::On).on_entry_obj(CountEntries);
+/// let mut machine = builder.build(0)?;
+/// machine.fire(Trigger::Switch)?;
+/// assert_eq!(*machine.object(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub trait EntryAction<S, T, O> {
+    fn on_entry(&mut self, transition: &Transition<S, T>, object: &mut O);
+
+    /// What the action does, shown in [`crate::StateMachineInfo`] and the
+    /// diagram exports
+    fn description(&self) -> &str {
+        "action"
+    }
+}
+
+/// The exit counterpart of [`EntryAction`].  Register it with
+/// [`crate::StateConfig::on_exit_obj`].
+pub trait ExitAction<S, T, O> {
+    fn on_exit(&mut self, transition: &Transition<S, T>, object: &mut O);
+
+    /// See [`EntryAction::description`]
+    fn description(&self) -> &str {
+        "action"
+    }
+}
+
+/// Actions dispatched statically rather than through boxed closures.
+///
+/// A machine built with [`crate::StateMachineBuilder::build_with_actions`]
+/// calls these for every transition, after any closures configured on the
+/// states.  The type is a generic parameter of the machine so the calls can be
+/// inlined, which matters for hot inner-loop machines.  Typically the
+/// implementation matches on the transition:
+///
+/// ```
+/// # use stateless_rs::{Owned, StateMachineBuilder, StaticActions, Transition};
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// # enum State { Off, On }
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// # enum Trigger { Switch }
+/// struct Lamp;
+///
+/// impl StaticActions<State, Trigger, u32> for Lamp {
+///     fn on_entry(&mut self, transition: &Transition<State, Trigger>, switched_on: &mut u32) {
+///         if transition.destination == State::On {
+///             *switched_on += 1;
+///         }
+///     }
+/// }
+///
+/// # fn main() -> Result<(), stateless_rs::StateMachineError<State, Trigger>> {
+/// let mut builder = StateMachineBuilder::new(State::Off);
+/// builder.config(State::Off).permit(Trigger::Switch, State::On);
+/// builder.config(State::On).permit(Trigger::Switch, State::Off);
+/// let mut machine = builder.build_with_actions::<Owned<_>, _>(0, Lamp)?;
+/// machine.fire(Trigger::Switch)?;
+/// machine.fire(Trigger::Switch)?;
+/// assert_eq!(*machine.object(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub trait StaticActions<S, T, O> {
+    fn on_entry(&mut self, _transition: &Transition<S, T>, _object: &mut O) {}
+
+    fn on_exit(&mut self, _transition: &Transition<S, T>, _object: &mut O) {}
+
+    fn on_internal(&mut self, _transition: &Transition<S, T>, _object: &mut O) {}
+}
+
+/// No static actions, the default for machines configured with closures
+impl<S, T, O> StaticActions<S, T, O> for () {}
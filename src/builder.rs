@@ -1,23 +1,32 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt::Debug;
+use core::hash::Hash;
 use derivative::Derivative;
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::fmt::Display;
-use std::hash::Hash;
-use std::ops::FnOnce;
-use std::rc::Rc;
-use std::sync::Arc;
-use std::sync::Mutex;
 use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
 
+use crate::action::StaticActions;
+use crate::const_table::ConstTransitionTable;
+use crate::middleware::BoxedMiddleware;
+use crate::middleware::Middleware;
+use crate::parameter_type::ParameterType;
 use crate::state_config::StateConfig;
 use crate::state_config::WrappedStateRep;
 use crate::state_machine::StateMachine;
 use crate::state_representation::StateRepresentation;
+use crate::storage::DefaultStorage;
+use crate::storage::Storage;
 use crate::transition::Transition;
+use crate::trigger_map::TriggerMatching;
+use crate::xstate;
 use crate::StateMachineError;
+use crate::SubscriptionId;
 use crate::TransitionEventHandler;
+use crate::XStateError;
 
 fn unwrap_rc_and_refcell<R>(item: Rc<RefCell<R>>) -> Result<R, Rc<RefCell<R>>> {
     let unrc = Rc::try_unwrap(item)?;
@@ -25,53 +34,394 @@ fn unwrap_rc_and_refcell<R>(item: Rc<RefCell<R>>) -> Result<R, Rc<RefCell<R>>> {
     Ok(val)
 }
 
-#[derive(Debug)]
-pub struct StateMachineBuilder<S, T, O> {
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct StateMachineBuilder<'a, S, T, O> {
     initial_state: S,
-    states: HashMap<S, WrappedStateRep<S, T, O>>,
-    transition_event: TransitionEventHandler<S, T>,
+    /// The order states are kept in: enum order unless given to
+    /// [`Self::with_states`]
+    state_order: Vec<S>,
+    /// Whether `state_order` is every state, see [`Self::with_state_universe`]
+    closed: bool,
+    /// Every state configured so far, in configuration order.  The rest get
+    /// a representation at build if the machine can ever be in them.
+    states: Vec<(S, WrappedStateRep<'a, S, T, O>)>,
+    transition_event: TransitionEventHandler<'a, S, T>,
+    #[derivative(Debug = "ignore")]
+    middlewares: Vec<BoxedMiddleware<S, T>>,
+    trigger_matching: TriggerMatching,
+    /// Payload types declared with [`Self::set_trigger_parameters`]
+    trigger_parameters: Vec<(T, ParameterType)>,
 }
 
-impl<S, T, O> StateMachineBuilder<S, T, O>
+impl<'a, S, T, O> StateMachineBuilder<'a, S, T, O>
 where
     S: IntoEnumIterator + Debug + Copy + Eq + Hash + 'static,
     T: Debug + Copy + Eq + Hash + 'static,
     O: Debug,
 {
     pub fn new(initial_state: S) -> Self {
-        let states: HashMap<S, WrappedStateRep<S, T, O>> = S::iter()
-            .map(|state| {
-                (
-                    state,
-                    Rc::new(RefCell::new(StateRepresentation::new(state))),
-                )
-            })
-            .collect();
+        Self::with_states(initial_state, S::iter())
+    }
+}
+
+impl<'a, S, T, O> StateMachineBuilder<'a, S, T, O>
+where
+    S: Debug + Copy + Eq + Hash + 'static,
+    T: Debug + Copy + Eq + Hash + 'static,
+    O: Debug,
+{
+    /// Like [`Self::new`] for states which don't derive
+    /// `strum::EnumIter`, such as enums from another crate.  `states` takes
+    /// the place of enum order wherever the machine lists states; states
+    /// configured without being in it come after, in configuration order.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// enum State { Off, On }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Switch }
+    ///
+    /// let mut builder =
+    ///     StateMachineBuilder::<_, _, ()>::with_states(State::Off, [State::Off, State::On]);
+    /// builder.config(State::Off).permit(Trigger::Switch, State::On);
+    /// let mut machine = builder.build(()).unwrap();
+    /// machine.fire(Trigger::Switch).unwrap();
+    /// assert_eq!(machine.states(), [State::Off, State::On]);
+    /// ```
+    pub fn with_states(initial_state: S, states: impl IntoIterator<Item = S>) -> Self {
         StateMachineBuilder {
             initial_state,
-            states,
+            state_order: states.into_iter().collect(),
+            closed: false,
+            states: Vec::new(),
             transition_event: TransitionEventHandler::new(),
+            middlewares: Vec::new(),
+            trigger_matching: TriggerMatching::Value,
+            trigger_parameters: Vec::new(),
         }
     }
 
-    pub fn config(&mut self, state: S) -> StateConfig<S, T, O> {
-        let representation = self
-            .states
-            .get(&state)
-            .expect("all states to have been created in constructor");
-        StateConfig::new(Rc::clone(representation))
+    /// Like [`Self::with_states`] but `states` is every state there is:
+    /// build fails with [`StateMachineError::UnknownState`] if the initial
+    /// state, a configured state or a destination isn't in it.  For machines
+    /// generated from data, where states are keys such as `u16` row ids
+    /// rather than enum variants.
+    ///
+    /// ```
+    /// # use stateless_rs::{StateMachineBuilder, StateMachineError};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Next }
+    /// let rows: [(u16, u16); 2] = [(10, 20), (20, 30)];
+    /// let mut builder = StateMachineBuilder::<u16, _, ()>::with_state_universe(10, [10, 20]);
+    /// for (from, to) in rows {
+    ///     builder.config(from).permit(Trigger::Next, to);
+    /// }
+    /// assert!(matches!(
+    ///     builder.build(()),
+    ///     Err(StateMachineError::UnknownState { state: 30 })
+    /// ));
+    /// ```
+    pub fn with_state_universe(initial_state: S, states: impl IntoIterator<Item = S>) -> Self {
+        let mut builder = Self::with_states(initial_state, states);
+        builder.closed = true;
+        builder
+    }
+
+    pub fn config(&mut self, state: S) -> StateConfig<'a, S, T, O> {
+        let representation = match self.representation(state) {
+            Some(representation) => representation,
+            None => {
+                let representation = Rc::new(RefCell::new(
+                    StateRepresentation::with_trigger_matching(state, self.trigger_matching),
+                ));
+                self.states.push((state, representation));
+                &self.states[self.states.len() - 1].1
+            }
+        };
+        StateConfig::new(Rc::clone(representation), self.trigger_matching)
+    }
+
+    /// Enum order, or the order given to [`Self::with_states`], followed by
+    /// any other configured states
+    fn ordered_states(&self) -> Vec<S> {
+        let mut order = self.state_order.clone();
+        for (state, _) in self.states.iter() {
+            if !order.contains(state) {
+                order.push(*state);
+            }
+        }
+        order
+    }
+
+    fn representation(&self, state: S) -> Option<&WrappedStateRep<'a, S, T, O>> {
+        self.states
+            .iter()
+            .find(|(s, _)| *s == state)
+            .map(|(_, rep)| rep)
+    }
+
+    /// Treat triggers as the same when they are the same enum variant, so
+    /// variants can carry data.  Behaviours configured for
+    /// `Trigger::SetVolume(0)` are taken when `Trigger::SetVolume(7)` is
+    /// fired, and actions see the fired value in [`Transition::trigger`].
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Playing }
+    /// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// enum Trigger { SetVolume(u8) }
+    ///
+    /// let mut builder = StateMachineBuilder::<State, Trigger, u8>::new(State::Playing);
+    /// builder.match_triggers_by_discriminant();
+    /// builder
+    ///     .config(State::Playing)
+    ///     .internal_transition(Trigger::SetVolume(0), |t, volume| {
+    ///         if let Some(Trigger::SetVolume(v)) = t.trigger {
+    ///             *volume = v;
+    ///         }
+    ///     });
+    /// let mut machine = builder.build(0).unwrap();
+    /// machine.fire(Trigger::SetVolume(7)).unwrap();
+    /// assert_eq!(*machine.object(), 7);
+    /// ```
+    ///
+    /// ## Panics
+    /// If any state has already been configured
+    pub fn match_triggers_by_discriminant(&mut self) {
+        assert!(
+            self.states.is_empty(),
+            "trigger matching must be chosen before configuring states"
+        );
+        self.trigger_matching = TriggerMatching::Discriminant;
     }
 
-    pub fn on_transitioned<F>(&mut self, f: F)
+    /// Permit every transition in `table`.  Actions and further transitions
+    /// can still be configured on top.
+    pub fn apply_const_table<const N: usize>(&mut self, table: &ConstTransitionTable<S, T, N>) {
+        for (source, trigger, destination) in table.transitions() {
+            self.config(*source).permit(*trigger, *destination);
+        }
+    }
+
+    /// Configure the states and transitions of an XState machine definition,
+    /// such as one exported from Stately's editor.  States are matched by
+    /// their `Debug` output and events by `trigger`.
+    ///
+    /// Descriptions and tags are kept.  Action names are ignored, so
+    /// actions are configured in Rust on top, and targetless transitions
+    /// become internal transitions without actions.  Guards, nested,
+    /// parallel and history states, delayed and eventless transitions are
+    /// rejected.
+    pub fn apply_xstate<F>(&mut self, json: &str, trigger: F) -> Result<(), XStateError>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        let known = self.ordered_states();
+        let by_name = |name: &str| known.iter().copied().find(|s| format!("{:?}", s) == name);
+        let machine = xstate::read(json, by_name, trigger)?;
+        if let Some(initial) = machine.initial {
+            if initial != self.initial_state {
+                return Err(XStateError::InitialStateMismatch {
+                    name: format!("{:?}", initial),
+                });
+            }
+        }
+        for (state, description, tags) in machine.states {
+            let mut config = self.config(state);
+            if let Some(description) = description {
+                config = config.describe(description);
+            }
+            for tag in tags {
+                config = config.tag(tag);
+            }
+        }
+        for transition in machine.transitions {
+            let config = self.config(transition.source);
+            match transition.destination {
+                Some(destination) => config.permit(transition.trigger, destination),
+                None => config.internal_transition(transition.trigger, |_, _| ()),
+            };
+        }
+        Ok(())
+    }
+
+    /// Every state tagged with `tag` so far, in enum order
+    pub fn states_with_tag(&self, tag: &str) -> Vec<S> {
+        self.ordered_states()
+            .into_iter()
+            .filter(|s| {
+                self.representation(*s)
+                    .is_some_and(|rep| rep.borrow().metadata.has_tag(tag))
+            })
+            .collect()
+    }
+
+    /// Call `f` after every transition.  The returned id can be passed to
+    /// [`StateMachine::remove_transitioned`] once the machine is built.
+    pub fn on_transitioned<F>(&mut self, f: F) -> SubscriptionId
     where
-        F: FnMut(&Transition<S, T>) + 'static,
+        F: FnMut(&Transition<S, T>) + 'a,
     {
-        self.transition_event.add_event(f);
+        self.transition_event.add_event(f)
+    }
+
+    /// Call `f` whenever `trigger` is successfully processed, whichever state
+    /// the machine is in.  Internal transitions count too.  Triggers are
+    /// compared as set by [`Self::match_triggers_by_discriminant`] at the
+    /// time this is called.
+    pub fn on_fired<F>(&mut self, trigger: T, mut f: F) -> SubscriptionId
+    where
+        F: FnMut(&Transition<S, T>) + 'a,
+    {
+        let matching = self.trigger_matching;
+        self.transition_event.add_event(move |transition| {
+            if transition
+                .trigger
+                .as_ref()
+                .is_some_and(|t| matching.matches(t, &trigger))
+            {
+                f(transition)
+            }
+        })
+    }
+
+    /// Call `f` once a transition by any of `triggers` has completed, after
+    /// its entry actions have run.  Useful for hooks such as persistence
+    /// which should only follow external triggers and skip housekeeping
+    /// ones.  Triggers are compared as for [`Self::on_fired`].
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Draft, Published }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Publish, Unpublish, Tick }
+    /// let saves = std::cell::Cell::new(0);
+    /// let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::Draft);
+    /// builder
+    ///     .config(State::Draft)
+    ///     .permit(Trigger::Publish, State::Published);
+    /// builder
+    ///     .config(State::Published)
+    ///     .permit(Trigger::Unpublish, State::Draft)
+    ///     .internal_transition(Trigger::Tick, |_, _| ());
+    /// builder.on_fired_by([Trigger::Publish, Trigger::Unpublish], |_| {
+    ///     saves.set(saves.get() + 1)
+    /// });
+    /// let mut machine = builder.build(()).unwrap();
+    /// machine.fire(Trigger::Publish).unwrap();
+    /// machine.fire(Trigger::Tick).unwrap();
+    /// assert_eq!(saves.get(), 1);
+    /// ```
+    pub fn on_fired_by<F>(
+        &mut self,
+        triggers: impl IntoIterator<Item = T>,
+        mut f: F,
+    ) -> SubscriptionId
+    where
+        F: FnMut(&Transition<S, T>) + 'a,
+    {
+        let matching = self.trigger_matching;
+        let triggers: Vec<T> = triggers.into_iter().collect();
+        self.transition_event.add_event(move |transition| {
+            if triggers.iter().any(|trigger| {
+                transition
+                    .trigger
+                    .as_ref()
+                    .is_some_and(|t| matching.matches(t, trigger))
+            }) {
+                f(transition)
+            }
+        })
+    }
+
+    /// Call `f` whenever a transition enters `state`, including re-entering
+    /// it, but not for internal transitions which stay in it.  This keeps
+    /// monitoring out of the state's own configuration.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Running, Failed }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Crash }
+    /// let failures = std::cell::Cell::new(0);
+    /// let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::Running);
+    /// builder
+    ///     .config(State::Running)
+    ///     .permit(Trigger::Crash, State::Failed);
+    /// builder.on_state_entered(State::Failed, |_| failures.set(failures.get() + 1));
+    /// let mut machine = builder.build(()).unwrap();
+    /// machine.fire(Trigger::Crash).unwrap();
+    /// assert_eq!(failures.get(), 1);
+    /// ```
+    pub fn on_state_entered<F>(&mut self, state: S, mut f: F) -> SubscriptionId
+    where
+        F: FnMut(&Transition<S, T>) + 'a,
+    {
+        self.transition_event.add_event(move |transition| {
+            if transition.destination == state && !transition.is_internal() {
+                f(transition)
+            }
+        })
+    }
+
+    /// Call `f` whenever a transition leaves `state`, including to re-enter
+    /// it, but not for internal transitions which stay in it
+    pub fn on_state_exited<F>(&mut self, state: S, mut f: F) -> SubscriptionId
+    where
+        F: FnMut(&Transition<S, T>) + 'a,
+    {
+        self.transition_event.add_event(move |transition| {
+            if transition.source == state && !transition.is_internal() {
+                f(transition)
+            }
+        })
+    }
+
+    /// Require `trigger` to be fired with
+    /// [`StateMachine::fire_with_any`] and a `P`.  Anything else is rejected
+    /// with [`StateMachineError::InvalidTriggerParameters`] before guards or
+    /// actions run.  Triggers are compared as set by
+    /// [`Self::match_triggers_by_discriminant`] when the machine is built.
+    ///
+    /// ```
+    /// # use stateless_rs::{StateMachineBuilder, StateMachineError};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { OnHook, Ringing }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Dial }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::OnHook);
+    /// builder.config(State::OnHook).permit(Trigger::Dial, State::Ringing);
+    /// builder.set_trigger_parameters::<(String, u32)>(Trigger::Dial);
+    /// let mut machine = builder.build(()).unwrap();
+    /// assert!(matches!(
+    ///     machine.fire_with_any(Trigger::Dial, "555-0100"),
+    ///     Err(StateMachineError::InvalidTriggerParameters { .. })
+    /// ));
+    /// machine.fire_with_any(Trigger::Dial, (String::from("555-0100"), 1_u32)).unwrap();
+    /// ```
+    pub fn set_trigger_parameters<P: Any>(&mut self, trigger: T) {
+        self.trigger_parameters
+            .push((trigger, ParameterType::of::<P>()));
+    }
+
+    /// Wrap every call to [`StateMachine::fire`] in `middleware`.  See
+    /// [`Middleware`] for details.  Unlike actions, middlewares must be
+    /// `'static`.
+    pub fn add_middleware<M>(&mut self, middleware: M)
+    where
+        M: Middleware<S, T> + 'static,
+    {
+        self.middlewares.push(Box::new(middleware));
     }
 
     /// Will consume the `StateMachineBuilder` and return a `StateMachine`.  The
-    /// `state_object` will be wrapped in a `Arc<Mutex<O>>` and you can pull it
-    /// out with
+    /// `state_object` will be wrapped in a [`DefaultStorage`] (an
+    /// `Arc<Mutex<O>>` with the `std` feature) and you can pull it out with
     /// ```
     /// # use stateless_rs::StateMachineBuilder;
     /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
@@ -88,24 +438,111 @@ where
     /// # Ok(())
     /// # }
     /// ```
-    pub fn build(self, state_object: O) -> Result<StateMachine<S, T, O>, StateMachineError<S, T>> {
-        // StateMachine::new(self.initial_state, self.states)
-        let state_reps: Result<HashMap<S, StateRepresentation<S, T, O>>, _> = self
+    pub fn build(
+        self,
+        state_object: O,
+    ) -> Result<StateMachine<'a, S, T, O>, StateMachineError<S, T>> {
+        self.build_with_storage::<DefaultStorage<O>>(state_object)
+    }
+
+    /// Like [`Self::build`] but wraps `state_object` in the [`Storage`] `St`
+    /// instead of an `Arc<Mutex<O>>`.
+    ///
+    /// ```
+    /// # use stateless_rs::{Owned, StateMachineBuilder};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { On }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger {
+    /// # }
+    /// # fn main() -> Result<(), stateless_rs::StateMachineError<State,Trigger>> {
+    /// let builder = StateMachineBuilder::<State, Trigger, i32>::new(State::On);
+    /// let machine = builder.build_with_storage::<Owned<_>>(42)?;
+    /// let object: &i32 = machine.object();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build_with_storage<St>(
+        self,
+        state_object: O,
+    ) -> Result<StateMachine<'a, S, T, O, St>, StateMachineError<S, T>>
+    where
+        St: Storage<O>,
+    {
+        self.build_with_actions(state_object, ())
+    }
+
+    /// Like [`Self::build_with_storage`] but also calls `actions` on every
+    /// transition through static rather than dynamic dispatch.  See
+    /// [`StaticActions`].
+    #[allow(clippy::type_complexity)]
+    pub fn build_with_actions<St, A>(
+        self,
+        state_object: O,
+        actions: A,
+    ) -> Result<StateMachine<'a, S, T, O, St, A>, StateMachineError<S, T>>
+    where
+        St: Storage<O>,
+        A: StaticActions<S, T, O>,
+    {
+        let mut order = self.ordered_states();
+        let mut configured = self
             .states
             .into_iter()
-            .map(|(state, rc_ref_rep)| {
-                let rep = unwrap_rc_and_refcell(rc_ref_rep);
-                rep.map(|r| (state, r))
-                    .map_err(|r| StateMachineError::<S, T>::ConfigStillInUse {
+            .map(|(_, rc_ref_rep)| {
+                unwrap_rc_and_refcell(rc_ref_rep).map_err(|r| {
+                    StateMachineError::<S, T>::ConfigStillInUse {
                         state: r.borrow().state(),
-                    })
+                    }
+                })
             })
+            .collect::<Result<Vec<StateRepresentation<'a, S, T, O>>, _>>()?;
+        if let Some(rep) = configured.iter().find(|rep| rep.stray_otherwise) {
+            return Err(StateMachineError::OtherwiseWithoutTransition { state: rep.state() });
+        }
+        // Unconfigured states the machine can be in are still valid, they
+        // just don't do anything
+        let reachable: Vec<S> = core::iter::once(self.initial_state)
+            .chain(configured.iter().flat_map(|rep| rep.destinations()))
+            .collect();
+        if self.closed {
+            if let Some(state) = configured
+                .iter()
+                .map(|rep| rep.state())
+                .chain(reachable.iter().copied())
+                .find(|state| !self.state_order.contains(state))
+            {
+                return Err(StateMachineError::UnknownState { state });
+            }
+        }
+        // Representations are kept in enum order so they can be indexed
+        for state in reachable.iter() {
+            if !order.contains(state) {
+                order.push(*state);
+            }
+        }
+        let state_reps = order
+            .into_iter()
+            .filter_map(
+                |state| match configured.iter().position(|r| r.state() == state) {
+                    Some(index) => Some(configured.swap_remove(index)),
+                    None if reachable.contains(&state) => Some(
+                        StateRepresentation::with_trigger_matching(state, self.trigger_matching),
+                    ),
+                    None => None,
+                },
+            )
             .collect();
         Ok(StateMachine::new(
             self.initial_state,
-            state_reps?,
-            Arc::new(Mutex::new(state_object)),
+            state_reps,
+            St::wrap(state_object),
+            actions,
             self.transition_event,
+            self.middlewares,
+            self.trigger_matching,
+            self.trigger_parameters,
+            self.closed.then_some(self.state_order),
         ))
     }
 }
@@ -116,10 +553,53 @@ mod tests {
     use crate::tests::{State, Trigger};
 
     #[test]
-    fn check_all_states_are_configured_on_new() {
+    fn states_are_created_on_first_config() {
+        let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::State1);
+        assert!(builder.states.is_empty());
+        builder.config(State::State2);
+        builder.config(State::State2);
+        assert_eq!(builder.states.len(), 1);
+    }
+
+    #[test]
+    fn only_reachable_states_are_built() -> eyre::Result<()> {
         let builder = StateMachineBuilder::<State, Trigger, ()>::new(State::State1);
-        assert_eq!(builder.states.len(), State::iter().count());
-        assert!(State::iter().all(|s| builder.states.contains_key(&s)));
+        let machine = builder.build(())?;
+        assert!(machine.state_metadata(State::State1).is_some());
+        assert!(machine.state_metadata(State::State2).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn with_states_orders_states_and_keeps_unlisted_ones() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<u8, Trigger, ()>::with_states(1, [2, 1]);
+        builder.config(1).permit(Trigger::Trig, 3);
+        builder.config(3).permit(Trigger::Trig2, 2);
+        let machine = builder.build(())?;
+        assert_eq!(machine.states(), [2, 1, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn state_universe_rejects_states_outside_it() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<usize, Trigger, ()>::with_state_universe(0, 0..3);
+        builder.config(0).permit(Trigger::Trig, 2);
+        builder.config(2).permit(Trigger::Trig, 0);
+        let machine = builder.build(())?;
+        assert_eq!(machine.states(), [0, 2]);
+
+        let mut builder = StateMachineBuilder::<usize, Trigger, ()>::with_state_universe(0, 0..3);
+        builder.config(7);
+        assert_eq!(
+            builder.build(()).unwrap_err(),
+            StateMachineError::UnknownState { state: 7 }
+        );
+        let builder = StateMachineBuilder::<usize, Trigger, ()>::with_state_universe(5, 0..3);
+        assert_eq!(
+            builder.build(()).unwrap_err(),
+            StateMachineError::UnknownState { state: 5 }
+        );
+        Ok(())
     }
 
     #[test]
@@ -144,7 +624,7 @@ mod tests {
             .config(State::State1)
             .on_entry(|_t, _o| println!("foobar"));
 
-        let rep = builder.states[&State::State1].borrow();
+        let rep = builder.representation(State::State1).unwrap().borrow();
         assert_eq!(rep.entry_actions.len(), 1);
         Ok(())
     }
@@ -156,11 +636,24 @@ mod tests {
             .config(State::State1)
             .on_exit(|_t, _o| println!("foobar"));
 
-        let rep = builder.states[&State::State1].borrow();
+        let rep = builder.representation(State::State1).unwrap().borrow();
         assert_eq!(rep.exit_actions.len(), 1);
         Ok(())
     }
 
+    #[test]
+    fn states_with_tag_are_in_enum_order() {
+        let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::State1);
+        builder.config(State::State2).tag("a").tag("b");
+        builder.config(State::State1).tag("a");
+        assert_eq!(
+            builder.states_with_tag("a"),
+            vec![State::State1, State::State2]
+        );
+        assert_eq!(builder.states_with_tag("b"), vec![State::State2]);
+        assert!(builder.states_with_tag("c").is_empty());
+    }
+
     #[test]
     fn on_transition_twice_adds_two_events() -> eyre::Result<()> {
         let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::State1);
@@ -0,0 +1,17 @@
+use crate::StateMachineError;
+
+/// What [`crate::StateMachine::process_all`] did instead of raising an event
+/// per transition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkSummary<S, T> {
+    /// Triggers accepted before processing stopped
+    pub fired: usize,
+    /// How many of those moved the machine, including re-entries
+    pub transitions: usize,
+    /// How many of those were internal transitions
+    pub internal: usize,
+    pub final_state: S,
+    /// Why processing stopped early, if it did.  The rejected trigger is the
+    /// one after the first `fired`.
+    pub error: Option<StateMachineError<S, T>>,
+}
@@ -0,0 +1,20 @@
+use alloc::string::String;
+use core::time::Duration;
+
+/// Something for the machine to do once a transition has committed,
+/// returned by actions added with [`crate::StateConfig::on_entry_commands`].
+/// Actions which only return commands can be tested by calling them, with
+/// nothing to mock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command<T> {
+    /// Fire the trigger
+    Fire(T),
+    /// Fire the trigger once `after` has passed on the machine's clock, see
+    /// [`crate::StateMachine::fire_due`]
+    Schedule { trigger: T, after: Duration },
+    /// Drop everything scheduled for the trigger
+    Cancel(T),
+    /// Pass an event on to whoever reads
+    /// [`crate::StateMachine::take_emitted`]
+    Emit(String),
+}
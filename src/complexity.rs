@@ -0,0 +1,236 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use crate::state_machine_info::StateMachineInfo;
+
+/// Size and branching metrics of a machine's configuration, from
+/// [`StateMachineInfo::complexity`].  Edges are the configured transitions,
+/// counting each destination of a dynamic transition and internal
+/// transitions as loops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComplexityReport {
+    pub states: usize,
+    pub edges: usize,
+    /// McCabe's `edges - states + 2 * parts` over the state graph, where
+    /// parts are the groups of states connected by some edge
+    pub cyclomatic: usize,
+    pub guarded_edges: usize,
+    /// The most guards combined into the guard of one edge
+    pub max_guards_per_edge: usize,
+    /// The most edges for one trigger in one state
+    pub max_alternatives: usize,
+}
+
+impl ComplexityReport {
+    pub(crate) fn of<S: Copy + PartialEq, T: PartialEq>(info: &StateMachineInfo<S, T>) -> Self {
+        let index = |state: S| info.states.iter().position(|s| s.state == state);
+        // Union-find over state indices to count connected parts
+        let mut parent: Vec<usize> = (0..info.states.len()).collect();
+        let mut report = Self {
+            states: info.states.len(),
+            edges: 0,
+            cyclomatic: 0,
+            guarded_edges: 0,
+            max_guards_per_edge: 0,
+            max_alternatives: 0,
+        };
+        for (source, state) in info.states.iter().enumerate() {
+            let mut alternatives: Vec<(&T, usize)> = Vec::new();
+            for transition in &state.transitions {
+                report.edges += 1;
+                if transition.guard_conditions > 0 {
+                    report.guarded_edges += 1;
+                }
+                report.max_guards_per_edge =
+                    report.max_guards_per_edge.max(transition.guard_conditions);
+                match alternatives
+                    .iter_mut()
+                    .find(|(trigger, _)| **trigger == transition.trigger)
+                {
+                    Some((_, count)) => *count += 1,
+                    None => alternatives.push((&transition.trigger, 1)),
+                }
+                let destination = transition.destination_from(state.state);
+                if let Some(destination) = index(destination) {
+                    let (a, b) = (root(&mut parent, source), root(&mut parent, destination));
+                    parent[a] = b;
+                }
+            }
+            let most = alternatives.iter().map(|(_, count)| *count).max();
+            report.max_alternatives = report.max_alternatives.max(most.unwrap_or(0));
+        }
+        let parts = (0..parent.len())
+            .filter(|&i| root(&mut parent, i) == i)
+            .count();
+        report.cyclomatic = (report.edges + 2 * parts).saturating_sub(report.states);
+        report
+    }
+
+    /// Every metric over its limit in `thresholds`, described for a review
+    /// comment or a failing test.  Empty if the machine is within them all.
+    pub fn violations(&self, thresholds: &ComplexityThresholds) -> Vec<String> {
+        [
+            ("states", self.states, thresholds.states),
+            ("edges", self.edges, thresholds.edges),
+            (
+                "cyclomatic complexity",
+                self.cyclomatic,
+                thresholds.cyclomatic,
+            ),
+            (
+                "guards per edge",
+                self.max_guards_per_edge,
+                thresholds.guards_per_edge,
+            ),
+            (
+                "alternatives per trigger",
+                self.max_alternatives,
+                thresholds.alternatives,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(metric, value, limit)| {
+            let limit = limit?;
+            (value > limit).then(|| format!("{metric} {value} is over {limit}"))
+        })
+        .collect()
+    }
+}
+
+/// The representative of `i`'s part, halving paths on the way
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+impl Display for ComplexityReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} states, {} edges ({} guarded), cyclomatic complexity {}, \
+             at most {} guards per edge and {} alternatives per trigger",
+            self.states,
+            self.edges,
+            self.guarded_edges,
+            self.cyclomatic,
+            self.max_guards_per_edge,
+            self.max_alternatives,
+        )
+    }
+}
+
+/// Limits for [`ComplexityReport::violations`].  Every metric is unlimited
+/// until given a limit.
+///
+/// ```
+/// # use stateless_rs::{ComplexityThresholds, StateMachineBuilder};
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// # enum State { Off, On }
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// # enum Trigger { Switch }
+/// let mut builder = StateMachineBuilder::<_, _, ()>::new(State::Off);
+/// builder.config(State::Off).permit(Trigger::Switch, State::On);
+/// builder.config(State::On).permit(Trigger::Switch, State::Off);
+/// let report = builder.build(()).unwrap().info().complexity();
+/// assert_eq!(report.cyclomatic, 2);
+/// let thresholds = ComplexityThresholds::new().states(10).cyclomatic(1);
+/// assert_eq!(report.violations(&thresholds), ["cyclomatic complexity 2 is over 1"]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComplexityThresholds {
+    states: Option<usize>,
+    edges: Option<usize>,
+    cyclomatic: Option<usize>,
+    guards_per_edge: Option<usize>,
+    alternatives: Option<usize>,
+}
+
+impl ComplexityThresholds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn states(mut self, max: usize) -> Self {
+        self.states = Some(max);
+        self
+    }
+
+    pub fn edges(mut self, max: usize) -> Self {
+        self.edges = Some(max);
+        self
+    }
+
+    pub fn cyclomatic(mut self, max: usize) -> Self {
+        self.cyclomatic = Some(max);
+        self
+    }
+
+    pub fn guards_per_edge(mut self, max: usize) -> Self {
+        self.guards_per_edge = Some(max);
+        self
+    }
+
+    pub fn alternatives(mut self, max: usize) -> Self {
+        self.alternatives = Some(max);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::{Guard, StateMachineBuilder};
+
+    #[test]
+    fn counts_edges_guards_and_alternatives() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(
+                Trigger::Trig,
+                State::State2,
+                Guard::new("positive", |o: &i32| *o > 0).and(|o: &i32| *o < 10),
+            )
+            .permit_if(Trigger::Trig, State::State1, |o: &i32| *o <= 0)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1);
+        let report = builder.build(0)?.info().complexity();
+
+        assert_eq!(
+            report,
+            ComplexityReport {
+                states: 2,
+                edges: 4,
+                cyclomatic: 4,
+                guarded_edges: 2,
+                max_guards_per_edge: 2,
+                max_alternatives: 2,
+            }
+        );
+        let thresholds = ComplexityThresholds::new()
+            .edges(4)
+            .guards_per_edge(1)
+            .alternatives(1);
+        assert_eq!(
+            report.violations(&thresholds),
+            [
+                "guards per edge 2 is over 1",
+                "alternatives per trigger 2 is over 1"
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "2 states, 4 edges (2 guarded), cyclomatic complexity 4, \
+             at most 2 guards per edge and 2 alternatives per trigger"
+        );
+        Ok(())
+    }
+}
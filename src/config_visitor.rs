@@ -0,0 +1,87 @@
+use crate::action::ActionInfo;
+use crate::state_machine_info::{StateInfo, TransitionInfo};
+
+/// Callbacks for walking a machine's configuration with
+/// [`crate::StateMachineInfo::accept`] or [`crate::StateMachine::accept`].
+///
+/// Every method does nothing by default, so a visitor only implements what
+/// it cares about.  States are visited in enum order; each state is
+/// followed by its entry actions, exit actions and then its transitions,
+/// each in the order they run or were configured.
+pub trait ConfigVisitor<S, T> {
+    fn visit_state(&mut self, _state: &StateInfo<S, T>) {}
+
+    fn visit_entry_action(&mut self, _state: S, _action: &ActionInfo) {}
+
+    fn visit_exit_action(&mut self, _state: S, _action: &ActionInfo) {}
+
+    fn visit_transition(&mut self, _source: S, _transition: &TransitionInfo<S, T>) {}
+
+    /// An action of an internal transition, after the transition itself
+    fn visit_transition_action(&mut self, _source: S, _trigger: T, _action: &ActionInfo) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::{StateMachineBuilder, Transition};
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl ConfigVisitor<State, Trigger> for Recorder {
+        fn visit_state(&mut self, state: &StateInfo<State, Trigger>) {
+            self.0.push(format!("state {:?}", state.state));
+        }
+
+        fn visit_entry_action(&mut self, state: State, _action: &ActionInfo) {
+            self.0.push(format!("entry {:?}", state));
+        }
+
+        fn visit_transition(&mut self, source: State, transition: &TransitionInfo<State, Trigger>) {
+            self.0.push(format!(
+                "{:?} -{:?}-> {:?}",
+                source, transition.trigger, transition.destination
+            ));
+        }
+
+        fn visit_transition_action(&mut self, source: State, trigger: Trigger, _: &ActionInfo) {
+            self.0.push(format!("action {:?} {:?}", source, trigger));
+        }
+    }
+
+    #[test]
+    fn visits_states_then_their_actions_and_transitions() -> eyre::Result<()> {
+        fn noop(_: &Transition<State, Trigger>, _: &mut ()) {}
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .on_entry(noop)
+            .on_exit(noop)
+            .internal_transition(Trigger::Trig2, noop);
+        let machine = builder.build(())?;
+
+        let mut recorder = Recorder::default();
+        machine.accept(&mut recorder);
+        assert_eq!(
+            recorder.0,
+            vec![
+                "state State1",
+                "State1 -Trig-> Some(State2)",
+                "state State2",
+                "entry State2",
+                "State2 -Trig2-> None",
+                "action State2 Trig2",
+            ]
+        );
+        Ok(())
+    }
+}
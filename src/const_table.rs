@@ -0,0 +1,154 @@
+/// A guard-free, action-free transition table which can live in a `const`.
+///
+/// Build one with [`crate::const_transition_table!`], which rejects a table
+/// with two transitions for the same source and trigger at compile time.
+/// The table can be queried directly with [`Self::destination`] or applied
+/// to a builder with [`crate::StateMachineBuilder::apply_const_table`] and
+/// have actions layered on at runtime.
+///
+/// ## Example
+/// ```
+/// # use stateless_rs::{const_transition_table, ConstTransitionTable};
+/// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// enum State { Off, On }
+/// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// enum Trigger { Switch }
+///
+/// const TABLE: ConstTransitionTable<State, Trigger, 2> = const_transition_table![
+///     State::Off, Trigger::Switch => State::On,
+///     State::On, Trigger::Switch => State::Off,
+/// ];
+///
+/// assert_eq!(TABLE.destination(State::Off, Trigger::Switch), Some(State::On));
+///
+/// # #[cfg(feature = "alloc")] {
+/// let mut builder = stateless_rs::StateMachineBuilder::<State, Trigger, ()>::new(State::Off);
+/// builder.apply_const_table(&TABLE);
+/// builder.config(State::On).on_entry(|_, _| println!("on"));
+/// # }
+/// ```
+///
+/// Duplicate transitions don't compile:
+/// ```compile_fail
+/// # use stateless_rs::{const_transition_table, ConstTransitionTable};
+/// # #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+/// # enum State { Off, On }
+/// # #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+/// # enum Trigger { Switch }
+/// const TABLE: ConstTransitionTable<State, Trigger, 2> = const_transition_table![
+///     State::Off, Trigger::Switch => State::On,
+///     State::Off, Trigger::Switch => State::Off,
+/// ];
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstTransitionTable<S, T, const N: usize> {
+    transitions: [(S, T, S); N],
+}
+
+impl<S, T, const N: usize> ConstTransitionTable<S, T, N>
+where
+    S: Copy,
+    T: Copy,
+{
+    /// Used by [`crate::const_transition_table!`].  `keys` are the source
+    /// and trigger of each transition as integers, since generic values
+    /// can't be compared in a `const fn`.
+    ///
+    /// ## Panics
+    /// If two transitions have the same key
+    #[doc(hidden)]
+    pub const fn new_checked(transitions: [(S, T, S); N], keys: [(usize, usize); N]) -> Self {
+        let mut i = 0;
+        while i < N {
+            let mut j = i + 1;
+            while j < N {
+                if keys[i].0 == keys[j].0 && keys[i].1 == keys[j].1 {
+                    panic!("duplicate source and trigger in const transition table");
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+        Self { transitions }
+    }
+
+    /// Every `(source, trigger, destination)` in declaration order
+    pub const fn transitions(&self) -> &[(S, T, S)] {
+        &self.transitions
+    }
+
+    pub const fn len(&self) -> usize {
+        N
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        N == 0
+    }
+}
+
+impl<S, T, const N: usize> ConstTransitionTable<S, T, N>
+where
+    S: Copy + PartialEq,
+    T: Copy + PartialEq,
+{
+    /// Where `trigger` leads from `source`, without building a machine
+    pub fn destination(&self, source: S, trigger: T) -> Option<S> {
+        self.transitions
+            .iter()
+            .find(|(s, t, _)| *s == source && *t == trigger)
+            .map(|(_, _, d)| *d)
+    }
+}
+
+/// Build a [`ConstTransitionTable`] from `source, trigger => destination`
+/// entries.  States and triggers must be fieldless enums.
+#[macro_export]
+macro_rules! const_transition_table {
+    ($($source:expr, $trigger:expr => $destination:expr),* $(,)?) => {
+        $crate::ConstTransitionTable::new_checked(
+            [$(($source, $trigger, $destination)),*],
+            [$(($source as usize, $trigger as usize)),*],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+
+    const TABLE: ConstTransitionTable<State, Trigger, 3> = const_transition_table![
+        State::State1, Trigger::Trig => State::State2,
+        State::State2, Trigger::Trig => State::State1,
+        State::State2, Trigger::Trig2 => State::State2,
+    ];
+
+    #[test]
+    fn destination_looks_up_transitions() {
+        assert_eq!(TABLE.len(), 3);
+        assert_eq!(
+            TABLE.destination(State::State1, Trigger::Trig),
+            Some(State::State2)
+        );
+        assert_eq!(TABLE.destination(State::State1, Trigger::Trig2), None);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn applied_table_drives_machine_with_runtime_actions() -> eyre::Result<()> {
+        let mut builder = crate::StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder.apply_const_table(&TABLE);
+        builder
+            .config(State::State2)
+            .on_entry(|_, count: &mut i32| *count += 1);
+        let mut machine = builder.build(0)?;
+
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 2);
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State1);
+        Ok(())
+    }
+}
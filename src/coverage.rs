@@ -0,0 +1,122 @@
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+use crate::state_machine_info::StateMachineInfo;
+use crate::trigger_map::TriggerMatching;
+use crate::Transition;
+
+/// How often one configured transition was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeCoverage<S, T> {
+    pub source: S,
+    pub trigger: T,
+    /// `None` for an internal transition
+    pub destination: Option<S>,
+    pub hits: u64,
+}
+
+/// Which configured transitions have been taken since
+/// [`crate::StateMachine::enable_coverage`], from
+/// [`crate::StateMachine::coverage_report`].
+///
+/// Its `Display` lists the untested transitions, so a test can fail with
+/// `assert!(report.is_complete(), "{report}")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport<S, T> {
+    /// Every configured transition, in [`StateMachineInfo`] order
+    pub edges: Vec<EdgeCoverage<S, T>>,
+}
+
+impl<S, T> CoverageReport<S, T> {
+    pub(crate) fn new(info: &StateMachineInfo<S, T>) -> Self
+    where
+        S: Copy,
+        T: Copy,
+    {
+        let edges = info
+            .states
+            .iter()
+            .flat_map(|state| {
+                state.transitions.iter().map(|t| EdgeCoverage {
+                    source: state.state,
+                    trigger: t.trigger,
+                    destination: t.destination,
+                    hits: 0,
+                })
+            })
+            .collect();
+        Self { edges }
+    }
+
+    /// Count `transition` against the first edge it could have been taken
+    /// along
+    pub(crate) fn record(
+        &mut self,
+        transition: &Transition<S, T>,
+        internal: bool,
+        matching: TriggerMatching,
+    ) where
+        S: PartialEq,
+        T: PartialEq,
+    {
+        let edge = self.edges.iter_mut().find(|edge| {
+            edge.source == transition.source
+                && transition
+                    .trigger
+                    .as_ref()
+                    .is_some_and(|t| matching.matches(&edge.trigger, t))
+                && match &edge.destination {
+                    Some(destination) => !internal && *destination == transition.destination,
+                    None => internal,
+                }
+        });
+        if let Some(edge) = edge {
+            edge.hits += 1;
+        }
+    }
+
+    pub fn untested(&self) -> impl Iterator<Item = &EdgeCoverage<S, T>> {
+        self.edges.iter().filter(|edge| edge.hits == 0)
+    }
+
+    /// Whether every configured transition has been taken
+    pub fn is_complete(&self) -> bool {
+        self.untested().next().is_none()
+    }
+
+    /// The fraction of configured transitions taken, `1.0` if there are none
+    pub fn ratio(&self) -> f64 {
+        if self.edges.is_empty() {
+            return 1.0;
+        }
+        let tested = self.edges.len() - self.untested().count();
+        tested as f64 / self.edges.len() as f64
+    }
+}
+
+impl<S: Debug, T: Debug> Display for CoverageReport<S, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let untested = self.untested().count();
+        write!(
+            f,
+            "{} of {} transitions untested",
+            untested,
+            self.edges.len()
+        )?;
+        for edge in self.untested() {
+            match &edge.destination {
+                Some(destination) => write!(
+                    f,
+                    "\n  {:?} --{:?}--> {:?}",
+                    edge.source, edge.trigger, destination
+                )?,
+                None => write!(
+                    f,
+                    "\n  {:?} --{:?}--> (internal)",
+                    edge.source, edge.trigger
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
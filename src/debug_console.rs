@@ -0,0 +1,129 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Debug;
+use core::hash::{BuildHasher, Hash};
+use std::io::{self, BufRead, Write};
+
+use crate::action::StaticActions;
+use crate::storage::Storage;
+use crate::StateMachine;
+
+const HELP: &str =
+    "commands: state, permitted, fire <trigger>, why-not <trigger>, graph, help, quit";
+
+/// A line-based text interface to a machine, for operators to inspect and
+/// poke it over a socket or terminal.
+///
+/// Commands are `state`, `permitted`, `fire <trigger>`, `why-not <trigger>`,
+/// `graph` (DOT with the current state highlighted), `help` and `quit`.
+/// Triggers are read with the function given to [`Self::new`].
+pub struct DebugConsole<'m, 'a, S, T, O, St, A, H, F> {
+    machine: &'m mut StateMachine<'a, S, T, O, St, A, H>,
+    parse_trigger: F,
+}
+
+impl<'m, 'a, S, T, O, St, A, H, F> DebugConsole<'m, 'a, S, T, O, St, A, H, F>
+where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+    F: Fn(&str) -> Option<T>,
+{
+    pub fn new(machine: &'m mut StateMachine<'a, S, T, O, St, A, H>, parse_trigger: F) -> Self {
+        Self {
+            machine,
+            parse_trigger,
+        }
+    }
+
+    /// Run one command and return its output
+    pub fn execute(&mut self, command: &str) -> String {
+        let mut words = command.split_whitespace();
+        let (Some(command), argument) = (words.next(), words.next()) else {
+            return String::new();
+        };
+        match (command, argument) {
+            ("state", None) => format!("{:?}", self.machine.state()),
+            ("permitted", None) => format!("{:?}", self.machine.permitted_triggers()),
+            ("graph", None) => self.machine.to_dot_live(),
+            ("help", None) => String::from(HELP),
+            ("fire", Some(trigger)) => match self.trigger(trigger) {
+                Ok(trigger) => match self.machine.fire(trigger) {
+                    Ok(()) => format!("ok, now {:?}", self.machine.state()),
+                    Err(error) => format!("rejected: {error}"),
+                },
+                Err(message) => message,
+            },
+            ("why-not", Some(trigger)) => match self.trigger(trigger) {
+                Ok(trigger) => match self.machine.why_not(trigger) {
+                    Some(error) => format!("{error}"),
+                    None => format!("{:?} is permitted in {:?}", trigger, self.machine.state()),
+                },
+                Err(message) => message,
+            },
+            _ => format!("unknown command {command:?}; {HELP}"),
+        }
+    }
+
+    /// Answer each line of `input` on `output` until `quit` or the end of
+    /// the input
+    pub fn serve<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim() == "quit" {
+                break;
+            }
+            let response = self.execute(&line);
+            writeln!(output, "{response}")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    fn trigger(&self, name: &str) -> Result<T, String> {
+        (self.parse_trigger)(name).ok_or_else(|| format!("unknown trigger {name:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    fn trigger(name: &str) -> Option<Trigger> {
+        match name {
+            "Trig" => Some(Trigger::Trig),
+            "Trig2" => Some(Trigger::Trig2),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn serves_commands_until_quit() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(())?;
+
+        let input =
+            "state\nwhy-not Trig2\nfire Trig\nfire Trig\nfire Nope\npermitted\nquit\nstate\n";
+        let mut output = Vec::new();
+        DebugConsole::new(&mut machine, trigger).serve(input.as_bytes(), &mut output)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "State1
+trigger Trig2 not permitted for State1
+ok, now State2
+rejected: trigger Trig not permitted for State2
+unknown trigger \"Nope\"
+[]
+"
+        );
+        Ok(())
+    }
+}
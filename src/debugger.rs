@@ -0,0 +1,118 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::trigger_map::TriggerMatching;
+use crate::Transition;
+
+type DebugCallback<'a, S, T, O> = Box<dyn FnMut(&DebugEvent<'_, S, T>, &O) + 'a>;
+
+/// A point in [`crate::StateMachine::fire`] where the debug callback can be
+/// called
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugPhase {
+    /// Before the source state's exit actions
+    Exit,
+    /// After the exit actions, before the current state changes
+    Transition,
+    /// After the current state changed, before the destination's entry
+    /// actions
+    Entry,
+    /// Before the actions of an internal transition
+    Internal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Breakpoint<S, T> {
+    /// Break when a transition is about to enter the state
+    EnterState(S),
+    /// Break when the trigger is about to be acted on
+    Trigger(T),
+}
+
+/// Passed to the callback set with [`crate::StateMachine::on_debug`]
+#[derive(Debug)]
+pub struct DebugEvent<'e, S, T> {
+    pub phase: DebugPhase,
+    pub transition: &'e Transition<S, T>,
+    /// The breakpoint that was hit, or `None` when single-stepping
+    pub breakpoint: Option<Breakpoint<S, T>>,
+}
+
+/// Single-stepping and breakpoints for one machine.  The machine is paused
+/// for as long as the callback runs.
+pub(crate) struct Debugger<'a, S, T, O> {
+    callback: Option<DebugCallback<'a, S, T, O>>,
+    pub(crate) stepping: bool,
+    pub(crate) breakpoints: Vec<Breakpoint<S, T>>,
+}
+
+impl<S, T, O> Debug for Debugger<'_, S, T, O>
+where
+    S: Debug,
+    T: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Debugger")
+            .field("attached", &self.callback.is_some())
+            .field("stepping", &self.stepping)
+            .field("breakpoints", &self.breakpoints)
+            .finish()
+    }
+}
+
+impl<'a, S, T, O> Debugger<'a, S, T, O>
+where
+    S: Copy + PartialEq,
+    T: Copy + PartialEq,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            callback: None,
+            stepping: false,
+            breakpoints: Vec::new(),
+        }
+    }
+
+    pub(crate) fn attach<F>(&mut self, f: F)
+    where
+        F: FnMut(&DebugEvent<'_, S, T>, &O) + 'a,
+    {
+        self.callback = Some(Box::new(f));
+    }
+
+    pub(crate) fn detach(&mut self) {
+        self.callback = None;
+    }
+
+    /// Call the callback if stepping or a breakpoint matches `phase`
+    pub(crate) fn check(
+        &mut self,
+        phase: DebugPhase,
+        transition: &Transition<S, T>,
+        matching: TriggerMatching,
+        object: &O,
+    ) {
+        let Some(callback) = &mut self.callback else {
+            return;
+        };
+        let breakpoint = self.breakpoints.iter().copied().find(|b| match (phase, b) {
+            (DebugPhase::Exit | DebugPhase::Internal, Breakpoint::Trigger(trigger)) => transition
+                .trigger
+                .as_ref()
+                .is_some_and(|t| matching.matches(trigger, t)),
+            (DebugPhase::Entry, Breakpoint::EnterState(state)) => *state == transition.destination,
+            _ => false,
+        });
+        if breakpoint.is_some() || self.stepping {
+            callback(
+                &DebugEvent {
+                    phase,
+                    transition,
+                    breakpoint,
+                },
+                object,
+            );
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::fmt::Write;
+
+use crate::action::ActionInfo;
+use crate::node_style::NodeStyle;
+use crate::state_machine_info::{StateMachineInfo, TransitionInfo};
+use crate::transition::Transition;
+
+/// `text` as a quoted DOT string
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The trigger an edge is taken by, after its label if it has one
+pub(crate) fn edge_label<S, T: Debug>(transition: &TransitionInfo<S, T>) -> String {
+    match &transition.label {
+        Some(label) => format!("{label}: {:?}", transition.trigger),
+        None => format!("{:?}", transition.trigger),
+    }
+}
+
+pub(crate) fn action_names(infos: &[ActionInfo]) -> String {
+    infos
+        .iter()
+        .map(|info| info.description.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Render the states and transitions of a machine as a Graphviz digraph.
+///
+/// State nodes list their entry and exit actions, transitions are labelled
+/// with their trigger and guard, and internal transitions are drawn as
+/// dashed loops labelled with their actions.  States in a region are drawn
+/// in a cluster named after it.  The edge `last` was taken along, if any, is
+/// drawn in bold.
+pub(crate) fn render<S, T>(
+    info: &StateMachineInfo<S, T>,
+    style: impl Fn(S) -> NodeStyle,
+    last: Option<&Transition<S, T>>,
+) -> String
+where
+    S: Copy + Eq + Debug,
+    T: Copy + Eq + Debug,
+{
+    let mut dot = String::from("digraph {\n");
+    for state_info in &info.states {
+        let state = format!("{:?}", state_info.state);
+        let mut label = state.clone();
+        if !state_info.entry_actions.is_empty() {
+            let _ = write!(
+                label,
+                "\nentry / {}",
+                action_names(&state_info.entry_actions)
+            );
+        }
+        if !state_info.exit_actions.is_empty() {
+            let _ = write!(label, "\nexit / {}", action_names(&state_info.exit_actions));
+        }
+        let _ = write!(dot, "    {} [label={}", quote(&state), quote(&label));
+        for (name, value) in style(state_info.state).attributes() {
+            let _ = write!(dot, ", {}={}", name, quote(value));
+        }
+        dot.push_str("];\n");
+    }
+    let mut regions: Vec<&str> = Vec::new();
+    for region in info.states.iter().filter_map(|s| s.region.as_deref()) {
+        if !regions.contains(&region) {
+            regions.push(region);
+        }
+    }
+    for region in regions {
+        let _ = write!(
+            dot,
+            "    subgraph {} {{\n        label={};\n",
+            quote(&format!("cluster_{region}")),
+            quote(region)
+        );
+        for state_info in &info.states {
+            if state_info.region.as_deref() == Some(region) {
+                let _ = writeln!(
+                    dot,
+                    "        {};",
+                    quote(&format!("{:?}", state_info.state))
+                );
+            }
+        }
+        dot.push_str("    }\n");
+    }
+    for state_info in &info.states {
+        let state = state_info.state;
+        let source = quote(&format!("{:?}", state));
+        for transition in &state_info.transitions {
+            let mut label = edge_label(transition);
+            if let Some(guard) = &transition.guard_description {
+                let _ = write!(label, " [{}]", guard);
+            }
+            if !transition.actions.is_empty() {
+                let _ = write!(label, " / {}", action_names(&transition.actions));
+            }
+            let destination = transition.destination_from(state);
+            let mut attributes = String::new();
+            if transition.destination.is_none() {
+                attributes.push_str(", style=dashed");
+            }
+            let taken = last.is_some_and(|t| {
+                t.source == state
+                    && t.trigger == Some(transition.trigger)
+                    && t.destination == destination
+            });
+            if taken {
+                attributes.push_str(", penwidth=2, color=\"blue\"");
+            }
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label={}{}];",
+                source,
+                quote(&format!("{:?}", destination)),
+                quote(&label),
+                attributes
+            );
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting_escapes_labels() {
+        assert_eq!(quote("a \"b\"\nc\\"), r#""a \"b\"\nc\\""#);
+    }
+}
@@ -0,0 +1,183 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+use crate::state_machine_info::{StateInfo, StateMachineInfo};
+
+/// Where two configurations first differ, from
+/// [`StateMachineInfo::equivalent_to`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence<S, T> {
+    /// Triggers leading from the initial state to `state`, shortest first
+    pub triggers: Vec<T>,
+    pub state: S,
+    /// The trigger the configurations disagree on, or `None` if they start
+    /// in different states
+    pub trigger: Option<T>,
+    /// Where `self` can go on `trigger`
+    pub destinations: Vec<S>,
+    /// Where `other` can go on `trigger`
+    pub other_destinations: Vec<S>,
+}
+
+impl<S: Debug, T: Debug> Display for Divergence<S, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.trigger {
+            Some(trigger) => write!(
+                f,
+                "after {:?}, {:?} on {:?} goes to {:?} instead of {:?}",
+                self.triggers, self.state, trigger, self.destinations, self.other_destinations
+            ),
+            None => write!(
+                f,
+                "initial states differ: {:?} and {:?}",
+                self.destinations, self.other_destinations
+            ),
+        }
+    }
+}
+
+/// Where `state` can go on `trigger`, guards assumed met
+fn destinations_on<S, T>(state: Option<&StateInfo<S, T>>, source: S, trigger: &T) -> Vec<S>
+where
+    S: Copy + PartialEq,
+    T: PartialEq,
+{
+    let mut destinations = Vec::new();
+    for transition in state.into_iter().flat_map(|s| &s.transitions) {
+        let destination = transition.destination_from(source);
+        if transition.trigger == *trigger && !destinations.contains(&destination) {
+            destinations.push(destination);
+        }
+    }
+    destinations
+}
+
+fn same_set<S: PartialEq>(a: &[S], b: &[S]) -> bool {
+    a.len() == b.len() && a.iter().all(|s| b.contains(s))
+}
+
+impl<S, T> StateMachineInfo<S, T>
+where
+    S: Copy + PartialEq,
+    T: Copy + PartialEq,
+{
+    /// Check that `self` and `other` accept the same trigger sequences and
+    /// end up in the same states, such as after refactoring how a machine
+    /// is configured.
+    ///
+    /// Actions and metadata are ignored, so an internal transition and a
+    /// re-entry are equivalent.  Guards can't be compared, so every guard is
+    /// assumed to be met and each trigger compared by the set of states it
+    /// can lead to.
+    pub fn equivalent_to(&self, other: &Self) -> Result<(), Divergence<S, T>> {
+        if self.initial_state != other.initial_state {
+            return Err(Divergence {
+                triggers: Vec::new(),
+                state: self.initial_state,
+                trigger: None,
+                destinations: alloc::vec![self.initial_state],
+                other_destinations: alloc::vec![other.initial_state],
+            });
+        }
+        let mut seen = alloc::vec![self.initial_state];
+        let mut queue = VecDeque::from([(self.initial_state, Vec::new())]);
+        while let Some((state, path)) = queue.pop_front() {
+            let ours = self.state(state);
+            let theirs = other.state(state);
+            let triggers = ours
+                .into_iter()
+                .chain(theirs)
+                .flat_map(|s| s.transitions.iter().map(|t| t.trigger));
+            let mut checked: Vec<T> = Vec::new();
+            for trigger in triggers {
+                if checked.contains(&trigger) {
+                    continue;
+                }
+                checked.push(trigger);
+                let destinations = destinations_on(ours, state, &trigger);
+                let other_destinations = destinations_on(theirs, state, &trigger);
+                if !same_set(&destinations, &other_destinations) {
+                    return Err(Divergence {
+                        triggers: path,
+                        state,
+                        trigger: Some(trigger),
+                        destinations,
+                        other_destinations,
+                    });
+                }
+                for destination in destinations {
+                    if !seen.contains(&destination) {
+                        seen.push(destination);
+                        let mut next = path.clone();
+                        next.push(trigger);
+                        queue.push_back((destination, next));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    #[test]
+    fn internal_transitions_match_reentries() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        let internal = builder.build(())?.info();
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State2)
+            .on_entry(|_, _| ());
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let reentry = builder.build(())?.info();
+
+        assert_eq!(internal.equivalent_to(&reentry), Ok(()));
+        assert_eq!(reentry.equivalent_to(&internal), Ok(()));
+        Ok(())
+    }
+
+    #[test]
+    fn reports_shortest_divergence() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1);
+        let toggle = builder.build(())?.info();
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let one_way = builder.build(())?.info();
+
+        let divergence = toggle.equivalent_to(&one_way).unwrap_err();
+        assert_eq!(divergence.triggers, vec![Trigger::Trig]);
+        assert_eq!(divergence.state, State::State2);
+        assert_eq!(divergence.trigger, Some(Trigger::Trig));
+        assert_eq!(divergence.destinations, vec![State::State1]);
+        assert!(divergence.other_destinations.is_empty());
+        assert_eq!(
+            divergence.to_string(),
+            "after [Trig], State2 on Trig goes to [State1] instead of []"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use core::any::Any;
+use core::fmt::Debug;
+
+use crate::transition::Payload;
+
+type Predicate<'a, O> = Rc<dyn Fn(&O, Option<&Payload>) -> bool + 'a>;
+type Annotator<'a, O> = Rc<dyn Fn(&O) -> Option<String> + 'a>;
+
+/// A condition on the state object that must hold for a trigger behaviour to
+/// be taken.
+///
+/// Every guard carries a description which is reported back in
+/// [`crate::StateMachineError::GuardConditionNotMet`] when it fails.
+pub struct Guard<'a, O> {
+    predicate: Predicate<'a, O>,
+    description: String,
+    annotator: Option<Annotator<'a, O>>,
+    /// How many guards were combined into this one
+    conditions: usize,
+}
+
+impl<'a, O> Guard<'a, O> {
+    pub fn new<F>(description: impl Into<String>, predicate: F) -> Self
+    where
+        F: Fn(&O) -> bool + 'a,
+    {
+        Self {
+            predicate: Rc::new(move |object, _| predicate(object)),
+            description: description.into(),
+            annotator: None,
+            conditions: 1,
+        }
+    }
+
+    /// A guard on the payload the trigger was fired with by
+    /// [`crate::StateMachine::fire_with_any`] as well as the state object.
+    /// It is never met unless the payload is a `P`.
+    pub fn with_payload<P, F>(description: impl Into<String>, predicate: F) -> Self
+    where
+        P: Any,
+        F: Fn(&P, &O) -> bool + 'a,
+    {
+        Self {
+            predicate: Rc::new(move |object, payload| {
+                payload
+                    .and_then(|payload| payload.downcast_ref())
+                    .is_some_and(|args| predicate(args, object))
+            }),
+            description: description.into(),
+            annotator: None,
+            conditions: 1,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// How many guards were combined with [`Self::and`] and [`Self::or`] to
+    /// make this one
+    pub fn conditions(&self) -> usize {
+        self.conditions
+    }
+
+    /// Evaluate without a payload, so guards made with
+    /// [`Self::with_payload`] are never met
+    pub fn evaluate(&self, object: &O) -> bool {
+        (self.predicate)(object, None)
+    }
+
+    pub(crate) fn evaluate_with(&self, object: &O, payload: Option<&Payload>) -> bool {
+        (self.predicate)(object, payload)
+    }
+
+    /// The reason given by a guard made with [`Self::annotated`]
+    pub(crate) fn annotation(&self, object: &O) -> Option<String> {
+        self.annotator.as_ref()?(object)
+    }
+
+    /// Approximate bytes allocated for the predicate and description
+    pub(crate) fn heap_bytes(&self) -> usize {
+        // Rc stores its two reference counts next to the closure
+        core::mem::size_of_val(&*self.predicate)
+            + 2 * core::mem::size_of::<usize>()
+            + self.description.capacity()
+    }
+
+    /// Replace the description, typically after combining guards
+    pub fn describe(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+}
+
+impl<'a, O: 'a> Guard<'a, O> {
+    /// A guard which explains itself: it is met when `annotator` returns a
+    /// reason, which is attached to the transition it allows with
+    /// [`crate::Transition::annotate`].  The annotator is called again once
+    /// the transition is chosen, so it should not have side effects.
+    /// Combining the guard with [`Self::and`], [`Self::or`] or [`Self::not`]
+    /// drops the annotation.
+    ///
+    /// ```
+    /// # use stateless_rs::{Guard, StateMachineBuilder};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Pending, Approved }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Review }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, u32>::new(State::Pending);
+    /// builder.config(State::Pending).permit_if(
+    ///     Trigger::Review,
+    ///     State::Approved,
+    ///     Guard::annotated("low risk", |risk: &u32| {
+    ///         (*risk < 10).then(|| format!("auto-approved: risk {risk}"))
+    ///     }),
+    /// );
+    /// let mut machine = builder.build(3).unwrap();
+    /// machine.fire(Trigger::Review).unwrap();
+    /// let transition = machine.last_transition().unwrap();
+    /// assert_eq!(*transition.annotations(), ["auto-approved: risk 3"]);
+    /// ```
+    pub fn annotated<F>(description: impl Into<String>, annotator: F) -> Self
+    where
+        F: Fn(&O) -> Option<String> + 'a,
+    {
+        let annotator: Annotator<'a, O> = Rc::new(annotator);
+        Self {
+            predicate: Rc::new({
+                let annotator = Rc::clone(&annotator);
+                move |object, _| annotator(object).is_some()
+            }),
+            description: description.into(),
+            annotator: Some(annotator),
+            conditions: 1,
+        }
+    }
+
+    /// Met when both `self` and `other` are met.  `other` is not evaluated
+    /// if `self` fails.
+    pub fn and(self, other: impl Into<Guard<'a, O>>) -> Self {
+        let other = other.into();
+        let description = format!("({} && {})", self.description, other.description);
+        let conditions = self.conditions + other.conditions;
+        Self {
+            predicate: Rc::new(move |o, p| self.evaluate_with(o, p) && other.evaluate_with(o, p)),
+            description,
+            annotator: None,
+            conditions,
+        }
+    }
+
+    /// Met when either `self` or `other` is met.  `other` is not evaluated
+    /// if `self` succeeds.
+    pub fn or(self, other: impl Into<Guard<'a, O>>) -> Self {
+        let other = other.into();
+        let description = format!("({} || {})", self.description, other.description);
+        let conditions = self.conditions + other.conditions;
+        Self {
+            predicate: Rc::new(move |o, p| self.evaluate_with(o, p) || other.evaluate_with(o, p)),
+            description,
+            annotator: None,
+            conditions,
+        }
+    }
+
+    /// Met when `self` is not
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        let description = format!("!{}", self.description);
+        let conditions = self.conditions;
+        Self {
+            predicate: Rc::new(move |o, p| !self.evaluate_with(o, p)),
+            description,
+            annotator: None,
+            conditions,
+        }
+    }
+}
+
+/// Closures become guards described as just "guard", since their type names
+/// are long and change between compiler versions.  Use [`Guard::new`] or
+/// [`Guard::describe`] to say what they check.
+impl<'a, O, F> From<F> for Guard<'a, O>
+where
+    F: Fn(&O) -> bool + 'a,
+{
+    fn from(predicate: F) -> Self {
+        Self::new("guard", predicate)
+    }
+}
+
+impl<O> Clone for Guard<'_, O> {
+    fn clone(&self) -> Self {
+        Self {
+            predicate: Rc::clone(&self.predicate),
+            description: self.description.clone(),
+            annotator: self.annotator.clone(),
+            conditions: self.conditions,
+        }
+    }
+}
+
+impl<O> Debug for Guard<'_, O> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Guard")
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guard_evaluates_predicate() {
+        let guard = Guard::new("is positive", |o: &i32| *o > 0);
+        assert!(guard.evaluate(&1));
+        assert!(!guard.evaluate(&-1));
+        assert_eq!(guard.description(), "is positive");
+    }
+
+    #[test]
+    fn combinators_evaluate_and_describe() {
+        let positive = Guard::new("positive", |o: &i32| *o > 0);
+        let even = Guard::new("even", |o: &i32| *o % 2 == 0);
+
+        let both = positive.clone().and(even.clone());
+        assert!(both.evaluate(&2));
+        assert!(!both.evaluate(&1));
+        assert_eq!(both.description(), "(positive && even)");
+
+        let either = positive.clone().or(even.clone());
+        assert!(either.evaluate(&-2));
+        assert!(!either.evaluate(&-1));
+        assert_eq!(either.description(), "(positive || even)");
+
+        let odd = even.not();
+        assert!(odd.evaluate(&1));
+        assert_eq!(odd.description(), "!even");
+
+        let renamed = positive.and(odd).describe("positive odd number");
+        assert!(renamed.evaluate(&3));
+        assert_eq!(renamed.description(), "positive odd number");
+    }
+
+    #[test]
+    fn payload_guards_see_the_payload_through_combinators() {
+        let within_limit = Guard::with_payload("within limit", |amount: &u64, limit: &u64| {
+            *amount <= *limit
+        });
+        let guard = within_limit.and(|limit: &u64| *limit > 0);
+        assert_eq!(guard.description(), "(within limit && guard)");
+        let small: Payload = alloc::sync::Arc::new(5_u64);
+        let large: Payload = alloc::sync::Arc::new(500_u64);
+        let wrong_type: Payload = alloc::sync::Arc::new(5_u8);
+        assert!(guard.evaluate_with(&100, Some(&small)));
+        assert!(!guard.evaluate_with(&100, Some(&large)));
+        assert!(!guard.evaluate_with(&100, Some(&wrong_type)));
+        assert!(!guard.evaluate(&100));
+    }
+}
@@ -0,0 +1,83 @@
+use core::hash::{BuildHasherDefault, Hasher};
+
+/// The hasher used to look states and triggers up at the API boundary.
+///
+/// This is SipHash unless the `fxhash` feature is enabled.  A machine can be
+/// switched to any other hasher with [`crate::StateMachine::with_hasher`].
+#[cfg(all(feature = "std", not(feature = "fxhash")))]
+pub type DefaultHashBuilder = std::collections::hash_map::RandomState;
+/// The hasher used to look states and triggers up at the API boundary.
+///
+/// This is SipHash unless the `fxhash` feature is enabled.  A machine can be
+/// switched to any other hasher with [`crate::StateMachine::with_hasher`].
+#[cfg(any(feature = "fxhash", not(feature = "std")))]
+pub type DefaultHashBuilder = FxBuildHasher;
+
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+/// The fast, non-cryptographic hasher used by rustc.  It isn't resistant to
+/// collision attacks, which doesn't matter for the fixed set of states and
+/// triggers a machine knows about.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            self.add_to_hash(u64::from_le_bytes(word));
+        }
+        for byte in chunks.remainder() {
+            self.add_to_hash(u64::from(*byte));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add_to_hash(u64::from(i));
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add_to_hash(u64::from(i));
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add_to_hash(u64::from(i));
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add_to_hash(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::hash::BuildHasher;
+
+    #[test]
+    fn equal_values_hash_equally() {
+        let build = FxBuildHasher::default();
+        assert_eq!(build.hash_one("abc"), build.hash_one("abc"));
+        assert_ne!(build.hash_one(1u32), build.hash_one(2u32));
+    }
+}
@@ -0,0 +1,157 @@
+use core::fmt::Debug;
+use derivative::Derivative;
+
+use crate::heapless_machine::{Edge, HeaplessStateMachine, StateAction};
+use crate::HeaplessAction;
+use crate::HeaplessError;
+use crate::HeaplessGuard;
+
+/// Put `item` in the first free slot
+fn push<X>(slots: &mut [Option<X>], item: X) -> Result<usize, X> {
+    match slots.iter().position(Option::is_none) {
+        Some(index) => {
+            slots[index] = Some(item);
+            Ok(index)
+        }
+        None => Err(item),
+    }
+}
+
+/// Configures a [`HeaplessStateMachine`] with room for at most `STATES`
+/// states, `TRANSITIONS` trigger behaviours and `ACTIONS` entry and exit
+/// actions.
+///
+/// Everything is stored inline, so guards and actions are plain function
+/// pointers and configuring one more than there is room for fails with
+/// [`HeaplessError::CapacityExceeded`].
+#[derive(Derivative)]
+#[derivative(Debug(bound = "S: Debug, T: Debug"))]
+pub struct HeaplessBuilder<
+    S,
+    T,
+    O,
+    const STATES: usize,
+    const TRANSITIONS: usize,
+    const ACTIONS: usize,
+> {
+    /// The initial state is always first
+    pub(crate) states: [Option<S>; STATES],
+    pub(crate) edges: [Option<Edge<S, T, O>>; TRANSITIONS],
+    pub(crate) actions: [Option<StateAction<S, T, O>>; ACTIONS],
+}
+
+impl<S, T, O, const STATES: usize, const TRANSITIONS: usize, const ACTIONS: usize>
+    HeaplessBuilder<S, T, O, STATES, TRANSITIONS, ACTIONS>
+where
+    S: Debug + Copy + Eq,
+    T: Debug + Copy + Eq,
+{
+    /// ## Panics
+    /// If `STATES` is zero
+    pub fn new(initial_state: S) -> Self {
+        assert!(
+            STATES > 0,
+            "a heapless machine needs room for its initial state"
+        );
+        let mut states = [None; STATES];
+        states[0] = Some(initial_state);
+        Self {
+            states,
+            edges: core::array::from_fn(|_| None),
+            actions: core::array::from_fn(|_| None),
+        }
+    }
+
+    pub fn permit(
+        &mut self,
+        source: S,
+        trigger: T,
+        destination: S,
+    ) -> Result<&mut Self, HeaplessError<S, T>> {
+        self.add_edge(source, trigger, Some(destination), None, None)
+    }
+
+    pub fn permit_if(
+        &mut self,
+        source: S,
+        trigger: T,
+        destination: S,
+        guard: HeaplessGuard<O>,
+    ) -> Result<&mut Self, HeaplessError<S, T>> {
+        self.add_edge(source, trigger, Some(destination), Some(guard), None)
+    }
+
+    pub fn internal_transition(
+        &mut self,
+        state: S,
+        trigger: T,
+        action: HeaplessAction<S, T, O>,
+    ) -> Result<&mut Self, HeaplessError<S, T>> {
+        self.add_edge(state, trigger, None, None, Some(action))
+    }
+
+    pub fn on_entry(
+        &mut self,
+        state: S,
+        action: HeaplessAction<S, T, O>,
+    ) -> Result<&mut Self, HeaplessError<S, T>> {
+        self.add_action(state, false, action)
+    }
+
+    pub fn on_exit(
+        &mut self,
+        state: S,
+        action: HeaplessAction<S, T, O>,
+    ) -> Result<&mut Self, HeaplessError<S, T>> {
+        self.add_action(state, true, action)
+    }
+
+    pub fn build(self, object: O) -> HeaplessStateMachine<S, T, O, STATES, TRANSITIONS, ACTIONS> {
+        HeaplessStateMachine::new(self, object)
+    }
+
+    /// The slot of `state`, taking a free one if it is new
+    fn state_index(&mut self, state: S) -> Result<usize, HeaplessError<S, T>> {
+        match self.states.iter().position(|s| *s == Some(state)) {
+            Some(index) => Ok(index),
+            None => push(&mut self.states, state).map_err(|_| HeaplessError::CapacityExceeded),
+        }
+    }
+
+    fn add_edge(
+        &mut self,
+        source: S,
+        trigger: T,
+        destination: Option<S>,
+        guard: Option<HeaplessGuard<O>>,
+        action: Option<HeaplessAction<S, T, O>>,
+    ) -> Result<&mut Self, HeaplessError<S, T>> {
+        let source = self.state_index(source)?;
+        let destination = destination.map(|d| self.state_index(d)).transpose()?;
+        let edge = Edge {
+            source,
+            trigger,
+            destination,
+            guard,
+            action,
+        };
+        push(&mut self.edges, edge).map_err(|_| HeaplessError::CapacityExceeded)?;
+        Ok(self)
+    }
+
+    fn add_action(
+        &mut self,
+        state: S,
+        exit: bool,
+        action: HeaplessAction<S, T, O>,
+    ) -> Result<&mut Self, HeaplessError<S, T>> {
+        let state = self.state_index(state)?;
+        let action = StateAction {
+            state,
+            exit,
+            action,
+        };
+        push(&mut self.actions, action).map_err(|_| HeaplessError::CapacityExceeded)?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,45 @@
+use core::fmt::{Debug, Display};
+
+/// Errors from a [`crate::HeaplessStateMachine`] and its builder.
+///
+/// Unlike [`crate::StateMachineError`] this carries no descriptions, so it
+/// can be built without an allocator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaplessError<S, T> {
+    TriggerNotPermitted {
+        state: S,
+        trigger: T,
+    },
+    GuardConditionNotMet {
+        state: S,
+        trigger: T,
+    },
+    AmbiguousTransition {
+        state: S,
+        trigger: T,
+    },
+    /// More states, transitions or actions were configured than the
+    /// builder has room for
+    CapacityExceeded,
+}
+
+impl<S: Debug, T: Debug> Display for HeaplessError<S, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TriggerNotPermitted { state, trigger } => {
+                write!(f, "trigger {trigger:?} not permitted for {state:?}")
+            }
+            Self::GuardConditionNotMet { state, trigger } => write!(
+                f,
+                "trigger {trigger:?} is valid for {state:?} but guard conditions are not met"
+            ),
+            Self::AmbiguousTransition { state, trigger } => write!(
+                f,
+                "multiple permitted transitions are configured for trigger {trigger:?} in {state:?}"
+            ),
+            Self::CapacityExceeded => write!(f, "heapless state machine capacity exceeded"),
+        }
+    }
+}
+
+impl<S: Debug, T: Debug> core::error::Error for HeaplessError<S, T> {}
@@ -0,0 +1,231 @@
+use core::fmt::{Debug, Display};
+use derivative::Derivative;
+
+use crate::HeaplessBuilder;
+use crate::HeaplessError;
+use crate::Transition;
+
+/// An entry, exit or internal action of a [`HeaplessStateMachine`]
+pub type HeaplessAction<S, T, O> = fn(&Transition<S, T>, &mut O);
+/// A guard of a [`HeaplessStateMachine`]
+pub type HeaplessGuard<O> = fn(&O) -> bool;
+
+/// A trigger behaviour, with states referred to by their slot
+#[derive(Derivative)]
+#[derivative(Debug(bound = "T: Debug"))]
+pub(crate) struct Edge<S, T, O> {
+    pub(crate) source: usize,
+    pub(crate) trigger: T,
+    /// `None` for an internal transition
+    pub(crate) destination: Option<usize>,
+    pub(crate) guard: Option<HeaplessGuard<O>>,
+    pub(crate) action: Option<HeaplessAction<S, T, O>>,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub(crate) struct StateAction<S, T, O> {
+    pub(crate) state: usize,
+    pub(crate) exit: bool,
+    pub(crate) action: HeaplessAction<S, T, O>,
+}
+
+/// A state machine for targets without an allocator, built by
+/// [`HeaplessBuilder`].
+///
+/// Its capacities are fixed at compile time and it owns the state object
+/// directly.  Triggers are matched by a linear scan, which is fast for the
+/// handful of transitions firmware usually has.
+#[derive(Derivative)]
+#[derivative(Debug(bound = "S: Debug, T: Debug, O: Debug"))]
+pub struct HeaplessStateMachine<
+    S,
+    T,
+    O,
+    const STATES: usize,
+    const TRANSITIONS: usize,
+    const ACTIONS: usize,
+> {
+    config: HeaplessBuilder<S, T, O, STATES, TRANSITIONS, ACTIONS>,
+    current: usize,
+    object: O,
+}
+
+impl<S, T, O, const STATES: usize, const TRANSITIONS: usize, const ACTIONS: usize>
+    HeaplessStateMachine<S, T, O, STATES, TRANSITIONS, ACTIONS>
+where
+    S: Debug + Copy + Eq,
+    T: Debug + Copy + Eq,
+{
+    pub(crate) fn new(
+        config: HeaplessBuilder<S, T, O, STATES, TRANSITIONS, ACTIONS>,
+        object: O,
+    ) -> Self {
+        Self {
+            config,
+            current: 0,
+            object,
+        }
+    }
+
+    pub fn state(&self) -> S {
+        self.state_at(self.current)
+    }
+
+    pub fn object(&self) -> &O {
+        &self.object
+    }
+
+    pub fn object_mut(&mut self) -> &mut O {
+        &mut self.object
+    }
+
+    /// Whether `trigger` would be accepted in the current state
+    pub fn can_fire(&self, trigger: T) -> bool {
+        self.select(trigger).is_ok()
+    }
+
+    /// Triggers that would be accepted in the current state, in the order
+    /// they were configured
+    pub fn permitted_triggers(&self) -> impl Iterator<Item = T> + '_ {
+        self.edges()
+            .filter(|edge| edge.source == self.current)
+            .map(|edge| edge.trigger)
+            .enumerate()
+            .filter(|(i, trigger)| {
+                let first = self
+                    .edges()
+                    .filter(|edge| edge.source == self.current)
+                    .position(|edge| edge.trigger == *trigger);
+                first == Some(*i) && self.can_fire(*trigger)
+            })
+            .map(|(_, trigger)| trigger)
+    }
+
+    pub fn fire(&mut self, trigger: T) -> Result<(), HeaplessError<S, T>> {
+        let edge = self.select(trigger)?;
+        let (destination, internal_action) = (edge.destination, edge.action);
+        let source = self.state();
+        match destination {
+            None => {
+                let transition = Transition::new(source, trigger, source);
+                if let Some(action) = internal_action {
+                    action(&transition, &mut self.object);
+                }
+            }
+            Some(destination) => {
+                let transition = Transition::new(source, trigger, self.state_at(destination));
+                self.run_actions(self.current, true, &transition);
+                self.current = destination;
+                self.run_actions(destination, false, &transition);
+            }
+        }
+        Ok(())
+    }
+
+    fn state_at(&self, index: usize) -> S {
+        self.config.states[index].expect("states are only referred to once configured")
+    }
+
+    fn edges(&self) -> impl Iterator<Item = &Edge<S, T, O>> {
+        self.config.edges.iter().flatten()
+    }
+
+    /// The one behaviour for `trigger` whose guard is met
+    fn select(&self, trigger: T) -> Result<&Edge<S, T, O>, HeaplessError<S, T>> {
+        let state = self.state();
+        let mut candidates = self
+            .edges()
+            .filter(|edge| edge.source == self.current && edge.trigger == trigger)
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(HeaplessError::TriggerNotPermitted { state, trigger });
+        }
+        let mut met = candidates.filter(|edge| edge.guard.is_none_or(|guard| guard(&self.object)));
+        match (met.next(), met.next()) {
+            (Some(edge), None) => Ok(edge),
+            (None, _) => Err(HeaplessError::GuardConditionNotMet { state, trigger }),
+            (Some(_), Some(_)) => Err(HeaplessError::AmbiguousTransition { state, trigger }),
+        }
+    }
+
+    fn run_actions(&mut self, state: usize, exit: bool, transition: &Transition<S, T>) {
+        let actions = self.config.actions.iter().flatten();
+        for action in actions.filter(|a| a.state == state && a.exit == exit) {
+            (action.action)(transition, &mut self.object);
+        }
+    }
+}
+
+impl<S, T, O, const STATES: usize, const TRANSITIONS: usize, const ACTIONS: usize> Display
+    for HeaplessStateMachine<S, T, O, STATES, TRANSITIONS, ACTIONS>
+where
+    S: Debug + Copy + Eq,
+    T: Debug + Copy + Eq,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "HeaplessStateMachine {{ state: {:?} }}", self.state())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+
+    type Machine = HeaplessStateMachine<State, Trigger, u32, 2, 4, 2>;
+
+    fn build() -> Result<Machine, HeaplessError<State, Trigger>> {
+        let mut builder = HeaplessBuilder::new(State::State1);
+        builder
+            .permit(State::State1, Trigger::Trig, State::State2)?
+            .permit_if(State::State2, Trigger::Trig, State::State1, |count| {
+                *count < 2
+            })?
+            .internal_transition(State::State2, Trigger::Trig2, |_, count| *count += 10)?
+            .on_entry(State::State2, |_, count| *count += 1)?;
+        Ok(builder.build(0))
+    }
+
+    #[test]
+    fn fires_transitions_and_actions() -> eyre::Result<()> {
+        let mut machine = build()?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 1);
+        assert_eq!(
+            machine.permitted_triggers().collect::<Vec<_>>(),
+            vec![Trigger::Trig, Trigger::Trig2]
+        );
+
+        machine.fire(Trigger::Trig2)?;
+        assert_eq!((machine.state(), *machine.object()), (State::State2, 11));
+        assert_eq!(
+            machine.fire(Trigger::Trig),
+            Err(HeaplessError::GuardConditionNotMet {
+                state: State::State2,
+                trigger: Trigger::Trig
+            })
+        );
+        assert!(!machine.can_fire(Trigger::Trig));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_configuration_beyond_capacity() {
+        let mut builder = HeaplessBuilder::<State, Trigger, (), 2, 1, 0>::new(State::State1);
+        assert!(builder
+            .permit(State::State1, Trigger::Trig, State::State2)
+            .is_ok());
+        assert_eq!(
+            builder
+                .permit(State::State2, Trigger::Trig, State::State1)
+                .err(),
+            Some(HeaplessError::CapacityExceeded)
+        );
+        assert_eq!(
+            builder.on_entry(State::State1, |_, _| ()).err(),
+            Some(HeaplessError::CapacityExceeded)
+        );
+    }
+}
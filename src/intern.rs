@@ -0,0 +1,115 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+
+use crate::hasher::DefaultHashBuilder;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// Maps keys to dense `u16` ids in the order they were first interned.
+///
+/// States and triggers are interned when the machine is built so the hot
+/// path works with small integers, converting back only at the API boundary.
+#[derive(Debug, Clone)]
+pub(crate) struct Interner<K, H = DefaultHashBuilder> {
+    keys: Vec<K>,
+    /// Without `std` there is no hash map and ids are found by scanning
+    /// `keys`, which is cheap for the handful of states a machine has
+    #[cfg(feature = "std")]
+    ids: HashMap<K, u16, H>,
+    hasher: PhantomData<H>,
+}
+
+impl<K, H> Interner<K, H>
+where
+    K: Copy + Eq + Hash,
+    H: BuildHasher + Default,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            #[cfg(feature = "std")]
+            ids: HashMap::default(),
+            hasher: PhantomData,
+        }
+    }
+
+    /// The id of `key`, interning it if it hasn't been seen before
+    ///
+    /// ## Panics
+    /// If more than `u16::MAX + 1` keys are interned
+    pub(crate) fn intern(&mut self, key: K) -> u16 {
+        if let Some(id) = self.id(&key) {
+            return id;
+        }
+        let id = u16::try_from(self.keys.len()).expect("at most 65536 states and triggers");
+        self.keys.push(key);
+        #[cfg(feature = "std")]
+        self.ids.insert(key, id);
+        id
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn id(&self, key: &K) -> Option<u16> {
+        self.ids.get(key).copied()
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn id(&self, key: &K) -> Option<u16> {
+        self.keys
+            .iter()
+            .position(|k| k == key)
+            .map(|index| index as u16)
+    }
+}
+
+impl<K: Copy, H> Interner<K, H> {
+    pub(crate) fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Approximate bytes allocated for the keys and, with `std`, the map
+    /// back to ids
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let keys = self.keys.capacity() * core::mem::size_of::<K>();
+        #[cfg(feature = "std")]
+        // hashbrown keeps a control byte per bucket
+        let keys = keys + self.ids.capacity() * (core::mem::size_of::<(K, u16)>() + 1);
+        keys
+    }
+}
+
+impl<K, H> FromIterator<K> for Interner<K, H>
+where
+    K: Copy + Eq + Hash,
+    H: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut interner = Self::new();
+        for key in iter {
+            interner.intern(key);
+        }
+        interner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::State;
+
+    #[test]
+    fn ids_are_dense_and_stable() {
+        let mut interner = Interner::<_>::new();
+        assert_eq!(interner.intern(State::State2), 0);
+        assert_eq!(interner.intern(State::State1), 1);
+        assert_eq!(interner.intern(State::State2), 0);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.id(&State::State1), Some(1));
+        assert_eq!(interner.keys(), &[State::State2, State::State1]);
+    }
+}
@@ -0,0 +1,267 @@
+//! Just enough JSON to read machine definitions, without a dependency
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Members in document order
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// `text` as a quoted JSON (and TypeScript) string
+pub(crate) fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", u32::from(c));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parse a whole document, or return the byte offset it stopped making
+/// sense at
+pub(crate) fn parse(text: &str) -> Result<Value, usize> {
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        text,
+        pos: 0,
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.pos == parser.bytes.len() {
+        Ok(value)
+    } else {
+        Err(parser.pos)
+    }
+}
+
+struct Parser<'t> {
+    bytes: &'t [u8],
+    text: &'t str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> Result<(), usize> {
+        self.whitespace();
+        if self.bytes.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.pos)
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, usize> {
+        if self.text[self.pos..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.pos)
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, usize> {
+        self.whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.pos),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, usize> {
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| matches!(b, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+        {
+            self.pos += 1;
+        }
+        self.text[start..self.pos]
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| start)
+    }
+
+    fn string(&mut self) -> Result<String, usize> {
+        self.eat(b'"')?;
+        let mut string = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let c = rest.chars().next().ok_or(self.pos)?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escape = self.bytes.get(self.pos).copied().ok_or(self.pos)?;
+                    self.pos += 1;
+                    string.push(match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.pos - 1),
+                    });
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// The four hex digits after `\u`, combined with a following low
+    /// surrogate if needed
+    fn unicode_escape(&mut self) -> Result<char, usize> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.text[self.pos..].starts_with("\\u") {
+                return Err(self.pos);
+            }
+            self.pos += 2;
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or(self.pos)
+    }
+
+    fn hex4(&mut self) -> Result<u32, usize> {
+        let digits = self.text.get(self.pos..self.pos + 4).ok_or(self.pos)?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.pos)?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Value, usize> {
+        self.eat(b'[')?;
+        let mut items = Vec::new();
+        if self.eat(b']').is_ok() {
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            if self.eat(b']').is_ok() {
+                return Ok(Value::Array(items));
+            }
+            self.eat(b',')?;
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, usize> {
+        self.eat(b'{')?;
+        let mut members = Vec::new();
+        if self.eat(b'}').is_ok() {
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.eat(b':')?;
+            let value = self.value()?;
+            members.push((key, value));
+            if self.eat(b'}').is_ok() {
+                return Ok(Value::Object(members));
+            }
+            self.eat(b',')?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_documents() {
+        let value = parse(r#" {"a": [1, -2.5e1, true, null], "b": {"c": "d\"é😀"}} "#);
+        assert_eq!(
+            value,
+            Ok(Value::Object(vec![
+                (
+                    "a".into(),
+                    Value::Array(vec![
+                        Value::Number(1.0),
+                        Value::Number(-25.0),
+                        Value::Bool(true),
+                        Value::Null
+                    ])
+                ),
+                (
+                    "b".into(),
+                    Value::Object(vec![("c".into(), Value::String("d\"é😀".into()))])
+                ),
+            ]))
+        );
+    }
+
+    #[test]
+    fn decodes_unicode_escapes() {
+        let value = parse(r#""\u00e9\ud83d\ude00\n""#);
+        assert_eq!(value, Ok(Value::String("é😀\n".into())));
+    }
+
+    #[test]
+    fn reports_where_parsing_failed() {
+        assert_eq!(parse(r#"{"a": }"#), Err(6));
+        assert_eq!(parse("[1] 2"), Err(4));
+        assert_eq!(parse(r#""open"#), Err(5));
+    }
+
+    #[test]
+    fn quote_escapes_control_characters() {
+        assert_eq!(quote("a\"\\\n\u{1}"), r#""a\"\\\n\u0001""#);
+    }
+}
@@ -0,0 +1,166 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::time::Duration;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::json::quote;
+use crate::telemetry::TelemetrySink;
+use crate::transition::Transition;
+use crate::StateMachineError;
+
+/// Writes every transition and rejection as one line of JSON, for log
+/// pipelines which expect structured events.  Attach it with
+/// [`crate::StateMachine::add_telemetry_sink`].
+///
+/// ```text
+/// {"timestamp_ms":1700000000000,"machine":"door","event":"transition","source":"Closed","trigger":"Open","destination":"Opened","duration_us":3}
+/// {"timestamp_ms":1700000000003,"machine":"door","event":"transition","source":"Opened","trigger":"Close","destination":"Closed","annotations":["draught"],"duration_us":2}
+/// {"timestamp_ms":1700000000005,"machine":"door","event":"rejected","source":"Opened","trigger":"Lock","error":"trigger Lock not permitted for Opened","duration_us":1}
+/// ```
+///
+/// States and triggers are written with their `Debug` names, with a `null`
+/// trigger for a transition the machine made without one, and durations
+/// are measured by the machine's [`crate::Clock`].  Transitions carry their
+/// [`Transition::label`] and [`Transition::annotations`] when they have them.  Logging never fails a
+/// fire, so write errors are dropped.
+pub struct JsonLogSink<'a> {
+    machine: String,
+    writer: Box<dyn Write + 'a>,
+}
+
+impl<'a> JsonLogSink<'a> {
+    /// Log to `writer`, naming the machine `machine` on every line
+    pub fn new(machine: impl Into<String>, writer: impl Write + 'a) -> Self {
+        Self {
+            machine: machine.into(),
+            writer: Box::new(writer),
+        }
+    }
+
+    fn write(&mut self, fields: &str, duration: Duration) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let _ = writeln!(
+            self.writer,
+            "{{\"timestamp_ms\":{timestamp},\"machine\":{},{fields},\"duration_us\":{}}}",
+            quote(&self.machine),
+            duration.as_micros(),
+        );
+    }
+}
+
+impl<S: Debug, T: Debug> TelemetrySink<S, T> for JsonLogSink<'_> {
+    fn transition_committed(&mut self, transition: &Transition<S, T>, duration: Duration) {
+        let mut fields = format!(
+            "\"event\":\"transition\",\"source\":{},\"trigger\":{},\"destination\":{}",
+            quote(&format!("{:?}", transition.source)),
+            transition
+                .trigger
+                .as_ref()
+                .map_or_else(|| String::from("null"), |t| quote(&format!("{t:?}"))),
+            quote(&format!("{:?}", transition.destination)),
+        );
+        if let Some(label) = transition.label() {
+            fields.push_str(&format!(",\"label\":{}", quote(label)));
+        }
+        let annotations = transition.annotations();
+        if !annotations.is_empty() {
+            let quoted: Vec<_> = annotations.iter().map(|a| quote(a)).collect();
+            fields.push_str(&format!(",\"annotations\":[{}]", quoted.join(",")));
+        }
+        self.write(&fields, duration);
+    }
+
+    fn fire_rejected(
+        &mut self,
+        source: S,
+        trigger: T,
+        error: &StateMachineError<S, T>,
+        duration: Duration,
+    ) {
+        let fields = format!(
+            "\"event\":\"rejected\",\"source\":{},\"trigger\":{},\"error\":{}",
+            quote(&format!("{source:?}")),
+            quote(&format!("{trigger:?}")),
+            quote(&format!("{error}")),
+        );
+        self.write(&fields, duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn logs_transitions_and_rejections_as_json_lines() -> eyre::Result<()> {
+        let mut output = Vec::new();
+        {
+            let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+            builder
+                .config(State::State1)
+                .permit(Trigger::Trig, State::State2);
+            let mut machine = builder.build(())?;
+            machine.add_telemetry_sink(JsonLogSink::new("test", &mut output));
+            machine.fire(Trigger::Trig)?;
+            assert!(machine.fire(Trigger::Trig).is_err());
+        }
+
+        let output = String::from_utf8(output)?;
+        let lines: Vec<_> = output
+            .lines()
+            .map(|line| {
+                // Timestamps and durations differ between runs
+                let start = line.find(",\"machine\"").unwrap();
+                let end = line.find(",\"duration_us\"").unwrap();
+                &line[start..end]
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ",\"machine\":\"test\",\"event\":\"transition\",\"source\":\"State1\",\"trigger\":\"Trig\",\"destination\":\"State2\"",
+                ",\"machine\":\"test\",\"event\":\"rejected\",\"source\":\"State2\",\"trigger\":\"Trig\",\"error\":\"trigger Trig not permitted for State2\"",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn logs_labels_and_annotations() -> eyre::Result<()> {
+        let mut output = Vec::new();
+        {
+            let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+            builder
+                .config(State::State1)
+                .permit_if(
+                    Trigger::Trig,
+                    State::State2,
+                    crate::Guard::annotated("always", |_| Some(String::from("by guard"))),
+                )
+                .label("go");
+            builder
+                .config(State::State2)
+                .on_entry(|t, _| t.annotate("by \"action\""));
+            let mut machine = builder.build(())?;
+            machine.add_telemetry_sink(JsonLogSink::new("test", &mut output));
+            machine.fire(Trigger::Trig)?;
+            let transition = machine.last_transition().unwrap();
+            assert_eq!(*transition.annotations(), ["by guard", "by \"action\""]);
+        }
+
+        let output = String::from_utf8(output)?;
+        assert!(output.contains(",\"label\":\"go\","));
+        assert!(output.contains(",\"annotations\":[\"by guard\",\"by \\\"action\\\"\"],"));
+        Ok(())
+    }
+}
@@ -1,24 +1,208 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
-#![allow(unused_imports)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+mod action;
+#[cfg(feature = "alloc")]
+#[cfg(feature = "alloc")]
 mod builder;
+#[cfg(feature = "alloc")]
+mod bulk_summary;
+#[cfg(feature = "alloc")]
+mod clock;
+#[cfg(feature = "alloc")]
+mod command;
+#[cfg(feature = "alloc")]
+mod complexity;
+#[cfg(feature = "alloc")]
+mod config_visitor;
+mod const_table;
+#[cfg(feature = "alloc")]
+mod coverage;
+#[cfg(feature = "std")]
+mod debug_console;
+#[cfg(feature = "alloc")]
+mod debugger;
+#[cfg(feature = "alloc")]
+mod dot;
+#[cfg(feature = "alloc")]
+mod equivalence;
+#[cfg(feature = "alloc")]
+mod guard;
+mod hasher;
+#[cfg(feature = "heapless")]
+mod heapless_builder;
+#[cfg(feature = "heapless")]
+mod heapless_error;
+#[cfg(feature = "heapless")]
+mod heapless_machine;
+#[cfg(feature = "alloc")]
+mod intern;
+#[cfg(feature = "alloc")]
+mod json;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod json_log;
+#[cfg(feature = "alloc")]
+mod lifecycle;
+#[cfg(feature = "alloc")]
+mod log_violation;
+#[cfg(feature = "alloc")]
+mod machine_bus;
+#[cfg(feature = "alloc")]
+mod memory_stats;
+#[cfg(feature = "alloc")]
+mod mermaid;
+#[cfg(feature = "alloc")]
+mod metadata;
+#[cfg(feature = "alloc")]
+mod middleware;
+#[cfg(feature = "std")]
+mod model_checker;
+#[cfg(feature = "alloc")]
+mod node_style;
+#[cfg(feature = "alloc")]
+mod parameter_type;
+#[cfg(feature = "alloc")]
+mod pause;
+#[cfg(feature = "alloc")]
+mod reconfiguration;
+#[cfg(feature = "alloc")]
+mod schema_export;
+#[cfg(feature = "alloc")]
+mod script;
+#[cfg(feature = "alloc")]
+mod small_vec;
+#[cfg(feature = "alloc")]
 mod state_config;
+#[cfg(feature = "alloc")]
 mod state_machine;
+#[cfg(feature = "alloc")]
+mod state_machine_info;
+#[cfg(feature = "alloc")]
 mod state_representation;
+#[cfg(feature = "alloc")]
 mod statemachine_error;
+#[cfg(feature = "alloc")]
+mod storage;
+#[cfg(feature = "alloc")]
+mod suggest;
+#[cfg(feature = "alloc")]
+mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "alloc")]
+mod trace;
+#[cfg(feature = "alloc")]
+mod trace_error;
 mod transition;
+#[cfg(feature = "alloc")]
 mod transition_event;
+#[cfg(feature = "alloc")]
+mod transition_table;
+#[cfg(feature = "alloc")]
 mod trigger_behaviour;
+#[cfg(feature = "alloc")]
+mod trigger_map;
+#[cfg(feature = "alloc")]
+mod xstate;
+#[cfg(feature = "alloc")]
+mod xstate_error;
 
+#[cfg(feature = "alloc")]
+pub use action::{ActionInfo, EntryAction, ExitAction, StaticActions};
+#[cfg(feature = "alloc")]
 pub use builder::StateMachineBuilder;
+#[cfg(feature = "alloc")]
+pub use bulk_summary::BulkSummary;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use clock::SystemClock;
+#[cfg(feature = "alloc")]
+pub use clock::{Clock, ManualClock};
+#[cfg(feature = "alloc")]
+pub use command::Command;
+#[cfg(feature = "alloc")]
+pub use complexity::{ComplexityReport, ComplexityThresholds};
+#[cfg(feature = "alloc")]
+pub use config_visitor::ConfigVisitor;
+pub use const_table::ConstTransitionTable;
+#[cfg(feature = "alloc")]
+pub use coverage::{CoverageReport, EdgeCoverage};
+#[cfg(feature = "std")]
+pub use debug_console::DebugConsole;
+#[cfg(feature = "alloc")]
+pub use debugger::{Breakpoint, DebugEvent, DebugPhase};
+#[cfg(feature = "alloc")]
+pub use equivalence::Divergence;
+#[cfg(feature = "alloc")]
+pub use guard::Guard;
+pub use hasher::{DefaultHashBuilder, FxBuildHasher, FxHasher};
+#[cfg(feature = "heapless")]
+pub use heapless_builder::HeaplessBuilder;
+#[cfg(feature = "heapless")]
+pub use heapless_error::HeaplessError;
+#[cfg(feature = "heapless")]
+pub use heapless_machine::{HeaplessAction, HeaplessGuard, HeaplessStateMachine};
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use json_log::JsonLogSink;
+#[cfg(feature = "alloc")]
+pub use lifecycle::Lifecycle;
+#[cfg(feature = "alloc")]
+pub use log_violation::LogViolation;
+#[cfg(feature = "alloc")]
+pub use machine_bus::MachineBus;
+#[cfg(feature = "alloc")]
+pub use memory_stats::MemoryStats;
+#[cfg(feature = "alloc")]
+pub use metadata::Metadata;
+#[cfg(feature = "alloc")]
+pub use middleware::{Middleware, Next};
+#[cfg(feature = "std")]
+pub use model_checker::{Counterexample, ModelCheckReport, ModelChecker};
+#[cfg(feature = "alloc")]
+pub use node_style::NodeStyle;
+#[cfg(feature = "alloc")]
+pub use pause::PauseMode;
+#[cfg(feature = "alloc")]
+pub use reconfiguration::Reconfiguration;
+#[cfg(feature = "alloc")]
+pub use script::Script;
+#[cfg(feature = "alloc")]
+pub use state_config::StateConfig;
+#[cfg(feature = "alloc")]
 pub use state_machine::StateMachine;
+#[cfg(feature = "alloc")]
+pub use state_machine_info::{StateInfo, StateMachineInfo, TransitionInfo};
+#[cfg(feature = "alloc")]
 pub use statemachine_error::StateMachineError;
+#[cfg(feature = "alloc")]
+pub use storage::{DefaultStorage, Owned, Storage};
+#[cfg(feature = "alloc")]
+pub use telemetry::TelemetrySink;
+#[cfg(feature = "alloc")]
+pub use trace::{TraceOutcome, TraceRecorder, TraceReplayer, TraceStep};
+#[cfg(feature = "alloc")]
+pub use trace_error::TraceError;
 pub use transition::Transition;
-pub use transition_event::TransitionEventHandler;
+#[cfg(feature = "alloc")]
+pub use transition_event::{Subscription, SubscriptionId, TransitionEventHandler};
+#[cfg(feature = "alloc")]
+pub use transition_table::TransitionTable;
+#[cfg(feature = "alloc")]
+pub use xstate_error::XStateError;
 
 #[cfg(test)]
 mod tests {
-    use strum_macros::EnumIter;
+    use strum_macros::{EnumIter, EnumString};
 
     #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, EnumIter)]
     pub enum State {
@@ -26,7 +210,7 @@ mod tests {
         State2,
     }
 
-    #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, EnumString)]
     pub enum Trigger {
         Trig,
         Trig2,
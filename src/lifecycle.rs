@@ -0,0 +1,10 @@
+/// Where a machine is in its life, see [`crate::StateMachine::lifecycle`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    /// Built, but neither started nor fired yet
+    NotStarted,
+    /// Started with [`crate::StateMachine::start`] or fired at least once
+    Running,
+    /// Stopped with [`crate::StateMachine::stop`]; every trigger is rejected
+    Stopped,
+}
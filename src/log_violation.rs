@@ -0,0 +1,59 @@
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+/// The first step of a transition log which the configuration doesn't
+/// allow, see [`crate::StateMachineInfo::verify_log`].  Steps count from 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogViolation<S, T> {
+    /// The step doesn't start where the one before it ended
+    Discontinuous { step: usize, expected: S, source: S },
+    /// `source` has no behaviour for `trigger`
+    NotPermitted { step: usize, source: S, trigger: T },
+    /// `trigger` can't take `source` to `destination`, only to `permitted`
+    WrongDestination {
+        step: usize,
+        source: S,
+        trigger: T,
+        destination: S,
+        permitted: Vec<S>,
+    },
+}
+
+impl<S, T> LogViolation<S, T> {
+    pub fn step(&self) -> usize {
+        match self {
+            Self::Discontinuous { step, .. }
+            | Self::NotPermitted { step, .. }
+            | Self::WrongDestination { step, .. } => *step,
+        }
+    }
+}
+
+impl<S: Debug, T: Debug> Display for LogViolation<S, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Discontinuous {
+                step,
+                expected,
+                source,
+            } => write!(f, "step {step} starts in {source:?} rather than {expected:?}"),
+            Self::NotPermitted {
+                step,
+                source,
+                trigger,
+            } => write!(f, "step {step}: trigger {trigger:?} not permitted for {source:?}"),
+            Self::WrongDestination {
+                step,
+                source,
+                trigger,
+                destination,
+                permitted,
+            } => write!(
+                f,
+                "step {step}: trigger {trigger:?} takes {source:?} to {permitted:?}, not {destination:?}"
+            ),
+        }
+    }
+}
+
+impl<S: Debug, T: Debug> core::error::Error for LogViolation<S, T> {}
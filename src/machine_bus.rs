@@ -0,0 +1,249 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt::Debug;
+use core::hash::{BuildHasher, Hash};
+
+use crate::action::StaticActions;
+use crate::storage::Storage;
+use crate::transition::Transition;
+use crate::{StateMachine, StateMachineError};
+
+/// Maps a published transition, as `&Transition<S, T>`, to a trigger for
+/// another machine
+type Rule = Box<dyn Fn(&dyn Any) -> Option<Box<dyn Any>>>;
+
+struct Route {
+    from: String,
+    to: String,
+    rule: Rule,
+}
+
+#[derive(Default)]
+struct Bus {
+    routes: Vec<Route>,
+    /// Triggers waiting to be delivered, by machine name
+    inboxes: Vec<(String, VecDeque<Box<dyn Any>>)>,
+}
+
+impl Bus {
+    fn inbox(&mut self, name: &str) -> &mut VecDeque<Box<dyn Any>> {
+        let index = match self.inboxes.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.inboxes.push((String::from(name), VecDeque::new()));
+                self.inboxes.len() - 1
+            }
+        };
+        &mut self.inboxes[index].1
+    }
+}
+
+/// Routes transitions of one machine to triggers on others, so machines can
+/// react to each other without wiring a channel for every pair.
+///
+/// Machines are named on the bus.  A machine publishes its transitions by
+/// registering [`Self::publisher`] as a transition handler, rules added with
+/// [`Self::route`] turn them into triggers for other machines, and
+/// [`Self::deliver`] fires the triggers waiting for a machine.  The bus is a
+/// cheap handle: clones share the same routes and inboxes.
+///
+/// ```
+/// # use stateless_rs::{MachineBus, StateMachineBuilder};
+/// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// enum Payment { Pending, Captured }
+/// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// enum PaymentTrigger { Capture }
+/// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// enum Order { Open, Paid }
+/// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// enum OrderTrigger { PaymentReceived }
+///
+/// let bus = MachineBus::new();
+/// bus.route("payment", "order", |t: &stateless_rs::Transition<Payment, PaymentTrigger>| {
+///     (t.destination == Payment::Captured).then_some(OrderTrigger::PaymentReceived)
+/// });
+///
+/// let mut payment = StateMachineBuilder::<_, _, ()>::new(Payment::Pending);
+/// payment
+///     .config(Payment::Pending)
+///     .permit(PaymentTrigger::Capture, Payment::Captured);
+/// payment.on_transitioned(bus.publisher("payment"));
+/// let mut payment = payment.build(()).unwrap();
+///
+/// let mut order = StateMachineBuilder::<_, _, ()>::new(Order::Open);
+/// order
+///     .config(Order::Open)
+///     .permit(OrderTrigger::PaymentReceived, Order::Paid);
+/// let mut order = order.build(()).unwrap();
+///
+/// payment.fire(PaymentTrigger::Capture).unwrap();
+/// assert_eq!(bus.pending("order"), 1);
+/// bus.deliver("order", &mut order).unwrap();
+/// assert_eq!(order.state(), Order::Paid);
+/// ```
+#[derive(Clone, Default)]
+pub struct MachineBus {
+    bus: Rc<RefCell<Bus>>,
+}
+
+impl MachineBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A transition handler which publishes the transitions of the machine
+    /// named `name`.  Register it with
+    /// [`crate::StateMachineBuilder::on_transitioned`].
+    pub fn publisher<S, T>(&self, name: impl Into<String>) -> impl FnMut(&Transition<S, T>)
+    where
+        S: 'static,
+        T: 'static,
+    {
+        let bus = Rc::clone(&self.bus);
+        let name = name.into();
+        move |transition| {
+            let mut bus = bus.borrow_mut();
+            let routed: Vec<_> = bus
+                .routes
+                .iter()
+                .filter(|route| route.from == name)
+                .filter_map(|route| Some((route.to.clone(), (route.rule)(transition)?)))
+                .collect();
+            for (to, trigger) in routed {
+                bus.inbox(&to).push_back(trigger);
+            }
+        }
+    }
+
+    /// When the machine named `from` publishes a transition for which `rule`
+    /// returns a trigger, queue it for the machine named `to`.  Transitions
+    /// of other types than the rule takes are ignored.
+    pub fn route<S, T, T2, F>(&self, from: impl Into<String>, to: impl Into<String>, rule: F)
+    where
+        S: 'static,
+        T: 'static,
+        T2: 'static,
+        F: Fn(&Transition<S, T>) -> Option<T2> + 'static,
+    {
+        self.bus.borrow_mut().routes.push(Route {
+            from: from.into(),
+            to: to.into(),
+            rule: Box::new(move |transition| {
+                let trigger = rule(transition.downcast_ref()?)?;
+                Some(Box::new(trigger) as Box<dyn Any>)
+            }),
+        });
+    }
+
+    /// How many triggers are waiting for the machine named `name`
+    pub fn pending(&self, name: &str) -> usize {
+        self.bus
+            .borrow()
+            .inboxes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map_or(0, |(_, inbox)| inbox.len())
+    }
+
+    /// Fire every trigger waiting for the machine named `name` on `machine`,
+    /// in the order they were routed, including any routed to it while
+    /// doing so.  A rejected trigger does not stop the rest; the first
+    /// rejection is returned once they have all been fired.
+    pub fn deliver<'a, S, T, O, St, A, H>(
+        &self,
+        name: &str,
+        machine: &mut StateMachine<'a, S, T, O, St, A, H>,
+    ) -> Result<(), StateMachineError<S, T>>
+    where
+        S: Debug + Copy + Eq + Hash + 'static,
+        T: Debug + Copy + Eq + Hash + 'static,
+        O: Debug,
+        St: Storage<O>,
+        A: StaticActions<S, T, O>,
+        H: BuildHasher + Default,
+    {
+        let mut first_error = None;
+        // The borrow must end before firing, which may publish
+        while let Some(trigger) = self.next_trigger::<T>(name) {
+            if let Err(error) = machine.fire(trigger) {
+                first_error.get_or_insert(error);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn next_trigger<T: Copy + 'static>(&self, name: &str) -> Option<T> {
+        let mut bus = self.bus.borrow_mut();
+        let inbox = bus.inbox(name);
+        let position = inbox.iter().position(|t| t.is::<T>())?;
+        inbox.remove(position)?.downcast_ref().copied()
+    }
+}
+
+impl Debug for MachineBus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let bus = self.bus.borrow();
+        f.debug_struct("MachineBus")
+            .field("routes", &bus.routes.len())
+            .field(
+                "pending",
+                &bus.inboxes
+                    .iter()
+                    .map(|(name, inbox)| (name, inbox.len()))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    #[test]
+    fn routes_transitions_between_machines() -> eyre::Result<()> {
+        let bus = MachineBus::new();
+        bus.route("a", "b", |t: &Transition<State, Trigger>| {
+            (t.destination == State::State2).then_some(Trigger::Trig)
+        });
+        bus.route("b", "a", |t: &Transition<State, Trigger>| {
+            (t.destination == State::State2).then_some(Trigger::Trig2)
+        });
+        let mut a = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        a.config(State::State1).permit(Trigger::Trig, State::State2);
+        a.config(State::State2)
+            .permit(Trigger::Trig2, State::State1);
+        a.on_transitioned(bus.publisher("a"));
+        let mut a = a.build(())?;
+        let mut b = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        b.config(State::State1).permit(Trigger::Trig, State::State2);
+        b.on_transitioned(bus.publisher("b"));
+        let mut b = b.build(())?;
+
+        a.fire(Trigger::Trig)?;
+        assert_eq!((bus.pending("a"), bus.pending("b")), (0, 1));
+        bus.deliver("b", &mut b)?;
+        assert_eq!(b.state(), State::State2);
+        assert_eq!((bus.pending("a"), bus.pending("b")), (1, 0));
+        bus.deliver("a", &mut a)?;
+        assert_eq!(a.state(), State::State1);
+
+        a.fire(Trigger::Trig)?;
+        assert_eq!(
+            bus.deliver("b", &mut b),
+            Err(StateMachineError::TriggerNotPermitted {
+                state: State::State2,
+                trigger: Trigger::Trig
+            })
+        );
+        assert_eq!(bus.pending("b"), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use core::ops::AddAssign;
+
+/// Counts and approximate sizes of what a [`crate::StateMachine`] holds,
+/// from [`crate::StateMachine::memory_stats`].
+///
+/// Sizes are in bytes and include the heap allocations each part owns, such
+/// as closures and descriptions.  Allocator overhead and anything captured
+/// by reference is not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub representations: usize,
+    pub behaviours: usize,
+    /// Entry, first entry, exit and internal actions
+    pub actions: usize,
+    pub machine_bytes: usize,
+    pub representation_bytes: usize,
+    pub behaviour_bytes: usize,
+    pub action_bytes: usize,
+}
+
+impl MemoryStats {
+    pub fn total_bytes(&self) -> usize {
+        self.machine_bytes + self.representation_bytes + self.behaviour_bytes + self.action_bytes
+    }
+}
+
+impl AddAssign for MemoryStats {
+    fn add_assign(&mut self, other: Self) {
+        self.representations += other.representations;
+        self.behaviours += other.behaviours;
+        self.actions += other.actions;
+        self.machine_bytes += other.machine_bytes;
+        self.representation_bytes += other.representation_bytes;
+        self.behaviour_bytes += other.behaviour_bytes;
+        self.action_bytes += other.action_bytes;
+    }
+}
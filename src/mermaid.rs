@@ -0,0 +1,79 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Debug;
+use core::fmt::Write;
+
+use crate::dot::{action_names, edge_label};
+use crate::state_machine_info::StateMachineInfo;
+use crate::transition::Transition;
+
+/// A Mermaid state id for `state`, which may only contain word characters
+fn state_id<S: Debug>(state: S) -> String {
+    format!("{:?}", state)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Render the states and transitions of a machine as a Mermaid state
+/// diagram with `current` highlighted.
+///
+/// Mermaid can't style individual transitions, so the one `last` was taken
+/// along is marked in its label instead.
+pub(crate) fn render_live<S, T>(
+    info: &StateMachineInfo<S, T>,
+    current: S,
+    last: Option<&Transition<S, T>>,
+) -> String
+where
+    S: Copy + Eq + Debug,
+    T: Copy + Eq + Debug,
+{
+    let mut mermaid = String::from("stateDiagram-v2\n");
+    for state_info in &info.states {
+        let id = state_id(state_info.state);
+        let _ = writeln!(mermaid, "    state \"{:?}\" as {}", state_info.state, id);
+        if !state_info.entry_actions.is_empty() {
+            let entry = action_names(&state_info.entry_actions);
+            let _ = writeln!(mermaid, "    {} : entry / {}", id, entry);
+        }
+        if !state_info.exit_actions.is_empty() {
+            let exit = action_names(&state_info.exit_actions);
+            let _ = writeln!(mermaid, "    {} : exit / {}", id, exit);
+        }
+    }
+    for state_info in &info.states {
+        let state = state_info.state;
+        for transition in &state_info.transitions {
+            let mut label = edge_label(transition);
+            if let Some(guard) = &transition.guard_description {
+                let _ = write!(label, " [{}]", guard);
+            }
+            if !transition.actions.is_empty() {
+                let _ = write!(label, " / {}", action_names(&transition.actions));
+            }
+            let destination = transition.destination_from(state);
+            let taken = last.is_some_and(|t| {
+                t.source == state
+                    && t.trigger == Some(transition.trigger)
+                    && t.destination == destination
+            });
+            if taken {
+                label.push_str(" (last)");
+            }
+            let _ = writeln!(
+                mermaid,
+                "    {} --> {} : {}",
+                state_id(state),
+                state_id(destination),
+                label
+            );
+        }
+    }
+    let _ = writeln!(
+        mermaid,
+        "    classDef current fill:#add8e6,font-weight:bold"
+    );
+    let _ = writeln!(mermaid, "    class {} current", state_id(current));
+    mermaid
+}
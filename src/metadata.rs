@@ -0,0 +1,47 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Human readable information attached to a state or a transition with
+/// [`crate::StateConfig::describe`], [`crate::StateConfig::tag`] and their
+/// `_transition` counterparts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub description: Option<String>,
+    /// Tags in the order they were added, without duplicates
+    pub tags: Vec<String>,
+}
+
+impl Metadata {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub(crate) fn add_tag(&mut self, tag: impl Into<String>) {
+        let tag = tag.into();
+        if !self.has_tag(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.description.as_ref().map_or(0, String::capacity)
+            + self.tags.capacity() * core::mem::size_of::<String>()
+            + self.tags.iter().map(String::capacity).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_not_duplicated() {
+        let mut metadata = Metadata::default();
+        metadata.add_tag("billing");
+        metadata.add_tag("slow");
+        metadata.add_tag("billing");
+        assert_eq!(metadata.tags, vec!["billing", "slow"]);
+        assert!(metadata.has_tag("slow"));
+        assert!(!metadata.has_tag("fast"));
+    }
+}
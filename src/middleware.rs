@@ -0,0 +1,98 @@
+use crate::StateMachineError;
+use alloc::boxed::Box;
+
+/// Wraps the processing of every fired trigger.
+///
+/// Middlewares run in the order they were added to the
+/// [`crate::StateMachineBuilder`].  Each one decides whether (and how many
+/// times) to pass the trigger on with [`Next::run`], which makes it the place
+/// for cross-cutting concerns such as authorization, logging and retries.
+///
+/// Closures of the form `FnMut(T, &mut Next<S, T>) -> Result<(), _>` are
+/// middlewares too.
+pub trait Middleware<S, T> {
+    fn call(
+        &mut self,
+        trigger: T,
+        next: &mut Next<'_, S, T>,
+    ) -> Result<(), StateMachineError<S, T>>;
+}
+
+impl<S, T, F> Middleware<S, T> for F
+where
+    F: FnMut(T, &mut Next<'_, S, T>) -> Result<(), StateMachineError<S, T>>,
+{
+    fn call(
+        &mut self,
+        trigger: T,
+        next: &mut Next<'_, S, T>,
+    ) -> Result<(), StateMachineError<S, T>> {
+        self(trigger, next)
+    }
+}
+
+pub(crate) type BoxedMiddleware<S, T> = Box<dyn Middleware<S, T>>;
+
+type Fire<'a, S, T> = dyn FnMut(T) -> Result<(), StateMachineError<S, T>> + 'a;
+
+/// The rest of the middleware pipeline, ending with the state machine itself
+pub struct Next<'a, S, T> {
+    middlewares: &'a mut [BoxedMiddleware<S, T>],
+    fire: &'a mut Fire<'a, S, T>,
+}
+
+impl<'a, S, T> Next<'a, S, T> {
+    pub(crate) fn new(
+        middlewares: &'a mut [BoxedMiddleware<S, T>],
+        fire: &'a mut Fire<'a, S, T>,
+    ) -> Self {
+        Self { middlewares, fire }
+    }
+
+    /// Pass `trigger` to the next middleware, or to the state machine if this
+    /// is the last one
+    pub fn run(&mut self, trigger: T) -> Result<(), StateMachineError<S, T>> {
+        match self.middlewares.split_first_mut() {
+            Some((middleware, rest)) => {
+                let mut next = Next {
+                    middlewares: rest,
+                    fire: &mut *self.fire,
+                };
+                middleware.call(trigger, &mut next)
+            }
+            None => (self.fire)(trigger),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn middlewares_run_in_order_before_fire() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (log1, log2, log3) = (Rc::clone(&log), Rc::clone(&log), Rc::clone(&log));
+        let mut middlewares: Vec<BoxedMiddleware<State, Trigger>> = vec![
+            Box::new(move |t, next: &mut Next<'_, State, Trigger>| {
+                log1.borrow_mut().push("first");
+                next.run(t)
+            }),
+            Box::new(move |t, next: &mut Next<'_, State, Trigger>| {
+                log2.borrow_mut().push("second");
+                next.run(t)
+            }),
+        ];
+        let mut fire = move |_t| {
+            log3.borrow_mut().push("fire");
+            Ok(())
+        };
+        Next::new(&mut middlewares, &mut fire)
+            .run(Trigger::Trig)
+            .unwrap();
+        assert_eq!(*log.borrow(), vec!["first", "second", "fire"]);
+    }
+}
@@ -0,0 +1,218 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::hash::{BuildHasher, Hash};
+use std::collections::HashSet;
+
+use crate::action::StaticActions;
+use crate::hasher::DefaultHashBuilder;
+use crate::storage::{DefaultStorage, Storage};
+use crate::StateMachine;
+
+type Builder<'a, S, T, O, St, A, H> = Box<dyn Fn() -> StateMachine<'a, S, T, O, St, A, H> + 'a>;
+type Abstraction<'a, O, K> = Box<dyn Fn(&O) -> K + 'a>;
+type Property<'a, S, O> = (String, Box<dyn Fn(S, &O) -> bool + 'a>);
+
+/// A shortest trigger sequence from the initial configuration to one
+/// breaking a property
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample<S, T> {
+    pub property: String,
+    pub triggers: Vec<T>,
+    pub state: S,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelCheckReport<S, T> {
+    /// Distinct configurations reached
+    pub configurations: usize,
+    /// `false` if a limit stopped exploration before every reachable
+    /// configuration was checked
+    pub exhaustive: bool,
+    pub counterexample: Option<Counterexample<S, T>>,
+}
+
+/// Exhaustively explores every configuration a machine can reach, checking
+/// safety properties in each.
+///
+/// A configuration is the current state plus an abstraction of the state
+/// object, such as the fields guards look at.  Exploration is breadth
+/// first over [`StateMachine::permitted_triggers`], so a counterexample is
+/// a shortest path.  Machines can't be cloned, so every configuration is
+/// reached by replaying its triggers on a fresh machine from `build`; keep
+/// actions free of outside side effects.
+pub struct ModelChecker<'a, S, T, O, K, St = DefaultStorage<O>, A = (), H = DefaultHashBuilder> {
+    build: Builder<'a, S, T, O, St, A, H>,
+    abstraction: Abstraction<'a, O, K>,
+    properties: Vec<Property<'a, S, O>>,
+    max_depth: usize,
+    max_configurations: usize,
+}
+
+impl<'a, S, T, O, K, St, A, H> ModelChecker<'a, S, T, O, K, St, A, H>
+where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    K: Eq + Hash,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+{
+    /// `build` must return an identical machine in its initial state every
+    /// time it is called
+    pub fn new<B, F>(build: B, abstraction: F) -> Self
+    where
+        B: Fn() -> StateMachine<'a, S, T, O, St, A, H> + 'a,
+        F: Fn(&O) -> K + 'a,
+    {
+        Self {
+            build: Box::new(build),
+            abstraction: Box::new(abstraction),
+            properties: Vec::new(),
+            max_depth: usize::MAX,
+            max_configurations: 100_000,
+        }
+    }
+
+    /// Require `holds` in every reachable configuration
+    pub fn property<F>(mut self, name: impl Into<String>, holds: F) -> Self
+    where
+        F: Fn(S, &O) -> bool + 'a,
+    {
+        self.properties.push((name.into(), Box::new(holds)));
+        self
+    }
+
+    /// Don't explore sequences longer than `depth` triggers
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Stop after this many configurations, 100,000 by default
+    pub fn max_configurations(mut self, configurations: usize) -> Self {
+        self.max_configurations = configurations;
+        self
+    }
+
+    pub fn explore(&self) -> ModelCheckReport<S, T> {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([Vec::new()]);
+        let mut exhaustive = true;
+        while let Some(triggers) = queue.pop_front() {
+            let Some(machine) = self.replay(&triggers) else {
+                continue;
+            };
+            let state = machine.state();
+            let object = machine.object();
+            if !seen.insert((state, (self.abstraction)(&object))) {
+                continue;
+            }
+            if let Some((name, _)) = self
+                .properties
+                .iter()
+                .find(|(_, holds)| !holds(state, &object))
+            {
+                return ModelCheckReport {
+                    configurations: seen.len(),
+                    exhaustive: false,
+                    counterexample: Some(Counterexample {
+                        property: name.clone(),
+                        triggers,
+                        state,
+                    }),
+                };
+            }
+            drop(object);
+            if seen.len() >= self.max_configurations {
+                exhaustive = false;
+                break;
+            }
+            if triggers.len() >= self.max_depth {
+                exhaustive &= machine.permitted_triggers().is_empty();
+                continue;
+            }
+            for trigger in machine.permitted_triggers() {
+                let mut next = triggers.clone();
+                next.push(trigger);
+                queue.push_back(next);
+            }
+        }
+        ModelCheckReport {
+            configurations: seen.len(),
+            exhaustive,
+            counterexample: None,
+        }
+    }
+
+    /// A fresh machine after `triggers`, or `None` if one was rejected
+    fn replay(&self, triggers: &[T]) -> Option<StateMachine<'a, S, T, O, St, A, H>> {
+        let mut machine = (self.build)();
+        for trigger in triggers {
+            machine.fire(*trigger).ok()?;
+        }
+        Some(machine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    /// Counts up in State2 and back to State1, refusing once `count` hits 3
+    fn counter<'a>() -> StateMachine<'a, State, Trigger, u32> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(Trigger::Trig, State::State2, |count: &u32| *count < 3);
+        builder
+            .config(State::State2)
+            .on_entry(|_, count| *count += 1)
+            .permit(Trigger::Trig, State::State1);
+        builder.build(0).expect("the machine is valid")
+    }
+
+    #[test]
+    fn explores_every_configuration() {
+        let report = ModelChecker::new(counter, |count| *count)
+            .property("count stays small", |_, count| *count <= 3)
+            .explore();
+        assert_eq!(
+            report,
+            ModelCheckReport {
+                configurations: 7,
+                exhaustive: true,
+                counterexample: None,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_shortest_counterexample() {
+        let report = ModelChecker::new(counter, |count| *count)
+            .property("never counts twice", |_, count| *count < 2)
+            .explore();
+        assert_eq!(
+            report.counterexample,
+            Some(Counterexample {
+                property: "never counts twice".into(),
+                triggers: vec![Trigger::Trig; 3],
+                state: State::State2,
+            })
+        );
+    }
+
+    #[test]
+    fn limits_make_exploration_partial() {
+        let report = ModelChecker::new(counter, |count| *count)
+            .max_depth(2)
+            .explore();
+        assert_eq!(report.configurations, 3);
+        assert!(!report.exhaustive);
+    }
+}
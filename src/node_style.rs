@@ -0,0 +1,47 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Graphviz attributes for one state in [`crate::StateMachine::to_dot_with_style`].
+/// Unset attributes are left to Graphviz's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeStyle {
+    /// Outline and text colour, such as `"red"` or `"#ff0000"`
+    pub color: Option<String>,
+    pub fill_color: Option<String>,
+    /// Such as `"box"` or `"doublecircle"`
+    pub shape: Option<String>,
+}
+
+impl NodeStyle {
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    /// Also sets `style=filled`, without which Graphviz ignores the fill
+    pub fn fill_color(mut self, color: impl Into<String>) -> Self {
+        self.fill_color = Some(color.into());
+        self
+    }
+
+    pub fn shape(mut self, shape: impl Into<String>) -> Self {
+        self.shape = Some(shape.into());
+        self
+    }
+
+    /// `(name, value)` pairs for the set attributes
+    pub(crate) fn attributes(&self) -> Vec<(&'static str, &str)> {
+        let mut attributes = Vec::new();
+        if let Some(color) = &self.color {
+            attributes.push(("color", color.as_str()));
+        }
+        if let Some(fill_color) = &self.fill_color {
+            attributes.push(("style", "filled"));
+            attributes.push(("fillcolor", fill_color.as_str()));
+        }
+        if let Some(shape) = &self.shape {
+            attributes.push(("shape", shape.as_str()));
+        }
+        attributes
+    }
+}
@@ -0,0 +1,43 @@
+use core::any::{type_name, Any, TypeId};
+
+use crate::transition::Payload;
+
+/// The payload type a trigger must be fired with, see
+/// [`crate::StateMachineBuilder::set_trigger_parameters`]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ParameterType {
+    id: TypeId,
+    pub(crate) name: &'static str,
+}
+
+impl ParameterType {
+    pub(crate) fn of<P: Any>() -> Self {
+        Self {
+            id: TypeId::of::<P>(),
+            name: type_name::<P>(),
+        }
+    }
+
+    /// Firing without a payload never matches
+    pub(crate) fn accepts(&self, payload: Option<&Payload>) -> bool {
+        payload.is_some_and(|payload| (**payload).type_id() == self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn accepts_only_payloads_of_the_type() {
+        let parameters = ParameterType::of::<(String, u32)>();
+        let right: Payload = Arc::new((String::from("555"), 1_u32));
+        let wrong: Payload = Arc::new(1_u32);
+        assert!(parameters.accepts(Some(&right)));
+        assert!(!parameters.accepts(Some(&wrong)));
+        assert!(!parameters.accepts(None));
+        assert_eq!(parameters.name, "(alloc::string::String, u32)");
+    }
+}
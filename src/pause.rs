@@ -0,0 +1,10 @@
+/// What a paused machine does with the triggers it is fired, see
+/// [`crate::StateMachine::pause`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PauseMode {
+    /// Reject them with [`crate::StateMachineError::MachinePaused`]
+    #[default]
+    Reject,
+    /// Keep them, with their payloads, to fire in order on resume
+    Queue,
+}
@@ -0,0 +1,48 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+use crate::state_config::{StateConfig, WrappedStateRep};
+use crate::state_representation::StateRepresentation;
+use crate::trigger_map::TriggerMatching;
+
+/// A built machine opened up for changes by
+/// [`crate::StateMachine::reconfigure`]
+pub struct Reconfiguration<'a, S, T, O> {
+    pub(crate) states: Vec<(S, WrappedStateRep<'a, S, T, O>)>,
+    trigger_matching: TriggerMatching,
+}
+
+impl<'a, S, T, O> Reconfiguration<'a, S, T, O>
+where
+    S: Debug + Copy + Eq + Hash + 'static,
+    T: Debug + Copy + Eq + Hash + 'static,
+{
+    pub(crate) fn new(
+        states: Vec<(S, WrappedStateRep<'a, S, T, O>)>,
+        trigger_matching: TriggerMatching,
+    ) -> Self {
+        Self {
+            states,
+            trigger_matching,
+        }
+    }
+
+    /// Like [`crate::StateMachineBuilder::config`], starting from how the
+    /// state is configured now
+    pub fn config(&mut self, state: S) -> StateConfig<'a, S, T, O> {
+        let representation = match self.states.iter().position(|(s, _)| *s == state) {
+            Some(index) => &self.states[index].1,
+            None => {
+                let representation = Rc::new(RefCell::new(
+                    StateRepresentation::with_trigger_matching(state, self.trigger_matching),
+                ));
+                self.states.push((state, representation));
+                &self.states[self.states.len() - 1].1
+            }
+        };
+        StateConfig::new(Rc::clone(representation), self.trigger_matching)
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Write};
+
+use crate::json::quote;
+use crate::state_machine_info::StateMachineInfo;
+
+fn name<V: Debug>(value: &V) -> String {
+    quote(&format!("{:?}", value))
+}
+
+impl<S, T> StateMachineInfo<S, T>
+where
+    S: Copy + PartialEq + Debug,
+    T: Copy + PartialEq + Debug,
+{
+    /// `(source, trigger, destination)` names, destination `null` for
+    /// internal transitions
+    fn transition_names(&self) -> Vec<(String, String, String)> {
+        self.states
+            .iter()
+            .flat_map(|state| {
+                state.transitions.iter().map(move |t| {
+                    let destination = t.destination.map_or(String::from("null"), |d| name(&d));
+                    (name(&state.state), name(&t.trigger), destination)
+                })
+            })
+            .collect()
+    }
+
+    /// TypeScript types for the states and triggers, named `{prefix}State`
+    /// and `{prefix}Trigger`, and the transitions as a constant, so a
+    /// frontend can mirror the machine.  Names are the `Debug` output of
+    /// each state and trigger.
+    pub fn to_typescript(&self, prefix: &str) -> String {
+        let union = |names: Vec<String>| {
+            if names.is_empty() {
+                String::from("never")
+            } else {
+                names.join(" | ")
+            }
+        };
+        let states = union(self.states.iter().map(|s| name(&s.state)).collect());
+        let triggers = union(self.triggers().map(|t| name(&t)).collect());
+        let mut ts = String::new();
+        let _ = writeln!(ts, "export type {prefix}State = {states};");
+        let _ = writeln!(ts, "export type {prefix}Trigger = {triggers};");
+        let _ = writeln!(
+            ts,
+            "export const {prefix}InitialState: {prefix}State = {};",
+            name(&self.initial_state)
+        );
+        let _ = writeln!(
+            ts,
+            "export const {prefix}Transitions: ReadonlyArray<{{ source: {prefix}State; trigger: {prefix}Trigger; destination: {prefix}State | null }}> = ["
+        );
+        for (source, trigger, destination) in self.transition_names() {
+            let _ = writeln!(
+                ts,
+                "  {{ source: {source}, trigger: {trigger}, destination: {destination} }},"
+            );
+        }
+        ts.push_str("];\n");
+        ts
+    }
+
+    /// A JSON Schema titled `title` with `State`, `Trigger` and
+    /// `Transition` definitions, where a `Transition` is one of the
+    /// configured `source`, `trigger`, `destination` combinations
+    pub fn to_json_schema(&self, title: &str) -> String {
+        let states: Vec<_> = self.states.iter().map(|s| name(&s.state)).collect();
+        let triggers: Vec<_> = self.triggers().map(|t| name(&t)).collect();
+        let transitions: Vec<_> = self
+            .transition_names()
+            .into_iter()
+            .map(|(source, trigger, destination)| {
+                format!(
+                    "        {{\"type\": \"object\", \"properties\": {{\"source\": {{\"const\": {source}}}, \"trigger\": {{\"const\": {trigger}}}, \"destination\": {{\"const\": {destination}}}}}, \"required\": [\"source\", \"trigger\", \"destination\"]}}"
+                )
+            })
+            .collect();
+        let mut schema = String::from("{\n");
+        let _ = writeln!(
+            schema,
+            "  \"$schema\": \"https://json-schema.org/draft/2020-12/schema\","
+        );
+        let _ = writeln!(schema, "  \"title\": {},", quote(title));
+        schema.push_str("  \"$defs\": {\n");
+        let _ = writeln!(
+            schema,
+            "    \"State\": {{\"enum\": [{}]}},",
+            states.join(", ")
+        );
+        let _ = writeln!(
+            schema,
+            "    \"Trigger\": {{\"enum\": [{}]}},",
+            triggers.join(", ")
+        );
+        let _ = writeln!(
+            schema,
+            "    \"Transition\": {{\"oneOf\": [\n{}\n    ]}}",
+            transitions.join(",\n")
+        );
+        schema.push_str("  }\n}\n");
+        schema
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    fn info() -> eyre::Result<StateMachineInfo<State, Trigger>> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        Ok(builder.build(())?.info())
+    }
+
+    #[test]
+    fn exports_typescript() -> eyre::Result<()> {
+        assert_eq!(
+            info()?.to_typescript("Door"),
+            r#"export type DoorState = "State1" | "State2";
+export type DoorTrigger = "Trig" | "Trig2";
+export const DoorInitialState: DoorState = "State1";
+export const DoorTransitions: ReadonlyArray<{ source: DoorState; trigger: DoorTrigger; destination: DoorState | null }> = [
+  { source: "State1", trigger: "Trig", destination: "State2" },
+  { source: "State2", trigger: "Trig2", destination: null },
+];
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exports_json_schema() -> eyre::Result<()> {
+        let schema = info()?.to_json_schema("Door \"v1\"");
+        assert!(schema.contains(r#""title": "Door \"v1\"","#));
+        assert!(schema.contains(r#""State": {"enum": ["State1", "State2"]},"#));
+        assert!(schema.contains(r#""Trigger": {"enum": ["Trig", "Trig2"]},"#));
+        assert!(schema.contains(r#"{"type": "object", "properties": {"source": {"const": "State2"}, "trigger": {"const": "Trig2"}, "destination": {"const": null}}, "required": ["source", "trigger", "destination"]}"#));
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use core::fmt::Debug;
+use core::hash::{BuildHasher, Hash};
+
+use crate::action::StaticActions;
+use crate::storage::Storage;
+use crate::{StateMachine, StateMachineError, Transition};
+
+/// The triggers of a scenario or replay, fired lazily.  Returned by
+/// [`StateMachine::run_script`].
+pub struct Script<'m, 'a, S, T, O, St, A, H, I> {
+    machine: &'m mut StateMachine<'a, S, T, O, St, A, H>,
+    triggers: I,
+}
+
+impl<'m, 'a, S, T, O, St, A, H, I> Script<'m, 'a, S, T, O, St, A, H, I> {
+    pub(crate) fn new(machine: &'m mut StateMachine<'a, S, T, O, St, A, H>, triggers: I) -> Self {
+        Self { machine, triggers }
+    }
+}
+
+impl<S, T, O, St, A, H, I> Iterator for Script<'_, '_, S, T, O, St, A, H, I>
+where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+    I: Iterator<Item = T>,
+{
+    type Item = Result<Transition<S, T>, StateMachineError<S, T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let trigger = self.triggers.next()?;
+        if self.machine.is_paused() {
+            return Some(Err(StateMachineError::MachinePaused {
+                state: self.machine.state(),
+                trigger: Some(trigger),
+            }));
+        }
+        let before = self
+            .machine
+            .last_transition()
+            .and_then(Transition::sequence);
+        // Take the transition before commands make more
+        let result = self.machine.fire_without_commands(trigger).and_then(|()| {
+            match self.machine.last_transition() {
+                // A middleware may accept a trigger without passing it on
+                Some(transition) if transition.sequence() != before => Ok(transition.clone()),
+                _ => Err(StateMachineError::Rejected {
+                    trigger,
+                    reason: "no transition was made".into(),
+                }),
+            }
+        });
+        self.machine.run_commands();
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{State, Trigger};
+    use crate::{Command, StateMachineBuilder, StateMachineError};
+
+    #[test]
+    fn script_fires_lazily_and_carries_on_after_rejections() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1)
+            .on_entry_commands(|_, _| vec![Command::Fire(Trigger::Trig2)]);
+        let mut machine = builder.build(())?;
+
+        let mut script = machine.run_script([Trigger::Trig2, Trigger::Trig]);
+        assert_eq!(
+            script.next(),
+            Some(Err(StateMachineError::TriggerNotPermitted {
+                state: State::State1,
+                trigger: Trigger::Trig2
+            }))
+        );
+        let transition = script.next().unwrap()?;
+        assert_eq!(
+            (transition.source, transition.destination),
+            (State::State1, State::State2)
+        );
+        assert!(script.next().is_none());
+        // The command fired once the transition had been yielded
+        assert_eq!(machine.state(), State::State1);
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use alloc::vec::Vec;
+use core::mem;
+
+/// A list which keeps up to `N` items inline and only allocates once it
+/// grows past that.
+///
+/// States rarely have more than a couple of actions of each kind, so this
+/// saves an allocation per action list and keeps the actions next to the
+/// rest of the state.
+#[derive(Clone)]
+pub(crate) enum SmallVec<T, const N: usize> {
+    /// The first `len` slots are filled
+    Inline {
+        items: [Option<T>; N],
+        len: usize,
+    },
+    Heap(Vec<T>),
+}
+
+impl<T, const N: usize> SmallVec<T, N> {
+    pub(crate) fn new() -> Self {
+        Self::Inline {
+            items: core::array::from_fn(|_| None),
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Self::Inline { len, .. } => *len,
+            Self::Heap(items) => items.len(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn push(&mut self, item: T) {
+        self.insert(self.len(), item);
+    }
+
+    /// ## Panics
+    /// If `index > len`
+    pub(crate) fn insert(&mut self, index: usize, item: T) {
+        match self {
+            Self::Inline { items, len } if *len < N => {
+                assert!(index <= *len, "insertion index out of bounds");
+                items[*len] = Some(item);
+                items[index..=*len].rotate_right(1);
+                *len += 1;
+            }
+            Self::Inline { items, .. } => {
+                let mut spilled: Vec<T> = Vec::with_capacity(N + 1);
+                spilled.extend(items.iter_mut().filter_map(Option::take));
+                spilled.insert(index, item);
+                *self = Self::Heap(spilled);
+            }
+            Self::Heap(items) => items.insert(index, item),
+        }
+    }
+
+    /// Bytes allocated once the list has spilled out of its inline storage
+    pub(crate) fn heap_bytes(&self) -> usize {
+        match self {
+            Self::Inline { .. } => 0,
+            Self::Heap(items) => items.capacity() * mem::size_of::<T>(),
+        }
+    }
+
+    /// The index of the first item for which `pred` is false, assuming the
+    /// list is partitioned by it
+    pub(crate) fn partition_point(&self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        self.iter()
+            .position(|item| !pred(item))
+            .unwrap_or(self.len())
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        let (inline, heap): (&[Option<T>], &[T]) = match self {
+            Self::Inline { items, len } => (&items[..*len], &[]),
+            Self::Heap(items) => (&[], items),
+        };
+        inline.iter().flatten().chain(heap)
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let (inline, heap): (&mut [Option<T>], &mut [T]) = match self {
+            Self::Inline { items, len } => (&mut items[..*len], &mut []),
+            Self::Heap(items) => (&mut [], items),
+        };
+        inline.iter_mut().flatten().chain(heap)
+    }
+
+    /// Remove every item, leaving an empty inline list
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = T> {
+        let (inline, heap) = match mem::take(self) {
+            Self::Inline { items, .. } => (Some(items), Vec::new()),
+            Self::Heap(items) => (None, items),
+        };
+        inline.into_iter().flatten().flatten().chain(heap)
+    }
+}
+
+impl<T, const N: usize> Default for SmallVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents<const N: usize>(list: &SmallVec<i32, N>) -> Vec<i32> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn stays_inline_up_to_capacity() {
+        let mut list = SmallVec::<i32, 2>::new();
+        list.push(2);
+        list.insert(0, 1);
+        assert!(matches!(list, SmallVec::Inline { .. }));
+        assert_eq!(contents(&list), vec![1, 2]);
+
+        list.insert(1, 3);
+        assert!(matches!(list, SmallVec::Heap(_)));
+        assert_eq!(contents(&list), vec![1, 3, 2]);
+    }
+
+    #[test]
+    fn partition_point_and_mutation() {
+        let mut list = SmallVec::<i32, 2>::new();
+        for i in [1, 3, 5] {
+            list.push(i);
+        }
+        assert_eq!(list.partition_point(|i| *i <= 3), 2);
+        list.iter_mut().for_each(|i| *i *= 10);
+        assert_eq!(contents(&list), vec![10, 30, 50]);
+    }
+
+    #[test]
+    fn drain_empties_the_list() {
+        let mut list = SmallVec::<i32, 2>::new();
+        list.push(1);
+        assert_eq!(list.drain().collect::<Vec<_>>(), vec![1]);
+        assert!(list.is_empty());
+
+        for i in 0..3 {
+            list.push(i);
+        }
+        assert_eq!(list.drain().collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(list.is_empty() && matches!(list, SmallVec::Inline { .. }));
+    }
+}
@@ -1,38 +1,38 @@
-use derivative::Derivative;
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::fmt::Display;
-use std::hash::Hash;
-use std::ops::FnOnce;
-use std::rc::Rc;
-use std::sync::Arc;
-use std::sync::Mutex;
-use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
-
-use crate::state_machine::StateMachine;
-use crate::state_representation::StateRepresentation;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+use crate::action::{ActionInfo, EntryAction, ExitAction};
+use crate::command::Command;
+use crate::guard::Guard;
+use crate::metadata::Metadata;
+use crate::state_representation::{EntryGuard, StateRepresentation};
 use crate::transition::Transition;
-use crate::trigger_behaviour::Internal;
-use crate::trigger_behaviour::Transitioning;
+use crate::transition_table::TransitionTable;
+use crate::trigger_behaviour::Dynamic;
 use crate::trigger_behaviour::TriggerBehaviour;
-use crate::StateMachineError;
-use crate::TransitionEventHandler;
+use crate::trigger_map::TriggerMatching;
 
-pub(crate) type WrappedStateRep<S, T, O> = Rc<RefCell<StateRepresentation<S, T, O>>>;
+pub(crate) type WrappedStateRep<'a, S, T, O> = Rc<RefCell<StateRepresentation<'a, S, T, O>>>;
 
-pub struct StateConfig<S, T, O> {
-    rep: WrappedStateRep<S, T, O>,
+pub struct StateConfig<'a, S, T, O> {
+    rep: WrappedStateRep<'a, S, T, O>,
+    /// How triggers given to trigger-filtered actions are compared
+    matching: TriggerMatching,
 }
 
-impl<S, T, O> StateConfig<S, T, O>
+impl<'a, S, T, O> StateConfig<'a, S, T, O>
 where
     S: Debug + Copy + Eq + Hash + 'static,
     T: Debug + Copy + Eq + Hash + 'static,
 {
-    pub(crate) fn new(rep: WrappedStateRep<S, T, O>) -> Self {
-        Self { rep }
+    pub(crate) fn new(rep: WrappedStateRep<'a, S, T, O>, matching: TriggerMatching) -> Self {
+        Self { rep, matching }
     }
 
     pub fn state(&self) -> S {
@@ -40,19 +40,146 @@ where
     }
 
     pub fn permit(self, trigger: T, destination_state: S) -> Self {
-        let behaviour =
-            TriggerBehaviour::Transitioning(Transitioning::new(trigger, destination_state));
+        let behaviour = TriggerBehaviour::transitioning(destination_state, None);
+        self.rep
+            .borrow_mut()
+            .add_trigger_behaviour(trigger, behaviour);
+        self
+    }
+
+    /// Permit `trigger` only when `guard` holds for the state object.  A
+    /// trigger may be permitted several times with different guards as long
+    /// as at most one of them is met when it fires.
+    pub fn permit_if<G>(self, trigger: T, destination_state: S, guard: G) -> Self
+    where
+        G: Into<Guard<'a, O>>,
+    {
+        let behaviour = TriggerBehaviour::transitioning(destination_state, Some(guard.into()));
+        self.rep
+            .borrow_mut()
+            .add_trigger_behaviour(trigger, behaviour);
+        self
+    }
+
+    /// Like [`Self::permit_if`] but the guard also sees the payload the
+    /// trigger was fired with, and is not met unless it is a `P`.  See
+    /// [`Guard::with_payload`].
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Pending, Approved }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Transfer }
+    /// # #[derive(Debug)]
+    /// # struct Account { limit: u64 }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, Account>::new(State::Pending);
+    /// builder.config(State::Pending).permit_if_with(
+    ///     Trigger::Transfer,
+    ///     State::Approved,
+    ///     |amount: &u64, account| *amount <= account.limit,
+    /// );
+    /// let mut machine = builder.build(Account { limit: 100 }).unwrap();
+    /// assert!(machine.fire_with_any(Trigger::Transfer, 500_u64).is_err());
+    /// machine.fire_with_any(Trigger::Transfer, 50_u64).unwrap();
+    /// ```
+    pub fn permit_if_with<P, F>(self, trigger: T, destination_state: S, guard: F) -> Self
+    where
+        P: Any,
+        F: Fn(&P, &O) -> bool + 'a,
+    {
+        let guard = Guard::with_payload("guard", guard);
+        self.permit_if(trigger, destination_state, guard)
+    }
+
+    /// Permit `trigger` to go to whichever of `destinations` `selector`
+    /// picks from the payload it was fired with by
+    /// [`StateMachine::fire_with_any`] and the state object.  Firing without
+    /// a `P` is rejected with
+    /// [`crate::StateMachineError::InvalidTriggerParameters`].
+    ///
+    /// The possible destinations are declared so the machine can be
+    /// exported, checked and visualised before anything fires.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { InReview, Approved, Rejected }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Review }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::InReview);
+    /// builder.config(State::InReview).permit_dynamic(
+    ///     Trigger::Review,
+    ///     [State::Approved, State::Rejected],
+    ///     |approved: &bool, _| if *approved { State::Approved } else { State::Rejected },
+    /// );
+    /// let mut machine = builder.build(()).unwrap();
+    /// machine.fire_with_any(Trigger::Review, false).unwrap();
+    /// assert_eq!(machine.state(), State::Rejected);
+    /// ```
+    pub fn permit_dynamic<P, F>(
+        self,
+        trigger: T,
+        destinations: impl IntoIterator<Item = S>,
+        selector: F,
+    ) -> Self
+    where
+        P: Any,
+        F: Fn(&P, &O) -> S + 'a,
+    {
+        let behaviour = TriggerBehaviour::dynamic(Dynamic::new(destinations, selector), None);
         self.rep
             .borrow_mut()
             .add_trigger_behaviour(trigger, behaviour);
         self
     }
 
+    /// Go to `destination` when none of the guards of the trigger added by
+    /// the previous `permit*` call are met.  More than one being met is
+    /// still [`StateMachineError::AmbiguousTransition`].
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Scoring, Gold, Silver, Bronze }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Finish }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, u32>::new(State::Scoring);
+    /// builder
+    ///     .config(State::Scoring)
+    ///     .permit_if(Trigger::Finish, State::Gold, |score: &u32| *score >= 90)
+    ///     .permit_if(Trigger::Finish, State::Silver, |score: &u32| (70..90).contains(score))
+    ///     .otherwise(State::Bronze);
+    /// let mut machine = builder.build(55).unwrap();
+    /// machine.fire(Trigger::Finish).unwrap();
+    /// assert_eq!(machine.state(), State::Bronze);
+    /// ```
+    ///
+    /// Building fails with [`StateMachineError::OtherwiseWithoutTransition`]
+    /// if no transition had been added to this state yet.
+    pub fn otherwise(self, destination_state: S) -> Self {
+        self.rep
+            .borrow_mut()
+            .add_fallback_behaviour(TriggerBehaviour::transitioning(destination_state, None));
+        self
+    }
+
+    /// Add every behaviour in `table` to this state
+    pub fn apply_table(self, table: &TransitionTable<'a, S, T, O>) -> Self {
+        {
+            let mut rep = self.rep.borrow_mut();
+            for (trigger, behaviour) in table.behaviours.iter() {
+                rep.add_trigger_behaviour(*trigger, behaviour.clone());
+            }
+        }
+        self
+    }
+
     pub fn internal_transition<F>(self, trigger: T, internal_action: F) -> Self
     where
-        F: FnMut(&Transition<S, T>, &mut O) + 'static,
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
     {
-        let behaviour = TriggerBehaviour::Internal(Internal::new(trigger));
+        let behaviour = TriggerBehaviour::internal(None);
         {
             let mut rep = self.rep.borrow_mut();
             rep.add_trigger_behaviour(trigger, behaviour);
@@ -61,19 +188,351 @@ where
         self
     }
 
+    /// Like [`Self::internal_transition`] but the action is given the
+    /// payload the trigger was fired with by [`StateMachine::fire_with_any`],
+    /// and only runs if it is a `P`
+    pub fn internal_transition_with<P, F>(self, trigger: T, mut internal_action: F) -> Self
+    where
+        P: Any,
+        F: FnMut(&P, &mut O) + 'a,
+    {
+        self.internal_transition(trigger, move |transition, object| {
+            if let Some(args) = transition.payload_downcast() {
+                internal_action(args, object)
+            }
+        })
+    }
+
     pub fn on_entry<F>(self, f: F) -> Self
     where
-        F: FnMut(&Transition<S, T>, &mut O) + 'static,
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
     {
         self.rep.borrow_mut().add_entry_action(f);
         self
     }
 
+    /// Like [`Self::on_entry`] but only runs when the state is entered
+    /// because of `trigger`, compared as the builder matches triggers, see
+    /// [`crate::StateMachineBuilder::match_triggers_by_discriminant`]
+    pub fn on_entry_from<F>(self, trigger: T, mut f: F) -> Self
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        let matching = self.matching;
+        self.on_entry(move |transition, object| {
+            if transition
+                .trigger
+                .as_ref()
+                .is_some_and(|t| matching.matches(t, &trigger))
+            {
+                f(transition, object)
+            }
+        })
+    }
+
+    /// Like [`Self::on_entry_from`] but `f` is given the payload the trigger
+    /// was fired with by [`StateMachine::fire_with_any`], and only runs if it
+    /// is a `P`
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Idle, Playing }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Play }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, u8>::new(State::Idle);
+    /// builder.config(State::Idle).permit(Trigger::Play, State::Playing);
+    /// builder
+    ///     .config(State::Playing)
+    ///     .on_entry_from_with(Trigger::Play, |args: &(u8,), volume| *volume = args.0);
+    /// let mut machine = builder.build(0).unwrap();
+    /// machine.fire_with_any(Trigger::Play, (7_u8,)).unwrap();
+    /// assert_eq!(*machine.object(), 7);
+    /// ```
+    pub fn on_entry_from_with<P, F>(self, trigger: T, mut f: F) -> Self
+    where
+        P: Any,
+        F: FnMut(&P, &mut O) + 'a,
+    {
+        self.on_entry_from(trigger, move |transition, object| {
+            if let Some(args) = transition.payload_downcast() {
+                f(args, object)
+            }
+        })
+    }
+
+    /// Like [`Self::on_entry`] but only runs when the state is entered from
+    /// `source`, whichever trigger caused the transition
+    pub fn on_entry_from_state<F>(self, source: S, mut f: F) -> Self
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        self.on_entry(move |transition, object| {
+            if transition.source == source {
+                f(transition, object)
+            }
+        })
+    }
+
+    /// Like [`Self::on_entry`] but ordered by `priority` instead of
+    /// registration order.  Lower priorities run first and
+    /// [`Self::on_entry`] uses priority `0`.
+    pub fn on_entry_with_priority<F>(self, priority: i32, f: F) -> Self
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        self.rep
+            .borrow_mut()
+            .add_entry_action_with_priority(priority, f);
+        self
+    }
+
+    /// Like [`Self::on_entry`] for an action implemented on a named type
+    pub fn on_entry_obj<A>(self, action: A) -> Self
+    where
+        A: EntryAction<S, T, O> + 'a,
+    {
+        self.rep.borrow_mut().add_entry_action_obj(action);
+        self
+    }
+
+    /// Like [`Self::on_entry`] but only runs the first time the state is
+    /// entered, so `f` may be `FnOnce`
+    pub fn on_first_entry<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&Transition<S, T>, &mut O) + 'a,
+    {
+        self.rep.borrow_mut().add_first_entry_action(f);
+        self
+    }
+
     pub fn on_exit<F>(self, f: F) -> Self
     where
-        F: FnMut(&Transition<S, T>, &mut O) + 'static,
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
     {
         self.rep.borrow_mut().add_exit_action(f);
         self
     }
+
+    /// Remove every transition and internal transition configured for
+    /// `trigger`, e.g. to replace them in
+    /// [`StateMachine::reconfigure`]
+    pub fn forget(self, trigger: T) -> Self {
+        self.rep.borrow_mut().forget(trigger);
+        self
+    }
+
+    /// Remove every entry, exit, command and compensate action configured
+    /// so far.  Internal transitions keep their actions; remove them with
+    /// [`Self::forget`].
+    pub fn clear_actions(self) -> Self {
+        self.rep.borrow_mut().clear_actions();
+        self
+    }
+
+    /// Register an action which runs after the entry actions and returns
+    /// [`Command`]s for the machine to carry out once the transition has
+    /// committed, rather than acting itself.
+    ///
+    /// ```
+    /// # use stateless_rs::{Command, StateMachineBuilder};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Idle, Busy, Done }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Start, Finish }
+    /// let mut builder = StateMachineBuilder::<_, _, ()>::new(State::Idle);
+    /// builder.config(State::Idle).permit(Trigger::Start, State::Busy);
+    /// builder
+    ///     .config(State::Busy)
+    ///     .permit(Trigger::Finish, State::Done)
+    ///     .on_entry_commands(|_, _| {
+    ///         vec![Command::Emit("busy".to_string()), Command::Fire(Trigger::Finish)]
+    ///     });
+    /// let mut machine = builder.build(()).unwrap();
+    /// machine.fire(Trigger::Start).unwrap();
+    /// assert_eq!(machine.state(), State::Done);
+    /// assert_eq!(machine.take_emitted(), ["busy"]);
+    /// ```
+    pub fn on_entry_commands<F>(self, f: F) -> Self
+    where
+        F: FnMut(&Transition<S, T>, &O) -> Vec<Command<T>> + 'a,
+    {
+        self.rep.borrow_mut().add_command_action(f);
+        self
+    }
+
+    /// Register an action which undoes entering the state, run by
+    /// [`crate::StateMachine::compensate_to`] when unwinding past it.  It sees
+    /// the transition which entered the state.
+    pub fn on_compensate<F>(self, f: F) -> Self
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        self.rep.borrow_mut().add_compensate_action(f);
+        self
+    }
+
+    /// Like [`Self::on_exit`] but only runs when the state is left for
+    /// `destination`
+    pub fn on_exit_to<F>(self, destination: S, mut f: F) -> Self
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        self.on_exit(move |transition, object| {
+            if transition.destination == destination {
+                f(transition, object)
+            }
+        })
+    }
+
+    /// Like [`Self::on_exit`] but ordered by `priority` instead of
+    /// registration order.  Lower priorities run first and [`Self::on_exit`]
+    /// uses priority `0`.
+    pub fn on_exit_with_priority<F>(self, priority: i32, f: F) -> Self
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        self.rep
+            .borrow_mut()
+            .add_exit_action_with_priority(priority, f);
+        self
+    }
+
+    /// The entry actions configured so far, in the order they will run
+    pub fn entry_actions(&self) -> Vec<ActionInfo> {
+        self.rep.borrow().entry_action_infos()
+    }
+
+    /// The exit actions configured so far, in the order they will run
+    pub fn exit_actions(&self) -> Vec<ActionInfo> {
+        self.rep.borrow().exit_action_infos()
+    }
+
+    /// Like [`Self::on_exit`] for an action implemented on a named type
+    pub fn on_exit_obj<A>(self, action: A) -> Self
+    where
+        A: ExitAction<S, T, O> + 'a,
+    {
+        self.rep.borrow_mut().add_exit_action_obj(action);
+        self
+    }
+
+    /// Describe the state for introspection and diagram exports
+    pub fn describe(self, description: impl Into<String>) -> Self {
+        self.rep.borrow_mut().metadata.description = Some(description.into());
+        self
+    }
+
+    /// Only let the machine into this state when `guard` is met, checked
+    /// for every inbound transition after its own guards.  Otherwise the
+    /// transition goes to `fallback` instead, which may have an entry guard
+    /// of its own, and is annotated with why.  Internal transitions do not
+    /// enter the state so are not checked.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Cart, Checkout, Empty }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Pay }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, usize>::new(State::Cart);
+    /// builder.config(State::Cart).permit(Trigger::Pay, State::Checkout);
+    /// builder
+    ///     .config(State::Checkout)
+    ///     .enter_if(|items: &usize| *items > 0, State::Empty);
+    /// let mut machine = builder.build(0).unwrap();
+    /// machine.fire(Trigger::Pay).unwrap();
+    /// assert_eq!(machine.state(), State::Empty);
+    /// ```
+    pub fn enter_if<G>(self, guard: G, fallback: S) -> Self
+    where
+        G: Into<Guard<'a, O>>,
+    {
+        self.rep.borrow_mut().entry_guard = Some(EntryGuard {
+            guard: guard.into(),
+            fallback,
+            fallback_index: 0,
+        });
+        self
+    }
+
+    /// Put the state in the named region, a grouping for queries such as
+    /// [`StateMachine::in_region`] and for clustering diagrams.  Unlike a
+    /// superstate it has no effect on behaviour.  A state is in at most one
+    /// region; calling this again moves it.
+    pub fn region(self, region: impl Into<String>) -> Self {
+        self.rep.borrow_mut().region = Some(region.into());
+        self
+    }
+
+    /// Put the state at step `step` of the happy path, for
+    /// [`StateMachine::progress`] and [`StateMachine::remaining_steps`].
+    /// Steps needn't be contiguous and states may share one.
+    pub fn step(self, step: u16) -> Self {
+        self.rep.borrow_mut().step = Some(step);
+        self
+    }
+
+    /// Tag the state so it can be grouped with others
+    pub fn tag(self, tag: impl Into<String>) -> Self {
+        self.rep.borrow_mut().metadata.add_tag(tag);
+        self
+    }
+
+    /// Describe the transition added by the previous `permit*` or
+    /// `internal_transition` call
+    ///
+    /// ## Panics
+    /// If no transition has been added to this state yet
+    pub fn describe_transition(self, description: impl Into<String>) -> Self {
+        self.last_behaviour_metadata(|metadata| metadata.description = Some(description.into()));
+        self
+    }
+
+    /// Name the transition added by the previous `permit*` or
+    /// `internal_transition` call, such as `"approve-order"`.  The label is
+    /// shown on its edge in DOT and Mermaid exports, carried by the
+    /// [`Transition`]s it makes, and so by telemetry and the JSON log, and
+    /// listed in [`crate::StateMachineError::AmbiguousTransition`].
+    ///
+    /// ## Panics
+    /// If no transition has been added to this state yet
+    pub fn label(self, label: impl Into<String>) -> Self {
+        let label: String = label.into();
+        self.rep
+            .borrow_mut()
+            .last_behaviour()
+            .expect("a transition must be added before it can be labelled")
+            .label = Some(Arc::from(label));
+        self
+    }
+
+    /// Tag the transition added by the previous `permit*` or
+    /// `internal_transition` call
+    ///
+    /// ## Panics
+    /// If no transition has been added to this state yet
+    pub fn tag_transition(self, tag: impl Into<String>) -> Self {
+        self.last_behaviour_metadata(|metadata| metadata.add_tag(tag));
+        self
+    }
+
+    /// The description and tags of the state
+    pub fn metadata(&self) -> Metadata {
+        self.rep.borrow().metadata.clone()
+    }
+
+    /// The description and tags of every transition configured for
+    /// `trigger`, in the order they were added
+    pub fn transition_metadata(&self, trigger: T) -> Vec<Metadata> {
+        self.rep.borrow().behaviour_metadata(trigger)
+    }
+
+    fn last_behaviour_metadata(&self, f: impl FnOnce(&mut Metadata)) {
+        let mut rep = self.rep.borrow_mut();
+        let metadata = rep
+            .last_behaviour_metadata()
+            .expect("a transition must be added before it can be described or tagged");
+        f(metadata);
+    }
 }
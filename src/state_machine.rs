@@ -1,16 +1,56 @@
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::fmt::Display;
-use std::hash::Hash;
-use std::sync::Arc;
-use std::sync::Mutex;
-use std::sync::MutexGuard;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::rc::{Rc, Weak};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt::Debug;
+use core::fmt::Display;
+use core::fmt::Write;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+use core::mem;
+use core::str::FromStr;
+use core::time::Duration;
+use derivative::Derivative;
 
+use crate::action::{ActionInfo, StaticActions};
+use crate::bulk_summary::BulkSummary;
+use crate::clock::Clock;
+use crate::command::Command;
+use crate::config_visitor::ConfigVisitor;
+use crate::coverage::CoverageReport;
+use crate::debugger::{Breakpoint, DebugEvent, DebugPhase, Debugger};
+use crate::dot;
+use crate::hasher::DefaultHashBuilder;
+use crate::intern::Interner;
+use crate::lifecycle::Lifecycle;
+use crate::memory_stats::MemoryStats;
+use crate::mermaid;
+use crate::metadata::Metadata;
+use crate::middleware::BoxedMiddleware;
+use crate::middleware::Next;
+use crate::node_style::NodeStyle;
+use crate::parameter_type::ParameterType;
+use crate::pause::PauseMode;
+use crate::reconfiguration::Reconfiguration;
+use crate::script::Script;
+use crate::state_machine_info::StateMachineInfo;
 use crate::state_representation::StateRepresentation;
-use crate::transition::Transition;
-use crate::transition_event;
-use crate::trigger_behaviour::TriggerBehaviour;
+use crate::storage::DefaultStorage;
+use crate::storage::Storage;
+use crate::suggest::did_you_mean;
+use crate::telemetry::TelemetrySink;
+use crate::transition::{Output, Payload, Transition};
+use crate::trigger_behaviour::Resolution;
+use crate::trigger_map::{TriggerKey, TriggerMatching};
 use crate::StateMachineError;
+use crate::Subscription;
+use crate::SubscriptionId;
 use crate::TransitionEventHandler;
 
 /// A finite state machine which holds a state object.
@@ -22,41 +62,355 @@ use crate::TransitionEventHandler;
 ///
 /// ## State Object
 ///
-/// Whatever you want to put into the state machine.  This will be wrapped
-/// inside a [`std::sync::Mutex`].  If you want to pull it out you will need to
-/// call `.object()` which will return a [`std::sync::MutexGuard`] and will need
-/// to be dereferenced
-#[derive(Debug)]
-pub struct StateMachine<S, T, O> {
+/// Whatever you want to put into the state machine.  By default this will be
+/// wrapped inside a [`std::sync::Mutex`] (a [`core::cell::RefCell`] without
+/// the `std` feature).  If you want to pull it out you will
+/// need to call `.object()` which will return a [`std::sync::MutexGuard`] and
+/// will need to be dereferenced.  The wrapper is the `St` parameter, chosen
+/// with [`crate::StateMachineBuilder::build_with_storage`]; see [`Storage`].
+///
+/// ## Static actions
+///
+/// The `A` parameter is a [`StaticActions`] implementation called on every
+/// transition without dynamic dispatch.  It is `()` unless the machine was
+/// built with [`crate::StateMachineBuilder::build_with_actions`].
+///
+/// ## Lifetime
+///
+/// Actions, guards and event handlers only have to live as long as `'a`, so
+/// they may borrow from the surrounding scope instead of being `'static`
+/// closures sharing state through an [`std::sync::Arc`].
+///
+/// ## Hasher
+///
+/// `H` hashes triggers when they are fired and states when they are looked
+/// up.  See [`DefaultHashBuilder`] and [`Self::with_hasher`].  Nothing is
+/// iterated in hash order, so everything that lists states or triggers is
+/// in enum or configuration order and the same on every run.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct StateMachine<'a, S, T, O, St = DefaultStorage<O>, A = (), H = DefaultHashBuilder> {
+    initial_state: S,
     current_state: S,
-    state_representations: HashMap<S, StateRepresentation<S, T, O>>,
-    object: Arc<Mutex<O>>,
-    transition_event: TransitionEventHandler<S, T>,
+    /// Interned id of the current state, which is also its position in
+    /// `state_representations`
+    current_index: u16,
+    state_representations: Vec<StateRepresentation<'a, S, T, O>>,
+    /// Only used to convert states at the API boundary, never when firing
+    states: Interner<S, H>,
+    /// Converts fired triggers to the ids the representations are indexed by
+    triggers: Interner<TriggerKey<T>, H>,
+    /// Declared payload types by trigger id
+    parameter_types: Vec<Option<ParameterType>>,
+    trigger_matching: TriggerMatching,
+    /// Every state there is, for a machine built with
+    /// [`crate::StateMachineBuilder::with_state_universe`]
+    universe: Option<Vec<S>>,
+    object: St,
+    #[derivative(Debug = "ignore")]
+    phantom: PhantomData<O>,
+    #[derivative(Debug = "ignore")]
+    actions: A,
+    transition_event: TransitionEventHandler<'a, S, T>,
+    #[derivative(Debug = "ignore")]
+    middlewares: Vec<BoxedMiddleware<S, T>>,
+    last_transition: Option<Transition<S, T>>,
+    last_rejection: Option<StateMachineError<S, T>>,
+    /// Set for the duration of [`Self::fire_with_any`]
+    #[derivative(Debug = "ignore")]
+    payload: Option<Payload>,
+    #[derivative(Debug = "ignore")]
+    clock: Box<dyn Clock + 'a>,
+    /// When the current state was entered, by `clock`
+    state_entered_at: Duration,
+    entry_counts: Vec<u64>,
+    /// `Running` once [`Self::start`] has run the initial entry actions,
+    /// which firing does not record
+    lifecycle: Lifecycle,
+    /// The sequence number of the next transition
+    next_sequence: u64,
+    paused: Option<PauseMode>,
+    /// [`Self::start`] was called while paused, so runs on resume
+    start_on_resume: bool,
+    /// Triggers fired while paused with [`PauseMode::Queue`]
+    #[derivative(Debug = "ignore")]
+    paused_triggers: VecDeque<(T, Option<Payload>)>,
+    /// Every transition which entered a state, with the state's index, for
+    /// [`Self::compensate_to`].  Only kept when some state has compensations.
+    #[derivative(Debug = "ignore")]
+    history: Option<Vec<(Transition<S, T>, u16)>>,
+    /// Returned by command actions and not carried out yet
+    commands: VecDeque<Command<T>>,
+    /// When each scheduled trigger is due, by `clock`, in scheduling order
+    timers: Vec<(Duration, T)>,
+    /// Events from [`Command::Emit`] not taken yet
+    emitted: Vec<String>,
+    /// Collects action outputs during [`Self::fire_with_output`]
+    #[derivative(Debug = "ignore")]
+    outputs: Option<Vec<Output>>,
+    debugger: Debugger<'a, S, T, O>,
+    /// Only recorded once enabled, since it costs a scan per fire
+    coverage: Option<CoverageReport<S, T>>,
+    #[derivative(Debug = "ignore")]
+    telemetry: Vec<Box<dyn TelemetrySink<S, T> + 'a>>,
 }
 
-impl<S, T, O> StateMachine<S, T, O>
+impl<'a, S, T, O, St, A, H> StateMachine<'a, S, T, O, St, A, H>
 where
     S: Copy + Eq + Hash + Debug,
     T: Copy + Eq + Hash + Debug,
     O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
 {
     // Must create with StateMachineBuilder
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         initial_state: S,
-        state_representations: HashMap<S, StateRepresentation<S, T, O>>,
-        object: Arc<Mutex<O>>,
-        transition_event: TransitionEventHandler<S, T>,
+        mut state_representations: Vec<StateRepresentation<'a, S, T, O>>,
+        object: St,
+        actions: A,
+        transition_event: TransitionEventHandler<'a, S, T>,
+        middlewares: Vec<BoxedMiddleware<S, T>>,
+        trigger_matching: TriggerMatching,
+        trigger_parameters: Vec<(T, ParameterType)>,
+        universe: Option<Vec<S>>,
     ) -> Self {
+        let states: Interner<S, H> = state_representations.iter().map(|r| r.state()).collect();
+        let triggers: Interner<TriggerKey<T>, H> = state_representations
+            .iter()
+            .flat_map(|r| r.triggers().map(|t| trigger_matching.key(t)))
+            .collect();
+        for rep in state_representations.iter_mut() {
+            rep.resolve_destinations(|state| states.id(&state).expect("every state is interned"));
+            rep.intern_triggers(&triggers);
+        }
+        // Later declarations win
+        let mut parameter_types = vec![None; triggers.len()];
+        for (trigger, parameters) in trigger_parameters {
+            if let Some(id) = triggers.id(&trigger_matching.key(&trigger)) {
+                parameter_types[usize::from(id)] = Some(parameters);
+            }
+        }
+        let entry_counts = vec![0; state_representations.len()];
+        let history = state_representations
+            .iter()
+            .any(StateRepresentation::has_compensate_actions)
+            .then(Vec::new);
         Self {
+            initial_state,
             current_state: initial_state,
+            current_index: states.id(&initial_state).expect("every state is interned"),
             state_representations,
+            states,
+            triggers,
+            parameter_types,
+            trigger_matching,
+            universe,
             object,
+            phantom: PhantomData,
+            actions,
             transition_event,
+            middlewares,
+            last_transition: None,
+            last_rejection: None,
+            payload: None,
+            clock: default_clock(),
+            state_entered_at: Duration::ZERO,
+            entry_counts,
+            lifecycle: Lifecycle::NotStarted,
+            next_sequence: 0,
+            paused: None,
+            start_on_resume: false,
+            paused_triggers: VecDeque::new(),
+            history,
+            commands: VecDeque::new(),
+            timers: Vec::new(),
+            emitted: Vec::new(),
+            outputs: None,
+            debugger: Debugger::new(),
+            coverage: None,
+            telemetry: Vec::new(),
+        }
+    }
+
+    /// Change the machine after build, for plugins which extend a workflow
+    /// at runtime.  `f` is handed the usual [`crate::StateConfig`]s by
+    /// [`Reconfiguration::config`], to add permits and actions or remove them
+    /// with [`crate::StateConfig::forget`] and
+    /// [`crate::StateConfig::clear_actions`].  This takes `&mut self`, so it
+    /// can't run while a transition is in flight.  New states come after the
+    /// existing ones.
+    ///
+    /// `f` changes a copy of the configuration, which only replaces the
+    /// machine's once it is checked.  Fails, leaving the machine as it was,
+    /// with [`StateMachineError::ConfigStillInUse`] if `f` keeps a
+    /// `StateConfig`, or for a machine built with
+    /// [`crate::StateMachineBuilder::with_state_universe`], with
+    /// [`StateMachineError::UnknownState`] if `f` configures a state outside
+    /// the universe or adds a transition to one.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Draft, Review, Published }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Submit }
+    /// let mut builder = StateMachineBuilder::<_, _, ()>::new(State::Draft);
+    /// builder.config(State::Draft).permit(Trigger::Submit, State::Published);
+    /// let mut machine = builder.build(()).unwrap();
+    ///
+    /// // A review plugin puts a step in the way
+    /// machine
+    ///     .reconfigure(|config| {
+    ///         config
+    ///             .config(State::Draft)
+    ///             .forget(Trigger::Submit)
+    ///             .permit(Trigger::Submit, State::Review);
+    ///         config.config(State::Review).permit(Trigger::Submit, State::Published);
+    ///     })
+    ///     .unwrap();
+    /// machine.fire(Trigger::Submit).unwrap();
+    /// assert_eq!(machine.state(), State::Review);
+    /// ```
+    pub fn reconfigure<F>(&mut self, f: F) -> Result<(), StateMachineError<S, T>>
+    where
+        S: 'static,
+        T: 'static,
+        F: FnOnce(&mut Reconfiguration<'a, S, T, O>),
+    {
+        let states = self
+            .state_representations
+            .iter()
+            .map(|rep| (rep.state(), Rc::new(RefCell::new(rep.clone()))))
+            .collect();
+        let mut reconfiguration = Reconfiguration::new(states, self.trigger_matching);
+        f(&mut reconfiguration);
+
+        let mut reps = Vec::with_capacity(reconfiguration.states.len());
+        for (state, rep) in reconfiguration.states {
+            match Rc::try_unwrap(rep) {
+                Ok(rep) => reps.push(rep.into_inner()),
+                Err(_) => return Err(StateMachineError::ConfigStillInUse { state }),
+            }
+        }
+        if let Some(rep) = reps.iter().find(|rep| rep.stray_otherwise) {
+            return Err(StateMachineError::OtherwiseWithoutTransition { state: rep.state() });
+        }
+        if let Some(universe) = &self.universe {
+            if let Some(state) = reps
+                .iter()
+                .map(|rep| rep.state())
+                .chain(reps.iter().flat_map(|rep| rep.destinations()))
+                .find(|state| !universe.contains(state))
+            {
+                return Err(StateMachineError::UnknownState { state });
+            }
+        }
+        let destinations: Vec<S> = reps.iter().flat_map(|rep| rep.destinations()).collect();
+        for state in destinations {
+            if !reps.iter().any(|rep| rep.state() == state) {
+                reps.push(StateRepresentation::with_trigger_matching(
+                    state,
+                    self.trigger_matching,
+                ));
+            }
+        }
+
+        // Existing states keep their ids; triggers are interned afresh
+        let parameters: Vec<(TriggerKey<T>, ParameterType)> = self
+            .triggers
+            .keys()
+            .iter()
+            .zip(&self.parameter_types)
+            .filter_map(|(key, parameters)| parameters.map(|parameters| (*key, parameters)))
+            .collect();
+        self.states = reps.iter().map(|r| r.state()).collect();
+        self.triggers = reps
+            .iter()
+            .flat_map(|r| r.triggers().map(|t| self.trigger_matching.key(t)))
+            .collect();
+        for rep in reps.iter_mut() {
+            rep.resolve_destinations(|state| {
+                self.states.id(&state).expect("every state is interned")
+            });
+            rep.intern_triggers(&self.triggers);
+        }
+        self.parameter_types = vec![None; self.triggers.len()];
+        for (key, parameters) in parameters {
+            if let Some(id) = self.triggers.id(&key) {
+                self.parameter_types[usize::from(id)] = Some(parameters);
+            }
+        }
+        self.entry_counts.resize(reps.len(), 0);
+        if self.history.is_none() && reps.iter().any(StateRepresentation::has_compensate_actions) {
+            self.history = Some(Vec::new());
+        }
+        self.state_representations = reps;
+        Ok(())
+    }
+
+    /// The same machine, looking states and triggers up with `H2`
+    ///
+    /// ```
+    /// # use stateless_rs::{FxBuildHasher, StateMachineBuilder};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { On }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger {
+    /// # }
+    /// # fn main() -> Result<(), stateless_rs::StateMachineError<State,Trigger>> {
+    /// let builder = StateMachineBuilder::<State, Trigger, ()>::new(State::On);
+    /// let machine = builder.build(())?.with_hasher::<FxBuildHasher>();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_hasher<H2>(self) -> StateMachine<'a, S, T, O, St, A, H2>
+    where
+        H2: BuildHasher + Default,
+    {
+        // Re-interning in the same order keeps every id the same
+        StateMachine {
+            initial_state: self.initial_state,
+            current_state: self.current_state,
+            current_index: self.current_index,
+            state_representations: self.state_representations,
+            states: self.states.keys().iter().copied().collect(),
+            triggers: self.triggers.keys().iter().copied().collect(),
+            parameter_types: self.parameter_types,
+            trigger_matching: self.trigger_matching,
+            universe: self.universe,
+            object: self.object,
+            phantom: PhantomData,
+            actions: self.actions,
+            transition_event: self.transition_event,
+            middlewares: self.middlewares,
+            last_transition: self.last_transition,
+            last_rejection: self.last_rejection,
+            payload: self.payload,
+            clock: self.clock,
+            state_entered_at: self.state_entered_at,
+            entry_counts: self.entry_counts,
+            lifecycle: self.lifecycle,
+            next_sequence: self.next_sequence,
+            paused: self.paused,
+            start_on_resume: self.start_on_resume,
+            paused_triggers: self.paused_triggers,
+            history: self.history,
+            commands: self.commands,
+            timers: self.timers,
+            emitted: self.emitted,
+            outputs: self.outputs,
+            debugger: self.debugger,
+            coverage: self.coverage,
+            telemetry: self.telemetry,
         }
     }
 
     /// Pull out the object that went into the
-    /// [`crate::StateMachineBuilder.build`] as a [`std::sync::MutexGuard`]
+    /// [`crate::StateMachineBuilder.build`] as a [`std::sync::MutexGuard`],
+    /// or whatever guard the machine's [`Storage`] hands out
     ///
     /// ## Example
     /// ```
@@ -76,265 +430,3250 @@ where
     /// # Ok(())
     /// # }
     /// ```
-    pub fn object(&self) -> MutexGuard<O> {
-        let o = self.object.lock().unwrap();
-        o
+    pub fn object(&self) -> St::Guard<'_> {
+        self.object.get()
+    }
+
+    /// Mutable access to the object without going through the storage's
+    /// guard
+    pub fn object_mut(&mut self) -> &mut O {
+        self.object.get_mut()
+    }
+
+    /// Returns the current state of the state machine
+    pub fn state(&self) -> S {
+        self.current_state
+    }
+
+    /// The display name of the current state, for UIs and APIs.  Deriving
+    /// `strum::Display` on the state enum is the easiest way to name states.
+    pub fn state_name(&self) -> String
+    where
+        S: Display,
+    {
+        self.current_state.to_string()
+    }
+
+    /// The initial state and every state configured or named as a
+    /// destination, in enum order or the order given to
+    /// [`crate::StateMachineBuilder::with_states`].  States added by
+    /// [`Self::reconfigure`] come last.  Enum variants the machine was never
+    /// told about are left out.
+    pub fn states(&self) -> Vec<S> {
+        self.states.keys().to_vec()
+    }
+
+    /// Every trigger configured in some state, in state order and then the
+    /// order they were configured in
+    pub fn triggers(&self) -> Vec<T> {
+        let mut triggers: Vec<T> = Vec::new();
+        for trigger in self.state_representations.iter().flat_map(|r| r.triggers()) {
+            if !triggers
+                .iter()
+                .any(|t| self.trigger_matching.matches(t, trigger))
+            {
+                triggers.push(*trigger);
+            }
+        }
+        triggers
+    }
+
+    /// The display names of [`Self::states`]
+    pub fn state_names(&self) -> Vec<String>
+    where
+        S: Display,
+    {
+        self.states.keys().iter().map(ToString::to_string).collect()
+    }
+
+    /// The display names of [`Self::triggers`].  See [`Self::fire_by_name`]
+    /// for going the other way.
+    pub fn trigger_names(&self) -> Vec<String>
+    where
+        T: Display,
+    {
+        self.triggers().iter().map(ToString::to_string).collect()
+    }
+
+    /// The state whose display name is `name`
+    pub fn state_by_name(&self, name: &str) -> Option<S>
+    where
+        S: Display,
+    {
+        self.states
+            .keys()
+            .iter()
+            .copied()
+            .find(|state| state.to_string() == name)
+    }
+
+    /// The description and tags given to `state` with
+    /// [`crate::StateConfig::describe`] and [`crate::StateConfig::tag`]
+    pub fn state_metadata(&self, state: S) -> Option<&Metadata> {
+        let id = self.states.id(&state)?;
+        Some(&self.state_representations[usize::from(id)].metadata)
+    }
+
+    /// Whether the current state has been tagged with `tag`
+    pub fn current_state_has_tag(&self, tag: &str) -> bool {
+        self.state_metadata(self.current_state)
+            .is_some_and(|m| m.has_tag(tag))
+    }
+
+    /// Every state tagged with `tag`, in the order of [`Self::states`]
+    pub fn states_with_tag(&self, tag: &str) -> Vec<S> {
+        self.state_representations
+            .iter()
+            .filter(|r| r.metadata.has_tag(tag))
+            .map(|r| r.state())
+            .collect()
+    }
+
+    /// Whether the current state is in `region`, see
+    /// [`crate::StateConfig::region`]
+    pub fn in_region(&self, region: &str) -> bool {
+        self.state_representations[usize::from(self.current_index)]
+            .region
+            .as_deref()
+            == Some(region)
+    }
+
+    /// Every state in `region`, in the order of [`Self::states`]
+    pub fn states_in_region(&self, region: &str) -> Vec<S> {
+        self.state_representations
+            .iter()
+            .filter(|r| r.region.as_deref() == Some(region))
+            .map(|r| r.state())
+            .collect()
+    }
+
+    /// How far along the happy path the current state is, from 0 to 1: its
+    /// step over the last step.  0 in a state without a step, see
+    /// [`crate::StateConfig::step`]
+    pub fn progress(&self) -> f32 {
+        let last = self
+            .state_representations
+            .iter()
+            .filter_map(|r| r.step)
+            .max();
+        match (self.current_representation().step, last) {
+            (Some(step), Some(last)) if last > 0 => f32::from(step) / f32::from(last),
+            _ => 0.0,
+        }
+    }
+
+    /// The states with a later step than the current state, in step order.
+    /// Every state with a step if the current state has none.
+    pub fn remaining_steps(&self) -> Vec<S> {
+        let current = self.current_representation().step;
+        let mut remaining: Vec<(u16, S)> = self
+            .state_representations
+            .iter()
+            .filter_map(|r| r.step.map(|step| (step, r.state())))
+            .filter(|(step, _)| current.is_none_or(|current| *step > current))
+            .collect();
+        remaining.sort_by_key(|(step, _)| *step);
+        remaining.into_iter().map(|(_, state)| state).collect()
+    }
+
+    /// Everything configured on this machine, as plain data
+    pub fn info(&self) -> StateMachineInfo<S, T> {
+        StateMachineInfo {
+            initial_state: self.initial_state,
+            states: self
+                .state_representations
+                .iter()
+                .map(StateRepresentation::info)
+                .collect(),
+        }
+    }
+
+    /// Walk the configuration with `visitor`, see [`StateMachineInfo::accept`]
+    pub fn accept<V: ConfigVisitor<S, T> + ?Sized>(&self, visitor: &mut V) {
+        self.info().accept(visitor);
+    }
+
+    /// A Graphviz digraph of every state and transition, labelled with
+    /// guard descriptions and actions.  Render it with `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with_style(|_| NodeStyle::default())
+    }
+
+    /// Like [`Self::to_dot`], with each state node styled by `style`, for
+    /// example to colour states by tag
+    pub fn to_dot_with_style<F>(&self, style: F) -> String
+    where
+        F: Fn(S) -> NodeStyle,
+    {
+        dot::render(&self.info(), style, None)
+    }
+
+    /// Like [`Self::to_dot`] with the current state filled in and the last
+    /// transition drawn in bold, for dashboards and support tooling
+    pub fn to_dot_live(&self) -> String {
+        let current = self.current_state;
+        dot::render(
+            &self.info(),
+            |state| {
+                if state == current {
+                    NodeStyle::default().fill_color("lightblue")
+                } else {
+                    NodeStyle::default()
+                }
+            },
+            self.last_transition.as_ref(),
+        )
+    }
+
+    /// A Mermaid state diagram with the current state highlighted and the
+    /// last transition marked `(last)`
+    pub fn to_mermaid_live(&self) -> String {
+        mermaid::render_live(
+            &self.info(),
+            self.current_state,
+            self.last_transition.as_ref(),
+        )
+    }
+
+    /// The triggers [`Self::fire`] would accept in the current state, before
+    /// any middleware gets a say, in the order they were configured
+    pub fn permitted_triggers(&self) -> Vec<T> {
+        let object = self.object.get();
+        self.current_representation().permitted_triggers(&object)
+    }
+
+    /// Whether [`Self::fire`] would accept `trigger` in the current state,
+    /// before any middleware gets a say
+    pub fn can_fire(&self, trigger: T) -> bool {
+        self.why_not(trigger).is_none()
+    }
+
+    /// The error [`Self::fire`] would return for `trigger` in the current
+    /// state, before any middleware gets a say, or `None` if it would be
+    /// accepted.  Nothing is run.
+    pub fn why_not(&self, trigger: T) -> Option<StateMachineError<S, T>> {
+        let Some(trigger_id) = self.triggers.id(&self.trigger_matching.key(&trigger)) else {
+            return Some(StateMachineError::TriggerNotPermitted {
+                state: self.current_state,
+                trigger,
+            });
+        };
+        let object = self.object.get();
+        self.current_representation()
+            .get_interned_behaviour(trigger_id, trigger, &object, None, |_, _| ())
+            .err()
+    }
+
+    /// A multi-line report of where the machine is and what it can do next,
+    /// for logs and support tickets: the current state, time in it,
+    /// lifecycle and pause, each configured trigger with whether it would
+    /// be accepted or why not, and the last transition.
+    ///
+    /// ```text
+    /// state: Review
+    /// time in state: 1.5s
+    /// lifecycle: Running
+    /// paused: Queue, 2 queued
+    /// triggers:
+    ///   Approve: permitted
+    ///   Escalate: trigger Escalate is valid for Review but guard conditions are not met: ["senior"]
+    /// last transition: Draft --Submit--> Review
+    /// ```
+    pub fn describe(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "state: {:?}", self.current_state);
+        let _ = writeln!(report, "time in state: {:?}", self.time_in_state());
+        let _ = writeln!(report, "lifecycle: {:?}", self.lifecycle());
+        if let Some(mode) = self.paused {
+            let queued = self.pending_count();
+            let _ = writeln!(report, "paused: {mode:?}, {queued} queued");
+        }
+        report.push_str("triggers:\n");
+        let triggers: Vec<T> = self.current_representation().triggers().copied().collect();
+        if triggers.is_empty() {
+            report.push_str("  (none)\n");
+        }
+        for trigger in triggers {
+            match self.why_not(trigger) {
+                None => {
+                    let _ = writeln!(report, "  {trigger:?}: permitted");
+                }
+                Some(error) => {
+                    let _ = writeln!(report, "  {trigger:?}: {error}");
+                }
+            }
+        }
+        match &self.last_transition {
+            Some(t) => {
+                let _ = match &t.trigger {
+                    Some(trigger) => writeln!(
+                        report,
+                        "last transition: {:?} --{trigger:?}--> {:?}",
+                        t.source, t.destination
+                    ),
+                    None => writeln!(
+                        report,
+                        "last transition: {:?} --> {:?}",
+                        t.source, t.destination
+                    ),
+                };
+            }
+            None => report.push_str("last transition: none\n"),
+        }
+        report
+    }
+
+    /// The representation of the current state.  It is kept by index and
+    /// updated on transition, so nothing is looked up between transitions.
+    fn current_representation(&self) -> &StateRepresentation<'a, S, T, O> {
+        &self.state_representations[usize::from(self.current_index)]
+    }
+
+    /// Counts and approximate sizes of the machine's states, behaviours and
+    /// actions.  See [`MemoryStats`] for what is counted.
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            machine_bytes: core::mem::size_of::<Self>()
+                + self.state_representations.capacity()
+                    * core::mem::size_of::<StateRepresentation<'a, S, T, O>>()
+                + self.states.heap_bytes()
+                + self.triggers.heap_bytes()
+                + self.entry_counts.capacity() * core::mem::size_of::<u64>(),
+            ..MemoryStats::default()
+        };
+        for rep in &self.state_representations {
+            stats += rep.memory_stats();
+        }
+        stats
+    }
+
+    /// How long the machine has been in the current state, by its
+    /// [`Clock`].  The initial state counts from when the machine was built,
+    /// and internal transitions do not reset it.
+    ///
+    /// Without the `std` feature, and on `wasm32-unknown-unknown`, the
+    /// default clock never advances, so this is zero unless a clock is set
+    /// with [`Self::set_clock`].
+    pub fn time_in_state(&self) -> Duration {
+        self.clock.now().saturating_sub(self.state_entered_at)
+    }
+
+    /// Time the machine with `clock` from now on.  Time in the current state
+    /// restarts from zero.
+    pub fn set_clock(&mut self, clock: impl Clock + 'a) {
+        self.clock = Box::new(clock);
+        self.state_entered_at = self.clock.now();
+    }
+
+    /// How many transitions have entered `state`, including re-entries.  The
+    /// machine being built in its initial state does not count, but
+    /// [`Self::start`] does.
+    pub fn entry_count(&self, state: S) -> u64 {
+        self.states
+            .id(&state)
+            .map_or(0, |id| self.entry_counts[usize::from(id)])
+    }
+
+    /// Run the initial state's entry actions, which building the machine
+    /// does not.  They see a transition from the initial state to itself
+    /// without a trigger.  A paused machine starts when it is resumed,
+    /// before any queued triggers are fired.  Does nothing if the machine
+    /// was started before or has already taken a transition.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Idle }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger {}
+    /// let mut builder = StateMachineBuilder::<State, Trigger, u32>::new(State::Idle);
+    /// builder.config(State::Idle).on_entry(|_, entries| *entries += 1);
+    /// let mut machine = builder.build(0).unwrap();
+    /// assert_eq!(*machine.object(), 0);
+    /// machine.start();
+    /// machine.start();
+    /// assert_eq!(*machine.object(), 1);
+    /// ```
+    pub fn start(&mut self) {
+        if self.lifecycle() != Lifecycle::NotStarted {
+            return;
+        }
+        if self.paused.is_some() {
+            self.start_on_resume = true;
+            return;
+        }
+        self.lifecycle = Lifecycle::Running;
+        let state = self.current_state;
+        let transition = self.stamp(Transition::without_trigger(state, state));
+        self.enter(&transition, self.current_index);
+        self.run_commands();
+    }
+
+    /// Shut the machine down: run the current state's exit actions, seeing a
+    /// transition from the state to itself without a trigger, and reject every
+    /// trigger from then on with [`StateMachineError::MachineStopped`].
+    /// Anything queued by [`Self::pause`] is rejected on resume.  Does
+    /// nothing if the machine is already stopped.
+    ///
+    /// ```
+    /// # use stateless_rs::{Lifecycle, StateMachineBuilder};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Connected }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Ping }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, bool>::new(State::Connected);
+    /// builder
+    ///     .config(State::Connected)
+    ///     .on_entry(|_, open| *open = true)
+    ///     .on_exit(|_, open| *open = false)
+    ///     .internal_transition(Trigger::Ping, |_, _| ());
+    /// let mut machine = builder.build(false).unwrap();
+    /// assert_eq!(machine.lifecycle(), Lifecycle::NotStarted);
+    /// machine.start();
+    /// assert!(*machine.object());
+    /// machine.stop();
+    /// assert!(!*machine.object());
+    /// assert_eq!(machine.lifecycle(), Lifecycle::Stopped);
+    /// assert!(machine.fire(Trigger::Ping).is_err());
+    /// ```
+    ///
+    /// Scheduled triggers and commands not yet carried out are dropped.
+    pub fn stop(&mut self) {
+        if self.lifecycle == Lifecycle::Stopped {
+            return;
+        }
+        self.lifecycle = Lifecycle::Stopped;
+        let state = self.current_state;
+        let transition = self.stamp(Transition::without_trigger(state, state));
+        self.exit(&transition);
+        self.commands.clear();
+        self.timers.clear();
+    }
+
+    pub fn lifecycle(&self) -> Lifecycle {
+        match self.lifecycle {
+            Lifecycle::NotStarted if self.last_transition.is_some() => Lifecycle::Running,
+            lifecycle => lifecycle,
+        }
+    }
+
+    /// Leave and re-enter the current state without a trigger, for
+    /// refreshing or retrying the current step.  This is a self-transition
+    /// like any other: exit and entry actions run, the state's entry guard
+    /// can redirect to its fallback, and transition handlers, coverage and
+    /// telemetry's [`TelemetrySink::transition_committed`] see it.  Having
+    /// no trigger, it goes around middleware and the telemetry hooks which
+    /// take one.
+    ///
+    /// Fails with [`StateMachineError::MachineStopped`] or
+    /// [`StateMachineError::MachinePaused`] while the machine is stopped or
+    /// paused, as [`Self::compensate_to`] does.
+    pub fn reenter(&mut self) -> Result<(), StateMachineError<S, T>> {
+        if let Some(error) = self.halted(None) {
+            return Err(error);
+        }
+        let started_at = self.telemetry_started_at();
+        let source = self.current_state;
+        let object: &O = self.object.get_mut();
+        let (destination, destination_index, refusals) = follow_entry_guards(
+            &self.state_representations,
+            source,
+            self.current_index,
+            object,
+            None,
+            |_, _| (),
+        )
+        .map_err(|state| StateMachineError::EntryGuardCycle { state })?;
+        let transition = Transition::without_trigger(source, destination);
+        for refusal in refusals {
+            transition.annotate(refusal);
+        }
+        self.execute(FirePlan {
+            transition,
+            destination_index: Some(destination_index),
+        });
+        self.report_committed(started_at);
+        self.run_commands();
+        Ok(())
+    }
+
+    /// Unwind to the most recent time the machine was in `state`, running
+    /// the compensations added with [`crate::StateConfig::on_compensate`] of
+    /// every state entered since, newest first, as a saga would.  The
+    /// compensations stand in for exit and entry actions, which do not run.
+    /// The machine then moves to `state` without a trigger, counting the
+    /// entry and raising transition events and telemetry's
+    /// [`TelemetrySink::transition_committed`] as usual.  Having no trigger,
+    /// it goes around middleware, as [`Self::reenter`] does.
+    ///
+    /// Fails with [`StateMachineError::NotInHistory`] if `state` is not in
+    /// the history.  The history is only kept when some state has
+    /// compensations.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Start, Reserved, Charged }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Next }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, Vec<&str>>::new(State::Start);
+    /// builder
+    ///     .config(State::Start)
+    ///     .permit(Trigger::Next, State::Reserved);
+    /// builder
+    ///     .config(State::Reserved)
+    ///     .permit(Trigger::Next, State::Charged)
+    ///     .on_compensate(|_, log| log.push("release"));
+    /// builder
+    ///     .config(State::Charged)
+    ///     .on_compensate(|_, log| log.push("refund"));
+    /// let mut machine = builder.build(Vec::new()).unwrap();
+    /// machine.fire(Trigger::Next).unwrap();
+    /// machine.fire(Trigger::Next).unwrap();
+    /// machine.compensate_to(State::Start).unwrap();
+    /// assert_eq!(machine.state(), State::Start);
+    /// assert_eq!(*machine.object(), ["refund", "release"]);
+    /// ```
+    pub fn compensate_to(&mut self, state: S) -> Result<(), StateMachineError<S, T>> {
+        if let Some(error) = self.halted(None) {
+            return Err(error);
+        }
+        let not_in_history = || StateMachineError::NotInHistory { state };
+        let started_at = self.telemetry_started_at();
+        let history = self.history.as_mut().ok_or_else(not_in_history)?;
+        let keep = match history.iter().rposition(|(t, _)| t.destination == state) {
+            Some(position) => position + 1,
+            None if state == self.initial_state => 0,
+            None => return Err(not_in_history()),
+        };
+        let object = self.object.get_mut();
+        for (transition, index) in history.drain(keep..).rev() {
+            self.state_representations[usize::from(index)].compensate(&transition, object);
+        }
+        let source = self.current_state;
+        let transition = self.stamp(Transition::without_trigger(source, state));
+        self.current_state = state;
+        self.current_index = self.states.id(&state).expect("every state is interned");
+        self.state_entered_at = self.clock.now();
+        self.entry_counts[usize::from(self.current_index)] += 1;
+        self.transition_event.fire_events(&transition);
+        self.last_transition = Some(transition);
+        self.report_committed(started_at);
+        Ok(())
+    }
+
+    /// Fire a trigger.  Will return `()` on success and a
+    /// [`crate::StateMachineError`] on failure
+    pub fn fire(&mut self, trigger: T) -> Result<(), StateMachineError<S, T>> {
+        let result = self.fire_without_commands(trigger);
+        self.run_commands();
+        result
+    }
+
+    /// [`Self::fire`], leaving any commands for [`Self::run_commands`]
+    pub(crate) fn fire_without_commands(
+        &mut self,
+        trigger: T,
+    ) -> Result<(), StateMachineError<S, T>> {
+        let source = self.current_state;
+        if self.paused == Some(PauseMode::Queue) && self.lifecycle != Lifecycle::Stopped {
+            self.paused_triggers
+                .push_back((trigger, self.payload.clone()));
+            return Ok(());
+        }
+        let started_at = self.telemetry_started_at();
+        for sink in self.telemetry.iter_mut() {
+            sink.fire_started(source, trigger);
+        }
+        let result = if let Some(error) = self.halted(Some(trigger)) {
+            Err(error)
+        } else {
+            let mut middlewares = core::mem::take(&mut self.middlewares);
+            let mut fire = |trigger| self.fireone(trigger);
+            let result = Next::new(&mut middlewares, &mut fire).run(trigger);
+            self.middlewares = middlewares;
+            result
+        };
+        if let Err(error) = &result {
+            if let Some(started_at) = started_at {
+                let duration = self.clock.now().saturating_sub(started_at);
+                for sink in self.telemetry.iter_mut() {
+                    sink.fire_rejected(source, trigger, error, duration);
+                }
+            }
+            self.last_rejection = Some(error.clone());
+        }
+        result
+    }
+
+    /// Fire every scheduled trigger which is due by the machine's clock,
+    /// earliest first.  A rejected trigger does not stop the rest; the first
+    /// rejection is returned once they have all been fired.  See
+    /// [`Command::Schedule`].
+    pub fn fire_due(&mut self) -> Result<(), StateMachineError<S, T>> {
+        let now = self.clock.now();
+        let mut first_error = None;
+        while let Some(index) = self
+            .timers
+            .iter()
+            .enumerate()
+            .filter(|(_, (due, _))| *due <= now)
+            .min_by_key(|(_, (due, _))| *due)
+            .map(|(index, _)| index)
+        {
+            let (_, trigger) = self.timers.remove(index);
+            if let Err(error) = self.fire(trigger) {
+                first_error.get_or_insert(error);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// When the next scheduled trigger is due by the machine's clock, for
+    /// deciding when to call [`Self::fire_due`]
+    pub fn next_due(&self) -> Option<Duration> {
+        self.timers.iter().map(|(due, _)| *due).min()
+    }
+
+    /// The events from [`Command::Emit`] since the last call, oldest first
+    pub fn take_emitted(&mut self) -> Vec<String> {
+        mem::take(&mut self.emitted)
+    }
+
+    /// Carry out the commands returned by command actions, including any
+    /// returned while doing so.  Triggers are fired as by [`Self::fire`]; a
+    /// rejection ends up in [`Self::last_rejection`] rather than failing
+    /// the fire which issued the command.
+    pub(crate) fn run_commands(&mut self) {
+        self.run_commands_with(|machine, trigger| {
+            let _ = machine.fire(trigger);
+        });
+    }
+
+    /// [`Self::run_commands`], firing triggers with `fire`
+    fn run_commands_with(&mut self, mut fire: impl FnMut(&mut Self, T)) {
+        while let Some(command) = self.commands.pop_front() {
+            match command {
+                Command::Fire(trigger) => fire(self, trigger),
+                Command::Schedule { trigger, after } => {
+                    self.timers.push((self.clock.now() + after, trigger));
+                }
+                Command::Cancel(trigger) => {
+                    let matching = self.trigger_matching;
+                    self.timers.retain(|(_, t)| !matching.matches(t, &trigger));
+                }
+                Command::Emit(event) => self.emitted.push(event),
+            }
+        }
+    }
+
+    /// Stop processing triggers until [`Self::resume`], for maintenance
+    /// windows or stepping through a long-running workflow.  `mode` decides
+    /// whether triggers fired meanwhile are rejected or kept for later.
+    /// Pausing again changes the mode and keeps anything already queued.
+    ///
+    /// ```
+    /// # use stateless_rs::{PauseMode, StateMachineBuilder};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Off, On }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Switch }
+    /// let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::Off);
+    /// builder.config(State::Off).permit(Trigger::Switch, State::On);
+    /// let mut machine = builder.build(()).unwrap();
+    /// machine.pause(PauseMode::Queue);
+    /// machine.fire(Trigger::Switch).unwrap();
+    /// assert_eq!(machine.state(), State::Off);
+    /// machine.resume().unwrap();
+    /// assert_eq!(machine.state(), State::On);
+    /// ```
+    pub fn pause(&mut self, mode: PauseMode) {
+        self.paused = Some(mode);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Process triggers again, first starting the machine if
+    /// [`Self::start`] was called while paused, then firing any triggers
+    /// queued meanwhile in the order they arrived.  A rejected trigger does
+    /// not stop the rest; the first rejection is returned once they have
+    /// all been fired.
+    pub fn resume(&mut self) -> Result<(), StateMachineError<S, T>> {
+        self.paused = None;
+        if core::mem::take(&mut self.start_on_resume) {
+            self.start();
+        }
+        let mut first_error = None;
+        while let Some((trigger, payload)) = self.paused_triggers.pop_front() {
+            self.payload = payload;
+            let result = self.fire(trigger);
+            self.payload = None;
+            if let Err(error) = result {
+                first_error.get_or_insert(error);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// The triggers queued while paused with [`PauseMode::Queue`], oldest
+    /// first
+    pub fn pending_triggers(&self) -> Vec<T> {
+        self.paused_triggers
+            .iter()
+            .map(|(trigger, _)| *trigger)
+            .collect()
+    }
+
+    /// How many triggers are queued, for deciding when to stop accepting
+    /// more
+    pub fn pending_count(&self) -> usize {
+        self.paused_triggers.len()
+    }
+
+    /// Remove the queued triggers without firing them, e.g. on entering an
+    /// error state, returning them oldest first.  Their payloads are dropped.
+    pub fn drain_pending(&mut self) -> Vec<T> {
+        self.paused_triggers
+            .drain(..)
+            .map(|(trigger, _)| trigger)
+            .collect()
+    }
+
+    /// Fire the trigger `name` parses to, for driving a machine from CLI
+    /// input, HTTP parameters or messages.  Deriving `strum::EnumString` on
+    /// the trigger enum is the easiest way to get a [`FromStr`].  Unknown
+    /// names are rejected with suggestions from the configured triggers'
+    /// `Debug` names.
+    ///
+    /// ```
+    /// # use stateless_rs::{StateMachineBuilder, StateMachineError};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Off, On }
+    /// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumString)]
+    /// enum Trigger { Switch }
+    ///
+    /// let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::Off);
+    /// builder.config(State::Off).permit(Trigger::Switch, State::On);
+    /// let mut machine = builder.build(()).unwrap();
+    /// assert_eq!(
+    ///     machine.fire_by_name("Swtich"),
+    ///     Err(StateMachineError::UnknownTrigger {
+    ///         name: "Swtich".to_string(),
+    ///         suggestions: vec!["Switch".to_string()],
+    ///     })
+    /// );
+    /// assert_eq!(
+    ///     machine.fire_by_name("swtich").unwrap_err().to_string(),
+    ///     r#"unknown trigger "swtich"; did you mean "Switch"?"#
+    /// );
+    /// machine.fire_by_name("Switch").unwrap();
+    /// assert_eq!(machine.state(), State::On);
+    /// ```
+    pub fn fire_by_name(&mut self, name: &str) -> Result<(), StateMachineError<S, T>>
+    where
+        T: FromStr,
+    {
+        let trigger = name
+            .parse()
+            .map_err(|_| StateMachineError::UnknownTrigger {
+                name: String::from(name),
+                suggestions: did_you_mean(name, self.triggers().iter().map(|t| format!("{t:?}"))),
+            })?;
+        self.fire(trigger)
+    }
+
+    /// Fire a trigger with an untyped payload, which actions and transition
+    /// handlers get back with [`Transition::payload_downcast`], or typed with
+    /// [`crate::StateConfig::on_entry_from_with`].  For
+    /// prototyping; an enum trigger carrying the data is checked by the
+    /// compiler, see [`crate::StateMachineBuilder::match_triggers_by_discriminant`].
+    pub fn fire_with_any(
+        &mut self,
+        trigger: T,
+        payload: impl Any + Send + Sync,
+    ) -> Result<(), StateMachineError<S, T>> {
+        self.payload = Some(Arc::new(payload));
+        let result = self.fire(trigger);
+        self.payload = None;
+        result
+    }
+
+    /// Fire a trigger and return the values of type `R` its actions passed
+    /// to [`Transition::output`], in the order they were output, for
+    /// request/response protocols.  Values from transitions fired by
+    /// [`Command::Fire`] are included.  Values of any other type are
+    /// dropped, so a machine can answer different callers with different
+    /// types.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Idle, Connected }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Connect }
+    /// let mut builder = StateMachineBuilder::<_, _, ()>::new(State::Idle);
+    /// builder
+    ///     .config(State::Connected)
+    ///     .on_entry(|t, _| t.output("welcome"));
+    /// builder.config(State::Idle).permit(Trigger::Connect, State::Connected);
+    /// let mut machine = builder.build(()).unwrap();
+    /// let replies: Vec<&str> = machine.fire_with_output(Trigger::Connect).unwrap();
+    /// assert_eq!(replies, ["welcome"]);
+    /// ```
+    pub fn fire_with_output<R>(&mut self, trigger: T) -> Result<Vec<R>, StateMachineError<S, T>>
+    where
+        R: Any + Send,
+    {
+        self.outputs = Some(Vec::new());
+        let result = self.fire(trigger);
+        let outputs = self.outputs.take().unwrap_or_default();
+        result.map(|()| {
+            outputs
+                .into_iter()
+                .filter_map(|output| output.downcast::<R>().ok())
+                .map(|output| *output)
+                .collect()
+        })
+    }
+
+    /// Fire the triggers in `triggers` one at a time as the returned iterator
+    /// is advanced, yielding the transition each made or why it was
+    /// rejected.  A rejection doesn't stop the script.  A paused machine
+    /// rejects triggers here even when pausing would queue them.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Off, On }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Switch }
+    /// let mut builder = StateMachineBuilder::<_, _, ()>::new(State::Off);
+    /// builder.config(State::Off).permit(Trigger::Switch, State::On);
+    /// builder.config(State::On).permit(Trigger::Switch, State::Off);
+    /// let mut machine = builder.build(()).unwrap();
+    /// let visited: Vec<State> = machine
+    ///     .run_script([Trigger::Switch; 3])
+    ///     .map(|transition| transition.unwrap().destination)
+    ///     .collect();
+    /// assert_eq!(visited, [State::On, State::Off, State::On]);
+    /// ```
+    pub fn run_script<I>(&mut self, triggers: I) -> Script<'_, 'a, S, T, O, St, A, H, I::IntoIter>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Script::new(self, triggers.into_iter())
+    }
+
+    /// Fire every trigger in `triggers` in a tight loop, for replays and
+    /// simulations.  Actions and middlewares run as usual, but transition
+    /// events are not raised and telemetry sinks are not told; the returned
+    /// summary stands in for them.
+    /// Commands are carried out after each trigger, as with [`Self::fire`],
+    /// and the triggers they fire are processed the same quiet way and
+    /// counted in the summary.  Processing stops at the first rejected
+    /// trigger from `triggers`; a rejected command only ends up in
+    /// [`Self::last_rejection`].  A paused machine rejects triggers here
+    /// even when pausing would queue them.
+    pub fn process_all<I>(&mut self, triggers: I) -> BulkSummary<S, T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut summary = BulkSummary {
+            fired: 0,
+            transitions: 0,
+            internal: 0,
+            final_state: self.current_state,
+            error: None,
+        };
+        let mut middlewares = core::mem::take(&mut self.middlewares);
+        let telemetry = core::mem::take(&mut self.telemetry);
+        for trigger in triggers {
+            if let Err(error) = self.fire_in_bulk(&mut middlewares, trigger, &mut summary) {
+                self.last_rejection = Some(error.clone());
+                summary.error = Some(error);
+                break;
+            }
+            self.run_commands_with(|machine, trigger| {
+                if let Err(error) = machine.fire_in_bulk(&mut middlewares, trigger, &mut summary) {
+                    machine.last_rejection = Some(error);
+                }
+            });
+        }
+        self.middlewares = middlewares;
+        self.telemetry = telemetry;
+        summary.final_state = self.current_state;
+        summary
+    }
+
+    /// Start counting which configured transitions are taken, from zero
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(CoverageReport::new(&self.info()));
     }
 
-    /// Returns the current state of the state machine
-    pub fn state(&self) -> S {
-        self.current_state
-    }
+    /// Every configured transition and how often it was taken, or `None`
+    /// unless [`Self::enable_coverage`] was called
+    pub fn coverage_report(&self) -> Option<&CoverageReport<S, T>> {
+        self.coverage.as_ref()
+    }
+
+    /// Call `f` whenever firing reaches a breakpoint, or at every
+    /// [`DebugPhase`] while stepping.  The machine waits for `f` to return,
+    /// so it can inspect the transition and state object or block for a
+    /// debugger front end.  Replaces any callback set before.
+    pub fn on_debug<F>(&mut self, f: F)
+    where
+        F: FnMut(&DebugEvent<'_, S, T>, &O) + 'a,
+    {
+        self.debugger.attach(f);
+    }
+
+    /// Remove the debug callback; breakpoints and stepping are kept
+    pub fn detach_debugger(&mut self) {
+        self.debugger.detach();
+    }
+
+    /// Whether the debug callback is called at every phase of every fire
+    pub fn set_stepping(&mut self, stepping: bool) {
+        self.debugger.stepping = stepping;
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint<S, T>) {
+        self.debugger.breakpoints.push(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint<S, T>) {
+        self.debugger.breakpoints.retain(|b| *b != breakpoint);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.debugger.breakpoints.clear();
+    }
+
+    /// The most recent transition, including internal ones.  `None` until a
+    /// trigger has been fired successfully.
+    pub fn last_transition(&self) -> Option<&Transition<S, T>> {
+        self.last_transition.as_ref()
+    }
+
+    /// The error from the most recent [`Self::fire`] that failed.  It is not
+    /// cleared by later successful fires.
+    pub fn last_rejection(&self) -> Option<&StateMachineError<S, T>> {
+        self.last_rejection.as_ref()
+    }
+
+    /// Call `f` after every transition until [`Self::remove_transitioned`]
+    /// is called with the returned id
+    pub fn on_transitioned<F>(&mut self, f: F) -> SubscriptionId
+    where
+        F: FnMut(&Transition<S, T>) + 'a,
+    {
+        self.transition_event.add_event(f)
+    }
+
+    /// Call `f` with the referent of `weak` after every transition, until
+    /// the referent is dropped.  Unlike capturing an `Rc` in
+    /// [`Self::on_transitioned`] this doesn't keep it alive, so a short-lived
+    /// subscriber such as a UI widget doesn't leak.
+    pub fn on_transitioned_weak<R, F>(&mut self, weak: Weak<R>, f: F) -> SubscriptionId
+    where
+        R: 'a,
+        F: FnMut(&R, &Transition<S, T>) + 'a,
+    {
+        self.transition_event.add_event_weak(weak, f)
+    }
+
+    /// Call `f` after every transition for as long as the returned
+    /// [`Subscription`] is alive
+    pub fn subscribe_transitioned<F>(&mut self, f: F) -> Subscription
+    where
+        F: FnMut(&Transition<S, T>) + 'a,
+    {
+        self.transition_event.subscribe(f)
+    }
+
+    /// Remove a transition handler added here or on the builder.  Returns
+    /// `false` if there was no such handler.
+    pub fn remove_transitioned(&mut self, id: SubscriptionId) -> bool {
+        self.transition_event.remove(id)
+    }
+
+    /// Report everything fired from now on to `sink`, after any sinks added
+    /// before
+    pub fn add_telemetry_sink(&mut self, sink: impl TelemetrySink<S, T> + 'a) {
+        self.telemetry.push(Box::new(sink));
+    }
+
+    fn fireone(&mut self, trigger: T) -> Result<(), StateMachineError<S, T>> {
+        let started_at = self.telemetry_started_at();
+        let plan = self.plan(trigger)?;
+        self.execute(plan);
+        self.report_committed(started_at);
+        Ok(())
+    }
+
+    /// Tell telemetry about the transition just made, if any sink was
+    /// listening when it started
+    fn report_committed(&mut self, started_at: Option<Duration>) {
+        if let (Some(started_at), Some(transition)) = (started_at, &self.last_transition) {
+            let duration = self.clock.now().saturating_sub(started_at);
+            for sink in self.telemetry.iter_mut() {
+                sink.transition_committed(transition, duration);
+            }
+        }
+    }
+
+    /// Why `trigger`, or a transition without one, cannot be processed at
+    /// all, if the machine is stopped or paused
+    fn halted(&self, trigger: Option<T>) -> Option<StateMachineError<S, T>> {
+        let state = self.current_state;
+        if self.lifecycle == Lifecycle::Stopped {
+            Some(StateMachineError::MachineStopped { state, trigger })
+        } else if self.paused.is_some() {
+            Some(StateMachineError::MachinePaused { state, trigger })
+        } else {
+            None
+        }
+    }
+
+    /// Only read the clock when there is someone to report to
+    fn telemetry_started_at(&self) -> Option<Duration> {
+        (!self.telemetry.is_empty()).then(|| self.clock.now())
+    }
+
+    /// Work out what firing `trigger` will do without running anything.
+    /// This is the only place a trigger can be rejected.
+    fn plan(&mut self, trigger: T) -> Result<FirePlan<S, T>, StateMachineError<S, T>> {
+        let source = self.current_state;
+        let trigger_id = self
+            .triggers
+            .id(&self.trigger_matching.key(&trigger))
+            .ok_or(StateMachineError::TriggerNotPermitted {
+                state: source,
+                trigger,
+            })?;
+        if let Some(parameters) = &self.parameter_types[usize::from(trigger_id)] {
+            if !parameters.accepts(self.payload.as_ref()) {
+                return Err(StateMachineError::InvalidTriggerParameters {
+                    trigger,
+                    expected: parameters.name,
+                });
+            }
+        }
+        let representation = &self.state_representations[usize::from(self.current_index)];
+        let telemetry = &mut self.telemetry;
+        let object: &O = self.object.get_mut();
+        let payload = self.payload.as_ref();
+        let behaviour = representation.get_interned_behaviour(
+            trigger_id,
+            trigger,
+            object,
+            payload,
+            |guard, met| {
+                for sink in telemetry.iter_mut() {
+                    sink.guard_evaluated(source, trigger, guard.description(), met);
+                }
+            },
+        )?;
+        let annotation = behaviour.guard().and_then(|guard| guard.annotation(object));
+        let transition = |destination| {
+            let transition = Transition::new(source, trigger, destination)
+                .with_payload(self.payload.clone())
+                .with_label(behaviour.label.clone());
+            if let Some(annotation) = annotation {
+                transition.annotate(annotation);
+            }
+            transition
+        };
+        Ok(match behaviour.resolve(source, object, payload) {
+            Resolution::Transition {
+                destination,
+                destination_index,
+            } => {
+                let (destination, destination_index, refusals) = follow_entry_guards(
+                    &self.state_representations,
+                    destination,
+                    destination_index,
+                    object,
+                    payload,
+                    |guard, met| {
+                        for sink in telemetry.iter_mut() {
+                            sink.guard_evaluated(source, trigger, guard, met);
+                        }
+                    },
+                )
+                .map_err(|state| StateMachineError::EntryGuardCycle { state })?;
+                let transition = transition(destination);
+                for refusal in refusals {
+                    transition.annotate(refusal);
+                }
+                FirePlan {
+                    transition,
+                    destination_index: Some(destination_index),
+                }
+            }
+            Resolution::Internal => FirePlan {
+                transition: transition(source).into_internal(),
+                destination_index: None,
+            },
+            Resolution::InvalidParameters { expected } => {
+                return Err(StateMachineError::InvalidTriggerParameters { trigger, expected })
+            }
+            Resolution::UndeclaredDestination { destination } => {
+                return Err(StateMachineError::Rejected {
+                    trigger,
+                    reason: format!("{destination:?} is not a declared destination"),
+                })
+            }
+        })
+    }
+
+    /// Run the actions and events for a planned transition and move to its
+    /// destination
+    fn execute(&mut self, plan: FirePlan<S, T>) {
+        let transition = self.apply(plan);
+        self.transition_event.fire_events(&transition);
+        self.last_transition = Some(transition);
+    }
+
+    /// Run the actions for a planned transition and move to its destination,
+    /// without raising events
+    fn apply(&mut self, plan: FirePlan<S, T>) -> Transition<S, T> {
+        let FirePlan {
+            transition,
+            destination_index,
+        } = plan;
+        let transition = self.stamp(transition);
+        match destination_index {
+            Some(destination_index) => {
+                self.exit(&transition);
+                self.enter(&transition, destination_index);
+            }
+            None => {
+                let object = self.object.get_mut();
+                self.debugger.check(
+                    DebugPhase::Internal,
+                    &transition,
+                    self.trigger_matching,
+                    object,
+                );
+                let telemetry = &mut self.telemetry;
+                self.state_representations[usize::from(self.current_index)].fire_internal_actions(
+                    &transition,
+                    object,
+                    |action| report_action(telemetry, DebugPhase::Internal, &transition, action),
+                );
+                self.actions.on_internal(&transition, object);
+            }
+        }
+        if let Some(coverage) = &mut self.coverage {
+            let matching = self.trigger_matching;
+            coverage.record(&transition, destination_index.is_none(), matching);
+        }
+        self.collect_outputs(&transition);
+        transition
+    }
+
+    /// Move the outputs of `transition`'s actions to [`Self::fire_with_output`],
+    /// or drop them if nothing is asking
+    fn collect_outputs(&mut self, transition: &Transition<S, T>) {
+        let outputs = transition.take_outputs();
+        if let Some(collected) = &mut self.outputs {
+            collected.extend(outputs);
+        }
+    }
+
+    /// Number and timestamp a transition the machine is about to make
+    fn stamp(&mut self, transition: Transition<S, T>) -> Transition<S, T> {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        transition.stamped(sequence, self.clock.now())
+    }
+
+    /// Run the current state's exit actions for `transition`
+    fn exit(&mut self, transition: &Transition<S, T>) {
+        let object = self.object.get_mut();
+        let matching = self.trigger_matching;
+        self.debugger
+            .check(DebugPhase::Exit, transition, matching, object);
+        let telemetry = &mut self.telemetry;
+        self.state_representations[usize::from(self.current_index)].exit(
+            transition,
+            object,
+            |action| report_action(telemetry, DebugPhase::Exit, transition, action),
+        );
+        self.actions.on_exit(transition, object);
+        self.debugger
+            .check(DebugPhase::Transition, transition, matching, object);
+    }
+
+    /// Move to the destination of `transition` and run its entry actions
+    fn enter(&mut self, transition: &Transition<S, T>, destination_index: u16) {
+        let object = self.object.get_mut();
+        self.current_state = transition.destination;
+        self.current_index = destination_index;
+        self.state_entered_at = self.clock.now();
+        if let Some(history) = &mut self.history {
+            history.push((transition.clone(), destination_index));
+        }
+        let destination_index = usize::from(destination_index);
+        self.entry_counts[destination_index] += 1;
+        self.debugger
+            .check(DebugPhase::Entry, transition, self.trigger_matching, object);
+        let telemetry = &mut self.telemetry;
+        self.state_representations[destination_index].enter(transition, object, |action| {
+            report_action(telemetry, DebugPhase::Entry, transition, action)
+        });
+        self.actions.on_entry(transition, object);
+        let commands = self.state_representations[destination_index].commands(transition, object);
+        self.commands.extend(commands);
+    }
+
+    /// Pass one trigger for [`Self::process_all`] through `middlewares`,
+    /// taken out of the machine for the duration
+    fn fire_in_bulk(
+        &mut self,
+        middlewares: &mut [BoxedMiddleware<S, T>],
+        trigger: T,
+        summary: &mut BulkSummary<S, T>,
+    ) -> Result<(), StateMachineError<S, T>> {
+        if middlewares.is_empty() {
+            return self.fire_quietly(trigger, summary);
+        }
+        let mut fire = |trigger| self.fire_quietly(trigger, summary);
+        Next::new(middlewares, &mut fire).run(trigger)
+    }
+
+    /// Plan and apply one trigger for [`Self::process_all`]
+    fn fire_quietly(
+        &mut self,
+        trigger: T,
+        summary: &mut BulkSummary<S, T>,
+    ) -> Result<(), StateMachineError<S, T>> {
+        if let Some(error) = self.halted(Some(trigger)) {
+            return Err(error);
+        }
+        let plan = self.plan(trigger)?;
+        let internal = plan.destination_index.is_none();
+        let transition = self.apply(plan);
+        summary.fired += 1;
+        if internal {
+            summary.internal += 1;
+        } else {
+            summary.transitions += 1;
+        }
+        self.last_transition = Some(transition);
+        Ok(())
+    }
+}
+
+/// Where a transition into `destination` ends up once entry guards have had
+/// their say, with why each refusing guard redirected it to its fallback.
+/// Each guard evaluated is passed to `report` with whether it was met.
+/// Fails with the intended destination if the guards redirect in a cycle.
+fn follow_entry_guards<S: Copy + Debug, T, O>(
+    representations: &[StateRepresentation<'_, S, T, O>],
+    mut destination: S,
+    mut destination_index: u16,
+    object: &O,
+    payload: Option<&Payload>,
+    mut report: impl FnMut(&str, bool),
+) -> Result<(S, u16, Vec<String>), S> {
+    let intended = destination;
+    let mut refusals = Vec::new();
+    while let Some(entry_guard) = &representations[usize::from(destination_index)].entry_guard {
+        let guard = &entry_guard.guard;
+        let met = guard.evaluate_with(object, payload);
+        report(guard.description(), met);
+        if met {
+            break;
+        }
+        if refusals.len() == representations.len() {
+            return Err(intended);
+        }
+        refusals.push(format!(
+            "entry to {destination:?} refused: {}",
+            guard.description()
+        ));
+        destination = entry_guard.fallback;
+        destination_index = entry_guard.fallback_index;
+    }
+    Ok((destination, destination_index, refusals))
+}
+
+fn report_action<S: Copy, T: Copy>(
+    telemetry: &mut [Box<dyn TelemetrySink<S, T> + '_>],
+    phase: DebugPhase,
+    transition: &Transition<S, T>,
+    action: &ActionInfo,
+) {
+    for sink in telemetry.iter_mut() {
+        sink.action_run(phase, transition, action);
+    }
+}
+
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn default_clock<'a>() -> Box<dyn Clock + 'a> {
+    Box::new(crate::clock::SystemClock::new())
+}
+
+#[cfg(not(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+)))]
+fn default_clock<'a>() -> Box<dyn Clock + 'a> {
+    Box::new(crate::clock::ManualClock::new())
+}
+
+/// Everything firing a trigger will do, worked out before any action runs
+struct FirePlan<S, T> {
+    transition: Transition<S, T>,
+    /// Interned id of the state being entered, `None` for internal
+    /// transitions
+    destination_index: Option<u16>,
+}
+
+impl<S, T, O, St, A, H> Display for StateMachine<'_, S, T, O, St, A, H>
+where
+    S: Debug,
+    St: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "StateMachine ( state: {:?}, object: {:?} )",
+            self.current_state, self.object
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn entry_into_unconfigured_state_works() -> eyre::Result<()> {
+        // If the user hasn't explicitly configured a state to do something, it
+        // is still part of the State enum and is a valid destination
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(())?;
+
+        assert_eq!(machine.state(), State::State1);
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        Ok(())
+    }
+
+    #[test]
+    fn actions_can_borrow_from_scope() -> eyre::Result<()> {
+        let entered = std::cell::Cell::new(0);
+        let destinations = std::cell::RefCell::new(Vec::new());
+        {
+            let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+            builder
+                .config(State::State1)
+                .permit(Trigger::Trig, State::State2);
+            builder
+                .config(State::State2)
+                .on_entry(|_t, _o| entered.set(entered.get() + 1));
+            builder.on_transitioned(|t| destinations.borrow_mut().push(t.destination));
+
+            let mut machine = builder.build(())?;
+            machine.fire(Trigger::Trig)?;
+        }
+        assert_eq!(entered.get(), 1);
+        assert_eq!(*destinations.borrow(), vec![State::State2]);
+        Ok(())
+    }
+
+    #[test]
+    fn planning_runs_nothing_until_executed() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .on_exit(|_, exits| *exits += 1);
+        let mut machine = builder.build(0)?;
+
+        let plan = machine.plan(Trigger::Trig)?;
+        assert_eq!(plan.destination_index, Some(1));
+        assert_eq!(machine.state(), State::State1);
+        assert_eq!(*machine.object(), 0);
+
+        machine.execute(plan);
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn switching_hasher_keeps_machine_working() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1);
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+
+        let mut machine = machine.with_hasher::<crate::FxBuildHasher>();
+        assert_eq!(machine.state(), State::State2);
+        machine.fire(Trigger::Trig2)?;
+        assert_eq!(machine.state(), State::State1);
+        assert_eq!(machine.entry_count(State::State1), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn permitted_triggers_are_in_configuration_order() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(Trigger::Trig2, State::State2, |o: &i32| *o > 0)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(0)?;
+
+        assert_eq!(machine.permitted_triggers(), vec![Trigger::Trig]);
+        *machine.object_mut() = 1;
+        assert_eq!(
+            machine.permitted_triggers(),
+            vec![Trigger::Trig2, Trigger::Trig]
+        );
+        Ok(())
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Command {
+        Goto(u8),
+        Stop,
+    }
+
+    #[test]
+    fn payload_triggers_match_by_discriminant() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, Vec<u8>>::new(State::State1);
+        builder.match_triggers_by_discriminant();
+        builder
+            .config(State::State1)
+            .permit(Command::Goto(0), State::State2);
+        builder.config(State::State2).on_entry(|t, seen| {
+            if let Some(Command::Goto(n)) = t.trigger {
+                seen.push(n);
+            }
+        });
+        builder.on_fired(Command::Goto(0), |t| {
+            assert_eq!(t.trigger, Some(Command::Goto(3)));
+        });
+        let mut machine = builder.build(Vec::new())?;
+
+        machine.fire(Command::Goto(3))?;
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), vec![3]);
+        assert_eq!(
+            machine.fire(Command::Stop),
+            Err(StateMachineError::TriggerNotPermitted {
+                state: State::State2,
+                trigger: Command::Stop
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn on_entry_from_matches_payload_triggers_by_discriminant() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, Vec<u8>>::new(State::State1);
+        builder.match_triggers_by_discriminant();
+        builder
+            .config(State::State1)
+            .permit(Command::Goto(0), State::State2);
+        builder
+            .config(State::State2)
+            .on_entry_from(Command::Goto(0), |t, seen| {
+                if let Some(Command::Goto(n)) = t.trigger {
+                    seen.push(n);
+                }
+            })
+            .on_entry_from(Command::Stop, |_, seen| seen.push(0));
+        let mut machine = builder.build(Vec::new())?;
+
+        machine.fire(Command::Goto(5))?;
+        assert_eq!(*machine.object(), vec![5]);
+        Ok(())
+    }
+
+    #[test]
+    fn memory_stats_count_configuration() -> eyre::Result<()> {
+        let builder = StateMachineBuilder::<_, Trigger, ()>::new(State::State1);
+        let machine = builder.build(())?;
+        let empty = machine.memory_stats();
+        assert_eq!(
+            (empty.representations, empty.behaviours, empty.actions),
+            (1, 0, 0)
+        );
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .permit_if(Trigger::Trig2, State::State2, |_: &()| true)
+            .on_exit(|_, _| ());
+        builder
+            .config(State::State2)
+            .on_entry(|_, _| ())
+            .internal_transition(Trigger::Trig, |_, _| ());
+        let machine = builder.build(())?;
+        let stats = machine.memory_stats();
+        assert_eq!(
+            (stats.representations, stats.behaviours, stats.actions),
+            (2, 3, 3)
+        );
+        assert!(stats.behaviour_bytes > 0);
+        assert!(stats.total_bytes() > empty.total_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn can_fire_follows_current_state_and_guards() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, bool>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(Trigger::Trig, State::State2, |allowed: &bool| *allowed);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1);
+        let mut machine = builder.build(false)?;
+
+        assert!(!machine.can_fire(Trigger::Trig));
+        *machine.object_mut() = true;
+        assert!(machine.can_fire(Trigger::Trig));
+        assert!(!machine.can_fire(Trigger::Trig2));
+        machine.fire(Trigger::Trig)?;
+        assert!(machine.can_fire(Trigger::Trig2));
+        assert!(!machine.can_fire(Trigger::Trig));
+        Ok(())
+    }
+
+    #[test]
+    fn otherwise_is_taken_only_when_no_guard_is_met() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, u32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(Trigger::Trig, State::State2, |n: &u32| *n > 0)
+            .permit_if(Trigger::Trig, State::State1, |n: &u32| *n > 1)
+            .otherwise(State::State1);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1);
+        let mut machine = builder.build(0)?;
+
+        assert!(machine.can_fire(Trigger::Trig));
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State1);
+        *machine.object_mut() = 2;
+        assert!(matches!(
+            machine.fire(Trigger::Trig),
+            Err(StateMachineError::AmbiguousTransition { .. })
+        ));
+        *machine.object_mut() = 1;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        Ok(())
+    }
+
+    #[test]
+    fn last_unguarded_permit_wins() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        Ok(())
+    }
+
+    #[test]
+    fn otherwise_before_any_transition_fails_the_build() {
+        let mut builder = StateMachineBuilder::<_, Trigger, ()>::new(State::State1);
+        builder.config(State::State1).otherwise(State::State2);
+        assert_eq!(
+            builder.build(()).err(),
+            Some(StateMachineError::OtherwiseWithoutTransition {
+                state: State::State1
+            })
+        );
+    }
+
+    #[test]
+    fn process_all_summarises_instead_of_raising_events() -> eyre::Result<()> {
+        let events = std::cell::Cell::new(0);
+        let mut builder = StateMachineBuilder::<_, _, u32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1)
+            .internal_transition(Trigger::Trig2, |_, count| *count += 1);
+        builder.on_transitioned(|_| events.set(events.get() + 1));
+        let mut machine = builder.build(0)?;
+
+        let triggers = [Trigger::Trig, Trigger::Trig2, Trigger::Trig, Trigger::Trig];
+        let summary = machine.process_all(triggers);
+        assert_eq!(
+            summary,
+            BulkSummary {
+                fired: 4,
+                transitions: 3,
+                internal: 1,
+                final_state: State::State2,
+                error: None,
+            }
+        );
+        assert_eq!(*machine.object(), 1);
+        assert_eq!(machine.entry_count(State::State2), 2);
+        assert_eq!(machine.last_transition().unwrap().source, State::State1);
+        drop(machine);
+        assert_eq!(events.get(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn process_all_stops_at_first_rejection() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(())?;
+
+        let summary = machine.process_all([Trigger::Trig, Trigger::Trig, Trigger::Trig]);
+        assert_eq!(summary.fired, 1);
+        assert_eq!(
+            summary.error,
+            Some(StateMachineError::TriggerNotPermitted {
+                state: State::State2,
+                trigger: Trigger::Trig
+            })
+        );
+        assert_eq!(machine.last_rejection(), summary.error.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn process_all_carries_out_commands_after_each_trigger() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1)
+            .on_entry_commands(|_, _| vec![crate::Command::Fire(Trigger::Trig2)]);
+        let mut machine = builder.build(())?;
+
+        let summary = machine.process_all([Trigger::Trig, Trigger::Trig]);
+        assert_eq!(summary.error, None);
+        assert_eq!(summary.fired, 4);
+        assert_eq!(summary.final_state, State::State1);
+        Ok(())
+    }
+
+    #[test]
+    fn dot_export_labels_guards_actions_and_styles() -> eyre::Result<()> {
+        fn notify(_: &Transition<State, Trigger>, _: &mut u32) {}
+        struct Notify;
+        impl crate::EntryAction<State, Trigger, u32> for Notify {
+            fn on_entry(&mut self, _: &Transition<State, Trigger>, _: &mut u32) {}
+
+            fn description(&self) -> &str {
+                "notify"
+            }
+        }
+
+        let mut builder = StateMachineBuilder::<_, _, u32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .tag("start")
+            .permit_if(
+                Trigger::Trig,
+                State::State2,
+                crate::Guard::new("ready", |_| true),
+            )
+            .on_exit(notify);
+        builder
+            .config(State::State2)
+            .on_entry_obj(Notify)
+            .internal_transition(Trigger::Trig2, notify);
+        let machine = builder.build(0)?;
+
+        let dot = machine.to_dot_with_style(|state| {
+            if machine
+                .state_metadata(state)
+                .is_some_and(|m| m.has_tag("start"))
+            {
+                NodeStyle::default().fill_color("green")
+            } else {
+                NodeStyle::default()
+            }
+        });
+        assert_eq!(
+            dot,
+            r#"digraph {
+    "State1" [label="State1\nexit / action", style="filled", fillcolor="green"];
+    "State2" [label="State2\nentry / notify"];
+    "State1" -> "State2" [label="Trig [ready]"];
+    "State2" -> "State2" [label="Trig2 / action", style=dashed];
+}
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn live_diagrams_highlight_current_state_and_last_transition() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1);
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+
+        assert_eq!(
+            machine.to_dot_live(),
+            r#"digraph {
+    "State1" [label="State1"];
+    "State2" [label="State2", style="filled", fillcolor="lightblue"];
+    "State1" -> "State2" [label="Trig", penwidth=2, color="blue"];
+    "State2" -> "State1" [label="Trig"];
+}
+"#
+        );
+        assert_eq!(
+            machine.to_mermaid_live(),
+            "stateDiagram-v2
+    state \"State1\" as State1
+    state \"State2\" as State2
+    State1 --> State2 : Trig (last)
+    State2 --> State1 : Trig
+    classDef current fill:#add8e6,font-weight:bold
+    class State2 current
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn info_describes_configuration() -> eyre::Result<()> {
+        fn notify(_: &Transition<State, Trigger>, _: &mut ()) {}
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State2)
+            .describe("second")
+            .on_entry(notify)
+            .internal_transition(Trigger::Trig2, notify);
+        builder
+            .config(State::State1)
+            .permit_if(
+                Trigger::Trig,
+                State::State2,
+                crate::Guard::new("ready", |_| true),
+            )
+            .tag_transition("external");
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+
+        let info = machine.info();
+        assert_eq!(info.initial_state, State::State1);
+        let states: Vec<_> = info.states.iter().map(|s| s.state).collect();
+        assert_eq!(states, vec![State::State1, State::State2]);
+
+        let first = info.state(State::State1).expect("State1 is configured");
+        assert_eq!(first.transitions.len(), 1);
+        let permit = &first.transitions[0];
+        assert_eq!(permit.destination, Some(State::State2));
+        assert_eq!(permit.guard_description.as_deref(), Some("ready"));
+        assert!(permit.metadata.has_tag("external"));
+
+        let second = info.state(State::State2).expect("State2 is configured");
+        assert_eq!(second.metadata.description.as_deref(), Some("second"));
+        assert_eq!(second.entry_actions.len(), 1);
+        let internal = &second.transitions[0];
+        assert_eq!(internal.destination, None);
+        assert_eq!(internal.destination_from(State::State2), State::State2);
+        assert_eq!(internal.actions.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn debugger_steps_through_phases_and_stops_at_breakpoints() -> eyre::Result<()> {
+        let hits = std::cell::RefCell::new(Vec::new());
+        let mut builder = StateMachineBuilder::<_, _, u32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .on_exit(|_, count| *count += 1);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        let mut machine = builder.build(0)?;
+        machine.on_debug(|event, count| {
+            hits.borrow_mut()
+                .push((event.phase, event.breakpoint, *count))
+        });
+
+        machine.set_stepping(true);
+        machine.fire(Trigger::Trig)?;
+        machine.set_stepping(false);
+        machine.add_breakpoint(Breakpoint::Trigger(Trigger::Trig2));
+        machine.add_breakpoint(Breakpoint::EnterState(State::State1));
+        machine.fire(Trigger::Trig2)?;
+        machine.fire(Trigger::Trig)?;
+        machine.clear_breakpoints();
+        machine.fire(Trigger::Trig)?;
+        drop(machine);
+
+        assert_eq!(
+            hits.into_inner(),
+            vec![
+                (DebugPhase::Exit, None, 0),
+                (DebugPhase::Transition, None, 1),
+                (DebugPhase::Entry, None, 1),
+                (
+                    DebugPhase::Internal,
+                    Some(Breakpoint::Trigger(Trigger::Trig2)),
+                    1
+                ),
+                (
+                    DebugPhase::Entry,
+                    Some(Breakpoint::EnterState(State::State1)),
+                    1
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn coverage_lists_untested_transitions() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        let mut machine = builder.build(())?;
+        assert!(machine.coverage_report().is_none());
+
+        machine.enable_coverage();
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
+        machine.fire(Trigger::Trig2)?;
+        let report = machine.coverage_report().expect("coverage is enabled");
+        assert!(!report.is_complete());
+        assert_eq!(report.edges[2].hits, 2);
+        assert_eq!(
+            format!("{report}"),
+            "1 of 3 transitions untested\n  State2 --Trig--> State1"
+        );
+
+        machine.fire(Trigger::Trig)?;
+        let report = machine.coverage_report().expect("coverage is enabled");
+        assert!(report.is_complete(), "{report}");
+        assert_eq!(report.ratio(), 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn fire_for_not_defined_throws_error() -> eyre::Result<()> {
+        let mut machine = StateMachineBuilder::new(State::State2).build(())?;
+        let result = machine.fire(Trigger::Trig);
+        assert!(result.is_err());
+        let error = result.err().unwrap();
+        assert_eq!(
+            error,
+            StateMachineError::TriggerNotPermitted {
+                state: State::State2,
+                trigger: Trigger::Trig
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn last_transition_and_rejection_are_kept() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(())?;
+        assert!(machine.last_transition().is_none());
+        assert!(machine.last_rejection().is_none());
+
+        machine.fire(Trigger::Trig)?;
+        let last = machine.last_transition().unwrap();
+        assert_eq!(
+            (last.source, last.trigger, last.destination),
+            (State::State1, Some(Trigger::Trig), State::State2)
+        );
+
+        assert!(machine.fire(Trigger::Trig2).is_err());
+        assert_eq!(
+            machine.last_rejection(),
+            Some(&StateMachineError::TriggerNotPermitted {
+                state: State::State2,
+                trigger: Trigger::Trig2
+            })
+        );
+        assert_eq!(
+            machine.last_transition().unwrap().destination,
+            State::State2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn time_in_state_follows_a_manual_clock() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        let mut machine = builder.build(())?;
+        let clock = crate::ManualClock::new();
+        machine.set_clock(clock.clone());
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(machine.time_in_state(), Duration::from_secs(30));
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.time_in_state(), Duration::ZERO);
+        clock.advance(Duration::from_secs(5));
+        machine.fire(Trigger::Trig2)?;
+        assert_eq!(machine.time_in_state(), Duration::from_secs(5));
+        Ok(())
+    }
+
+    #[test]
+    fn scheduled_commands_fire_when_due() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .on_entry_commands(|_, _| vec![crate::Command::Cancel(Trigger::Trig2)]);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1)
+            .on_entry_commands(|_, _| {
+                vec![
+                    crate::Command::Schedule {
+                        trigger: Trigger::Trig2,
+                        after: Duration::from_secs(10),
+                    },
+                    crate::Command::Schedule {
+                        trigger: Trigger::Trig2,
+                        after: Duration::from_secs(20),
+                    },
+                ]
+            });
+        let mut machine = builder.build(())?;
+        let clock = crate::ManualClock::new();
+        machine.set_clock(clock.clone());
+
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.next_due(), Some(Duration::from_secs(10)));
+        clock.advance(Duration::from_secs(5));
+        machine.fire_due()?;
+        assert_eq!(machine.state(), State::State2);
+        clock.advance(Duration::from_secs(5));
+        machine.fire_due()?;
+        assert_eq!(machine.state(), State::State1);
+        // Entering State1 cancelled the second timer
+        assert_eq!(machine.next_due(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn stop_drops_scheduled_triggers() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State1)
+            .on_entry_commands(|_, _| {
+                vec![crate::Command::Schedule {
+                    trigger: Trigger::Trig,
+                    after: Duration::from_secs(10),
+                }]
+            });
+        let mut machine = builder.build(())?;
+        let clock = crate::ManualClock::new();
+        machine.set_clock(clock.clone());
+
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.next_due(), Some(Duration::from_secs(10)));
+        machine.stop();
+        assert_eq!(machine.next_due(), None);
+        clock.advance(Duration::from_secs(10));
+        machine.fire_due()?;
+        assert!(machine.commands.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn fire_with_output_returns_outputs_of_the_asked_type() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .on_exit(|t, _| t.output(1_u32))
+            .on_compensate(|_, _| ());
+        builder
+            .config(State::State2)
+            .on_entry(|t, _| {
+                t.output(2_u32);
+                t.output("ignored");
+            })
+            .internal_transition(Trigger::Trig2, |t, _| {
+                t.output(3_u32);
+                t.output(std::cell::Cell::new(4_u8));
+            });
+        let mut machine = builder.build(())?;
+
+        assert_eq!(machine.fire_with_output::<u32>(Trigger::Trig)?, [1, 2]);
+        assert_eq!(machine.fire_with_output::<u32>(Trigger::Trig2)?, [3]);
+        // Outputs only have to be `Send`
+        assert_eq!(
+            machine.fire_with_output::<std::cell::Cell<u8>>(Trigger::Trig2)?,
+            [std::cell::Cell::new(4)]
+        );
+        assert!(machine.fire_with_output::<u32>(Trigger::Trig).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn transitions_are_numbered_and_timestamped() -> eyre::Result<()> {
+        let seen = std::cell::RefCell::new(Vec::new());
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder.on_transitioned(|t| seen.borrow_mut().push((t.sequence(), t.timestamp())));
+        let mut machine = builder.build(())?;
+        let clock = crate::ManualClock::new();
+        machine.set_clock(clock.clone());
+
+        machine.start();
+        clock.advance(Duration::from_secs(2));
+        assert!(machine.fire(Trigger::Trig2).is_err());
+        machine.fire(Trigger::Trig)?;
+        machine.reenter()?;
+        assert_eq!(
+            *seen.borrow(),
+            [
+                (Some(1), Some(Duration::from_secs(2))),
+                (Some(2), Some(Duration::from_secs(2))),
+            ]
+        );
+        assert_eq!(
+            Transition::new(State::State1, Trigger::Trig, State::State2).sequence(),
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn entry_count_and_time_in_state_track_transitions() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State2)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        let mut machine = builder.build(())?;
+
+        std::thread::sleep(Duration::from_millis(5));
+        let before = machine.time_in_state();
+        assert!(before >= Duration::from_millis(5));
+
+        machine.fire(Trigger::Trig)?;
+        assert!(machine.time_in_state() < before);
+        machine.fire(Trigger::Trig)?; // re-entry
+        machine.fire(Trigger::Trig2)?; // internal
+        assert_eq!(machine.entry_count(State::State1), 0);
+        assert_eq!(machine.entry_count(State::State2), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn state_metadata_is_kept_after_build() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        let config = builder
+            .config(State::State1)
+            .describe("awaiting payment")
+            .tag("billing")
+            .permit(Trigger::Trig, State::State2)
+            .describe_transition("pay")
+            .tag_transition("external");
+        assert_eq!(
+            config.transition_metadata(Trigger::Trig),
+            vec![Metadata {
+                description: Some("pay".to_string()),
+                tags: vec!["external".to_string()],
+            }]
+        );
+        drop(config);
+
+        let machine = builder.build(())?;
+        let metadata = machine.state_metadata(State::State1).unwrap();
+        assert_eq!(metadata.description.as_deref(), Some("awaiting payment"));
+        assert!(metadata.has_tag("billing"));
+        assert_eq!(
+            machine.state_metadata(State::State2),
+            Some(&Metadata::default())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn current_state_tags_follow_transitions() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .tag("billing")
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(())?;
+
+        assert!(machine.current_state_has_tag("billing"));
+        assert_eq!(machine.states_with_tag("billing"), vec![State::State1]);
+        machine.fire(Trigger::Trig)?;
+        assert!(!machine.current_state_has_tag("billing"));
+        Ok(())
+    }
+
+    #[test]
+    fn machine_fires_with_owned_storage() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(Trigger::Trig, State::State2, |o: &i32| *o == 0);
+        builder.config(State::State2).on_entry(|_t, o| *o += 1);
+
+        let mut machine = builder.build_with_storage::<crate::Owned<_>>(0)?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(*machine.object(), 1);
+        *machine.object_mut() += 1;
+        assert_eq!(*machine.object(), 2);
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct Counts {
+        entries: u32,
+        exits: u32,
+        internals: u32,
+    }
+
+    impl crate::StaticActions<State, Trigger, ()> for Counts {
+        fn on_entry(&mut self, _t: &Transition<State, Trigger>, _o: &mut ()) {
+            self.entries += 1;
+        }
+
+        fn on_exit(&mut self, _t: &Transition<State, Trigger>, _o: &mut ()) {
+            self.exits += 1;
+        }
+
+        fn on_internal(&mut self, _t: &Transition<State, Trigger>, _o: &mut ()) {
+            self.internals += 1;
+        }
+    }
+
+    #[test]
+    fn static_actions_fire_on_each_phase() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+
+        let mut machine =
+            builder.build_with_actions::<crate::Owned<_>, _>((), Counts::default())?;
+        machine.fire(Trigger::Trig2)?;
+        machine.fire(Trigger::Trig)?;
+        let counts = &machine.actions;
+        assert_eq!((counts.entries, counts.exits, counts.internals), (1, 1, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn statemachine_on_entry_fires() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .on_entry(move |_transition, obj| *obj = true);
+
+        let mut machine = builder.build(false)?;
+
+        assert_eq!(machine.state(), State::State1);
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        assert!(*machine.object());
+        Ok(())
+    }
+
+    #[test]
+    fn statemachine_on_entry_fires_multiple_actions() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .on_entry(move |_transition, object| {
+                *object += 1;
+            })
+            .on_entry(move |_transition, object| {
+                *object += 2;
+            });
+
+        let mut machine = builder.build(0)?;
+
+        assert_eq!(machine.state(), State::State1);
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn on_first_entry_only_fires_once() -> eyre::Result<()> {
+        let greeting = String::from("hello");
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .on_first_entry(move |_t, o: &mut Vec<String>| o.push(greeting))
+            .permit(Trigger::Trig, State::State1);
+
+        let mut machine = builder.build(Vec::new())?;
+        for _ in 0..4 {
+            machine.fire(Trigger::Trig)?;
+        }
+        assert_eq!(*machine.object(), vec!["hello".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn statemachine_on_exit_fires_multiple_actions() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .on_exit(move |_transition, object| {
+                *object += 1;
+            })
+            .on_exit(move |_transition, object| {
+                *object += 2;
+            })
+            .permit(Trigger::Trig, State::State2);
+
+        let mut machine = builder.build(0)?;
+
+        assert_eq!(machine.state(), State::State1);
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 3);
+        Ok(())
+    }
+
+    struct Record(&'static str);
+
+    impl crate::EntryAction<State, Trigger, Vec<&'static str>> for Record {
+        fn on_entry(&mut self, _t: &Transition<State, Trigger>, log: &mut Vec<&'static str>) {
+            log.push(self.0);
+        }
+    }
+
+    impl crate::ExitAction<State, Trigger, Vec<&'static str>> for Record {
+        fn on_exit(&mut self, _t: &Transition<State, Trigger>, log: &mut Vec<&'static str>) {
+            log.push(self.0);
+        }
+    }
+
+    #[test]
+    fn entry_and_exit_action_objects_fire() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .on_exit_obj(Record("exit"))
+            .permit(Trigger::Trig, State::State2);
+        builder.config(State::State2).on_entry_obj(Record("entry"));
+
+        let mut machine = builder.build(Vec::new())?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(*machine.object(), vec!["exit", "entry"]);
+        Ok(())
+    }
+
+    #[test]
+    fn on_exit_to_only_fires_for_destination() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .on_exit_to(State::State2, |_t, o: &mut i32| *o += 1)
+            .permit(Trigger::Trig, State::State2)
+            .permit(Trigger::Trig2, State::State1);
+
+        let mut machine = builder.build(0)?;
+        machine.fire(Trigger::Trig2)?; // re-enter State1
+        assert_eq!(*machine.object(), 0);
+        machine.fire(Trigger::Trig)?; // to State2
+        assert_eq!(*machine.object(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn actions_downcast_untyped_payloads() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, Vec<u32>>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .on_entry(|t, seen| seen.extend(t.payload_downcast::<u32>()))
+            .permit(Trigger::Trig, State::State1);
+        let mut machine = builder.build(Vec::new())?;
+
+        machine.fire_with_any(Trigger::Trig, 7_u32)?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire_with_any(Trigger::Trig, "not a u32")?;
+        assert_eq!(*machine.object(), vec![7]);
+        assert!(machine
+            .last_transition()
+            .unwrap()
+            .payload_downcast::<&str>()
+            .is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn internal_actions_receive_typed_payloads() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .internal_transition_with(Trigger::Trig, |by: &i32, o| *o += by);
+        let mut machine = builder.build(0)?;
+
+        machine.fire_with_any(Trigger::Trig, 5)?;
+        machine.fire_with_any(Trigger::Trig, 5_u8)?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(*machine.object(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn states_and_triggers_are_listed_by_display_name() -> eyre::Result<()> {
+        #[derive(
+            PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter, strum_macros::Display,
+        )]
+        enum Light {
+            #[strum(serialize = "off")]
+            Off,
+            #[strum(serialize = "on")]
+            On,
+        }
+        #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::Display)]
+        enum Switch {
+            #[strum(serialize = "flip")]
+            Flip,
+            #[strum(serialize = "unplug")]
+            Unplug,
+        }
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(Light::Off);
+        builder.config(Light::Off).permit(Switch::Flip, Light::On);
+        builder
+            .config(Light::On)
+            .permit(Switch::Flip, Light::Off)
+            .permit(Switch::Unplug, Light::Off);
+        let machine = builder.build(())?;
+
+        assert_eq!(machine.state_name(), "off");
+        assert_eq!(machine.states(), vec![Light::Off, Light::On]);
+        assert_eq!(machine.state_names(), vec!["off", "on"]);
+        assert_eq!(machine.triggers(), vec![Switch::Flip, Switch::Unplug]);
+        assert_eq!(machine.trigger_names(), vec!["flip", "unplug"]);
+        assert_eq!(machine.state_by_name("on"), Some(Light::On));
+        assert_eq!(machine.state_by_name("On"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn reenter_runs_exit_then_entry_actions() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, Trigger, Vec<&str>>::new(State::State1);
+        builder
+            .config(State::State1)
+            .on_exit(|_, log| log.push("exit"))
+            .on_entry(|_, log| log.push("entry"));
+        let transitions = std::cell::Cell::new(0);
+        let mut machine = builder.build(Vec::new())?;
+        machine.on_transitioned(|t| {
+            assert!(t.is_reentry());
+            transitions.set(transitions.get() + 1)
+        });
+
+        machine.reenter()?;
+        assert_eq!(machine.state(), State::State1);
+        assert_eq!(*machine.object(), vec!["exit", "entry"]);
+        assert_eq!(machine.entry_count(State::State1), 1);
+        drop(machine);
+        assert_eq!(transitions.get(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn reenter_goes_through_entry_guards_and_telemetry() -> eyre::Result<()> {
+        struct Committed<'c>(&'c std::cell::RefCell<Vec<(State, Option<Trigger>, State)>>);
+        impl TelemetrySink<State, Trigger> for Committed<'_> {
+            fn transition_committed(&mut self, t: &Transition<State, Trigger>, _: Duration) {
+                self.0
+                    .borrow_mut()
+                    .push((t.source, t.trigger, t.destination));
+            }
+        }
+        let committed = std::cell::RefCell::new(Vec::new());
+        let mut builder = StateMachineBuilder::<_, _, bool>::new(State::State1);
+        builder.config(State::State1).enter_if(
+            crate::Guard::new("open", |open: &bool| *open),
+            State::State2,
+        );
+        let mut machine = builder.build(true)?;
+        machine.add_telemetry_sink(Committed(&committed));
+
+        machine.reenter()?;
+        *machine.object_mut() = false;
+        machine.reenter()?;
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(machine.entry_count(State::State1), 1);
+        assert_eq!(machine.entry_count(State::State2), 1);
+        assert_eq!(
+            *machine.last_transition().unwrap().annotations(),
+            ["entry to State1 refused: open"]
+        );
+        drop(machine);
+        assert_eq!(
+            *committed.borrow(),
+            [
+                (State::State1, None, State::State1),
+                (State::State1, None, State::State2)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reenter_is_rejected_while_paused() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, Trigger, Vec<&str>>::new(State::State1);
+        builder
+            .config(State::State1)
+            .on_exit(|_, log| log.push("exit"))
+            .on_entry(|_, log| log.push("entry"));
+        let mut machine = builder.build(Vec::new())?;
+
+        machine.pause(crate::PauseMode::Queue);
+        assert_eq!(
+            machine.reenter(),
+            Err(StateMachineError::MachinePaused {
+                state: State::State1,
+                trigger: None
+            })
+        );
+        assert!(machine.object().is_empty());
+        machine.resume()?;
+        machine.reenter()?;
+        assert_eq!(*machine.object(), vec!["exit", "entry"]);
+        Ok(())
+    }
+
+    #[test]
+    fn dynamic_destinations_are_chosen_from_the_payload() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, u8>::new(State::State1);
+        builder.config(State::State1).permit_dynamic(
+            Trigger::Trig,
+            [State::State1, State::State2],
+            |n: &u8, limit| {
+                if n > limit {
+                    State::State2
+                } else {
+                    State::State1
+                }
+            },
+        );
+        builder.config(State::State2).permit_dynamic(
+            Trigger::Trig,
+            [State::State1],
+            |_: &(), _| State::State2,
+        );
+        let mut machine = builder.build(3)?;
+
+        let destinations: Vec<_> = machine.info().states[0]
+            .transitions
+            .iter()
+            .map(|t| t.destination)
+            .collect();
+        assert_eq!(destinations, [Some(State::State1), Some(State::State2)]);
+        machine.fire_with_any(Trigger::Trig, 2_u8)?;
+        assert_eq!(machine.state(), State::State1);
+        assert!(matches!(
+            machine.fire(Trigger::Trig),
+            Err(StateMachineError::InvalidTriggerParameters { .. })
+        ));
+        machine.fire_with_any(Trigger::Trig, 4_u8)?;
+        assert_eq!(machine.state(), State::State2);
+        assert!(matches!(
+            machine.fire_with_any(Trigger::Trig, ()),
+            Err(StateMachineError::Rejected { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn on_entry_from_only_fires_for_trigger() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .permit(Trigger::Trig2, State::State2);
+        builder
+            .config(State::State2)
+            .on_entry_from(Trigger::Trig2, |_t, o: &mut i32| *o += 1)
+            .permit(Trigger::Trig, State::State1);
+
+        let mut machine = builder.build(0)?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(*machine.object(), 0);
+        machine.fire(Trigger::Trig2)?;
+        assert_eq!(*machine.object(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn on_entry_from_state_only_fires_for_source() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .on_entry_from_state(State::State2, |_t, o: &mut i32| *o += 1)
+            .permit(Trigger::Trig, State::State2)
+            .permit(Trigger::Trig2, State::State1);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1)
+            .permit(Trigger::Trig2, State::State1);
+
+        let mut machine = builder.build(0)?;
+        machine.fire(Trigger::Trig2)?; // re-enter State1 from State1
+        assert_eq!(*machine.object(), 0);
+        machine.fire(Trigger::Trig)?; // to State2
+        machine.fire(Trigger::Trig)?; // back to State1
+        machine.fire(Trigger::Trig)?; // to State2
+        machine.fire(Trigger::Trig2)?; // back to State1 with another trigger
+        assert_eq!(*machine.object(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn transitioned_event_happens_on_transition() -> eyre::Result<()> {
+        let count = Arc::new(Mutex::new(0));
+        let count1 = Arc::clone(&count);
+
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+
+        builder.on_transitioned(move |_t| {
+            let mut data = count1.lock().unwrap();
+            *data += 1
+        });
+
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+
+        assert_eq!(*count.lock().unwrap(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn internal_transition_fires() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .internal_transition(Trigger::Trig, |_t, o| *o += 1);
+
+        let mut machine = builder.build(0)?;
+        machine.fire(Trigger::Trig)?;
+
+        assert_eq!(*machine.object(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn internal_transition_does_not_fire_on_entry() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+
+        builder
+            .config(State::State2)
+            .internal_transition(Trigger::Trig, |_t, o| *o += 1);
+
+        let mut machine = builder.build(0)?;
+        machine.fire(Trigger::Trig)?; // send to state2
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 0, "internal not fired");
+        machine.fire(Trigger::Trig)?; // re-enter to state2
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 1, "internal has fired");
+        Ok(())
+    }
+
+    #[test]
+    fn entry_action_does_not_fire_on_internal_transition() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+
+        builder
+            .config(State::State2)
+            .on_entry(|_t, o| *o += 1)
+            .internal_transition(Trigger::Trig, |_, _| ());
+
+        let mut machine = builder.build(0)?;
+        machine.fire(Trigger::Trig)?; // send to state2
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 1, "entry has fired");
+        machine.fire(Trigger::Trig)?; // re-enter to state2
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 1, "entry not fired");
+        Ok(())
+    }
+
+    #[test]
+    fn middleware_can_reject_trigger() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder.add_middleware(|trigger, _next: &mut Next<'_, State, Trigger>| {
+            Err(StateMachineError::Rejected {
+                trigger,
+                reason: "not authorized".to_string(),
+            })
+        });
+
+        let mut machine = builder.build(())?;
+        assert_eq!(
+            machine.fire(Trigger::Trig),
+            Err(StateMachineError::Rejected {
+                trigger: Trigger::Trig,
+                reason: "not authorized".to_string()
+            })
+        );
+        assert_eq!(machine.state(), State::State1);
+        Ok(())
+    }
+
+    #[test]
+    fn middleware_can_retry_trigger() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(Trigger::Trig, State::State2, |o: &i32| *o > 0)
+            .internal_transition(Trigger::Trig2, |_, o| *o += 1);
+        builder.add_middleware(|trigger, next: &mut Next<'_, State, Trigger>| {
+            next.run(trigger).or_else(|_| {
+                next.run(Trigger::Trig2)?;
+                next.run(trigger)
+            })
+        });
+
+        let mut machine = builder.build(0)?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn on_fired_only_sees_its_trigger() -> eyre::Result<()> {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let fired1 = Arc::clone(&fired);
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .internal_transition(Trigger::Trig, |_, _| ())
+            .permit(Trigger::Trig2, State::State1);
+        builder.on_fired(Trigger::Trig, move |t| {
+            fired1.lock().unwrap().push((t.source, t.destination))
+        });
+
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
+
+        assert_eq!(
+            *fired.lock().unwrap(),
+            vec![
+                (State::State1, State::State2),
+                (State::State2, State::State2)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn paused_machine_rejects_or_queues_triggers() -> eyre::Result<()> {
+        let payloads = std::cell::RefCell::new(Vec::new());
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1);
+        builder.on_transitioned(|t| {
+            payloads
+                .borrow_mut()
+                .push(t.payload_downcast::<u32>().copied())
+        });
+        let mut machine = builder.build(())?;
+
+        machine.pause(crate::PauseMode::Reject);
+        assert_eq!(
+            machine.fire(Trigger::Trig),
+            Err(StateMachineError::MachinePaused {
+                state: State::State1,
+                trigger: Some(Trigger::Trig)
+            })
+        );
+        machine.pause(crate::PauseMode::Queue);
+        machine.fire(Trigger::Trig2)?;
+        machine.fire_with_any(Trigger::Trig, 7_u32)?;
+        machine.fire(Trigger::Trig2)?;
+        assert!(machine.is_paused());
+        assert_eq!(machine.state(), State::State1);
 
-    /// Fire a trigger.  Will return `()` on success and a
-    /// [`crate::StateMachineError`] on failure
-    ///
-    /// TODO
-    /// * Implement a queue and concurrent access
-    pub fn fire(&mut self, trigger: T) -> Result<(), StateMachineError<S, T>> {
-        // Set up queue
-        self.fireone(trigger)
+        assert_eq!(
+            machine.resume(),
+            Err(StateMachineError::TriggerNotPermitted {
+                state: State::State1,
+                trigger: Trigger::Trig2
+            })
+        );
+        assert!(!machine.is_paused());
+        assert_eq!(machine.state(), State::State1);
+        assert_eq!(*payloads.borrow(), [Some(7), None]);
+        Ok(())
     }
 
-    fn representation(&mut self) -> Option<&mut StateRepresentation<S, T, O>> {
-        self.state_representations.get_mut(&self.current_state)
+    #[test]
+    fn start_while_paused_waits_for_resume() -> eyre::Result<()> {
+        let entered = std::cell::RefCell::new(Vec::new());
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .on_entry(|t, _| entered.borrow_mut().push((t.destination, t.trigger)))
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .on_entry(|t, _| entered.borrow_mut().push((t.destination, t.trigger)));
+        let mut machine = builder.build(())?;
+
+        machine.pause(crate::PauseMode::Queue);
+        machine.start();
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.lifecycle(), Lifecycle::NotStarted);
+        assert!(entered.borrow().is_empty());
+
+        machine.resume()?;
+        assert_eq!(
+            *entered.borrow(),
+            [(State::State1, None), (State::State2, Some(Trigger::Trig))]
+        );
+        assert_eq!(machine.lifecycle(), Lifecycle::Running);
+        Ok(())
     }
 
-    fn fireone(&mut self, trigger: T) -> Result<(), StateMachineError<S, T>> {
-        let state_object = Arc::clone(&self.object);
-        let current_state = self.current_state;
-
-        let behaviour = {
-            let representation = self
-                .representation()
-                .expect("representations should all exist");
-            representation.get_behaviour(trigger)?
-        };
-        let transition = match behaviour {
-            TriggerBehaviour::Transitioning(b) => {
-                let representation = self
-                    .representation()
-                    .expect("representations should all exist");
-                let destination = b.fire(current_state);
-                let transition = Transition::new(current_state, trigger, destination);
-                representation.exit(&transition, Arc::clone(&state_object));
-                self.current_state = transition.destination;
-                let representation = self
-                    .representation()
-                    .expect("representations should all exist");
-                representation.enter(&transition, state_object);
-                transition
-            }
-            TriggerBehaviour::Internal(b) => {
-                b.fire(current_state); // TODO: does nothing now. Maybe needed for parameters
-                let representation = self
-                    .representation()
-                    .expect("representations should all exist");
-                let transition = Transition::new(current_state, trigger, current_state);
-                representation.fire_internal_actions(&transition, Arc::clone(&state_object));
-                transition
-            }
-        };
+    #[test]
+    fn pending_triggers_can_be_inspected_and_drained() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(())?;
 
-        self.transition_event.fire_events(&transition);
+        machine.pause(crate::PauseMode::Queue);
+        machine.fire(Trigger::Trig2)?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.pending_count(), 2);
+        assert_eq!(machine.pending_triggers(), [Trigger::Trig2, Trigger::Trig]);
 
+        assert_eq!(machine.drain_pending(), [Trigger::Trig2, Trigger::Trig]);
+        assert_eq!(machine.pending_count(), 0);
+        machine.resume()?;
+        assert_eq!(machine.state(), State::State1);
         Ok(())
     }
-}
 
-impl<S, T, O> Display for StateMachine<S, T, O>
-where
-    S: Debug,
-    O: Debug,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "StateMachine ( state: {:?}, object: {:?} )",
-            self.current_state, self.object
-        )
-    }
-}
+    #[test]
+    fn stopped_machine_rejects_everything() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, u32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .on_exit(|_, exits| *exits += 1)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(0)?;
+        machine.fire(Trigger::Trig2).unwrap_err();
+        assert_eq!(machine.lifecycle(), Lifecycle::NotStarted);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::tests::{State, Trigger};
-    use crate::StateMachineBuilder;
+        machine.stop();
+        machine.stop();
+        machine.start();
+        machine.reenter().unwrap_err();
+        assert_eq!(*machine.object(), 1);
+        assert_eq!(machine.lifecycle(), Lifecycle::Stopped);
+        let stopped = StateMachineError::MachineStopped {
+            state: State::State1,
+            trigger: Some(Trigger::Trig),
+        };
+        assert_eq!(machine.fire(Trigger::Trig), Err(stopped.clone()));
+        assert_eq!(machine.process_all([Trigger::Trig]).error, Some(stopped));
+        Ok(())
+    }
 
     #[test]
-    fn entry_into_unconfigured_state_works() -> eyre::Result<()> {
-        // If the user hasn't explicitly configured a state to do something, it
-        // is still part of the State enum and is a valid destination
-        let mut builder = StateMachineBuilder::new(State::State1);
+    fn compensate_to_unwinds_to_the_latest_visit() -> eyre::Result<()> {
+        let mut builder =
+            StateMachineBuilder::<_, _, Vec<(State, Option<Trigger>)>>::new(State::State1);
         builder
             .config(State::State1)
             .permit(Trigger::Trig, State::State2);
-        let mut machine = builder.build(())?;
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1)
+            .on_compensate(|t, undone| undone.push((t.destination, t.trigger)));
+        let mut machine = builder.build(Vec::new())?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
+        machine.fire(Trigger::Trig)?;
 
+        machine.compensate_to(State::State1)?;
         assert_eq!(machine.state(), State::State1);
-        let result = machine.fire(Trigger::Trig)?;
+        assert_eq!(*machine.object(), [(State::State2, Some(Trigger::Trig))]);
+        // Already at the latest visit, so there is nothing more to undo
+        machine.compensate_to(State::State1)?;
+        assert_eq!(machine.object().len(), 1);
+        machine.compensate_to(State::State2)?;
         assert_eq!(machine.state(), State::State2);
+        assert_eq!(machine.object().len(), 1);
+        assert_eq!(machine.entry_count(State::State1), 3);
+        assert_eq!(machine.entry_count(State::State2), 3);
+        let last = machine.last_transition().unwrap();
+        assert_eq!((last.source, last.trigger), (State::State1, None));
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(
+            machine.compensate_to(State::State2),
+            Err(StateMachineError::NotInHistory {
+                state: State::State2
+            })
+        );
         Ok(())
     }
 
     #[test]
-    fn fire_for_not_defined_throws_error() -> eyre::Result<()> {
-        let mut machine = StateMachineBuilder::new(State::State2).build(())?;
-        let result = machine.fire(Trigger::Trig);
-        assert!(result.is_err());
-        let error = result.err().unwrap();
+    fn labels_name_edges_transitions_and_ambiguities() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .label("advance");
+        builder
+            .config(State::State2)
+            .permit_if(Trigger::Trig, State::State1, |_: &()| true)
+            .label("back")
+            .permit_if(Trigger::Trig, State::State2, |_: &()| true)
+            .permit_if(Trigger::Trig, State::State2, |_: &()| true)
+            .label("stay");
+        let mut machine = builder.build(())?;
+
+        assert!(machine
+            .to_dot()
+            .contains("\"State1\" -> \"State2\" [label=\"advance: Trig\"]"));
+        assert!(machine
+            .to_mermaid_live()
+            .contains("State2 --> State1 : back: Trig"));
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.last_transition().unwrap().label(), Some("advance"));
+        let error = machine.fire(Trigger::Trig).unwrap_err();
         assert_eq!(
-            error,
-            StateMachineError::TriggerNotPermitted {
-                state: State::State2,
-                trigger: Trigger::Trig
-            }
+            error.to_string(),
+            "multiple permitted transitions are configured for trigger Trig in State2: back, stay"
         );
         Ok(())
     }
 
     #[test]
-    fn statemachine_on_entry_fires() -> eyre::Result<()> {
-        let mut builder = StateMachineBuilder::new(State::State1);
+    fn reconfigure_changes_a_built_machine() -> eyre::Result<()> {
+        let entered = Arc::new(Mutex::new(0));
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
         builder
             .config(State::State1)
             .permit(Trigger::Trig, State::State2);
-        builder
-            .config(State::State2)
-            .on_entry(move |_transition, obj| *obj = true);
-
-        let mut machine = builder.build(false)?;
+        let mut machine = builder.build(())?;
 
-        assert_eq!(machine.state(), State::State1);
+        let counter = Arc::clone(&entered);
+        machine.reconfigure(|config| {
+            config
+                .config(State::State2)
+                .on_entry(move |_, _| *counter.lock().unwrap() += 1)
+                .permit(Trigger::Trig2, State::State1);
+        })?;
         machine.fire(Trigger::Trig)?;
-        assert_eq!(machine.state(), State::State2);
-        assert!(*machine.object());
+        assert_eq!(*entered.lock().unwrap(), 1);
+        machine.fire(Trigger::Trig2)?;
+
+        machine.reconfigure(|config| {
+            config.config(State::State1).forget(Trigger::Trig);
+            config.config(State::State2).clear_actions();
+        })?;
+        assert!(machine.fire(Trigger::Trig).is_err());
+        assert!(machine.permitted_triggers().is_empty());
+
+        let mut kept = Vec::new();
+        let error = machine
+            .reconfigure(|config| kept.push(config.config(State::State2)))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            StateMachineError::ConfigStillInUse {
+                state: State::State2
+            }
+        );
         Ok(())
     }
 
     #[test]
-    fn statemachine_on_entry_fires_multiple_actions() -> eyre::Result<()> {
-        let mut builder = StateMachineBuilder::new(State::State1);
+    fn failed_reconfigure_keeps_the_old_configuration() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, Vec<&str>>::new(State::State1);
         builder
             .config(State::State1)
+            .on_entry(|_, log| log.push("entry"))
             .permit(Trigger::Trig, State::State2);
         builder
             .config(State::State2)
-            .on_entry(move |_transition, object| {
-                *object += 1;
-            })
-            .on_entry(move |_transition, object| {
-                *object += 2;
-            });
+            .permit(Trigger::Trig2, State::State1);
+        let mut machine = builder.build(Vec::new())?;
 
-        let mut machine = builder.build(0)?;
+        let mut kept = Vec::new();
+        let error = machine
+            .reconfigure(|config| {
+                config
+                    .config(State::State1)
+                    .forget(Trigger::Trig)
+                    .clear_actions();
+                kept.push(config.config(State::State2).forget(Trigger::Trig2));
+            })
+            .unwrap_err();
+        assert_eq!(
+            error,
+            StateMachineError::ConfigStillInUse {
+                state: State::State2
+            }
+        );
+        drop(kept);
 
+        assert_eq!(machine.permitted_triggers(), [Trigger::Trig]);
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
         assert_eq!(machine.state(), State::State1);
+        assert_eq!(*machine.object(), ["entry"]);
+        Ok(())
+    }
+
+    #[test]
+    fn reconfigure_keeps_to_the_state_universe() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<u8, _, Vec<&str>>::with_state_universe(0, 0..2);
+        builder
+            .config(0)
+            .permit(Trigger::Trig, 1)
+            .on_compensate(|_, log| log.push("undo"));
+        let mut machine = builder.build(Vec::new())?;
+
+        assert_eq!(
+            machine.reconfigure(|config| {
+                config.config(0).permit(Trigger::Trig2, 5);
+                config.config(1).permit(Trigger::Trig, 0);
+            }),
+            Err(StateMachineError::UnknownState { state: 5 })
+        );
+        assert_eq!(machine.states(), [0, 1]);
+        assert_eq!(machine.permitted_triggers(), [Trigger::Trig]);
+        assert_eq!(
+            machine.reconfigure(|config| {
+                config.config(7);
+            }),
+            Err(StateMachineError::UnknownState { state: 7 })
+        );
+        assert_eq!(machine.states(), [0, 1]);
+
+        machine.reconfigure(|config| {
+            config.config(0).clear_actions();
+            config.config(1).permit(Trigger::Trig, 0);
+        })?;
         machine.fire(Trigger::Trig)?;
-        assert_eq!(machine.state(), State::State2);
-        assert_eq!(*machine.object(), 3);
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig)?;
+        machine.compensate_to(0)?;
+        assert!(machine.object().is_empty());
         Ok(())
     }
 
     #[test]
-    fn statemachine_on_exit_fires_multiple_actions() -> eyre::Result<()> {
-        let mut builder = StateMachineBuilder::new(State::State1);
+    fn progress_follows_the_configured_steps() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<u8, _, ()>::with_states(0, 0..4);
+        builder.config(0).step(0).permit(Trigger::Trig, 1);
+        builder
+            .config(1)
+            .step(1)
+            .permit(Trigger::Trig, 2)
+            .permit(Trigger::Trig2, 9);
+        builder.config(2).step(4);
+        builder.config(3).step(2);
+        let mut machine = builder.build(())?;
+
+        assert_eq!(machine.progress(), 0.0);
+        assert_eq!(machine.remaining_steps(), [1, 3, 2]);
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.progress(), 0.25);
+        assert_eq!(machine.remaining_steps(), [3, 2]);
+        machine.fire(Trigger::Trig2)?;
+        assert_eq!(machine.progress(), 0.0);
+        assert_eq!(machine.remaining_steps(), [0, 1, 3, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn regions_group_states_for_queries_and_dot() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
         builder
             .config(State::State1)
-            .on_exit(move |_transition, object| {
-                *object += 1;
-            })
-            .on_exit(move |_transition, object| {
-                *object += 2;
-            })
+            .region("billing")
             .permit(Trigger::Trig, State::State2);
+        builder.config(State::State2);
+        let mut machine = builder.build(())?;
 
-        let mut machine = builder.build(0)?;
+        assert!(machine.in_region("billing"));
+        assert_eq!(machine.states_in_region("billing"), [State::State1]);
+        assert!(machine.to_dot().contains(
+            "    subgraph \"cluster_billing\" {\n        label=\"billing\";\n        \"State1\";\n    }\n"
+        ));
+        machine.fire(Trigger::Trig)?;
+        assert!(!machine.in_region("billing"));
+        Ok(())
+    }
 
-        assert_eq!(machine.state(), State::State1);
+    #[test]
+    fn entry_guards_redirect_to_their_fallback() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, bool>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2)
+            .enter_if(crate::Guard::new("never", |_: &bool| false), State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig2, State::State1)
+            .enter_if(
+                crate::Guard::new("allowed", |allowed: &bool| *allowed),
+                State::State1,
+            );
+        let mut machine = builder.build(false)?;
+
+        assert_eq!(
+            machine.fire(Trigger::Trig),
+            Err(StateMachineError::EntryGuardCycle {
+                state: State::State2
+            })
+        );
+        *machine.object_mut() = true;
         machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
         assert_eq!(machine.state(), State::State2);
-        assert_eq!(*machine.object(), 3);
+        let transition = machine.last_transition().unwrap();
+        assert_eq!(transition.destination, State::State2);
+        assert_eq!(
+            *transition.annotations(),
+            ["entry to State1 refused: never"]
+        );
         Ok(())
     }
 
     #[test]
-    fn transitioned_event_happens_on_transition() -> eyre::Result<()> {
-        let count = Arc::new(Mutex::new(0));
-        let count1 = Arc::clone(&count);
-
-        let mut builder = StateMachineBuilder::new(State::State1);
+    fn describe_reports_state_triggers_and_last_transition() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
         builder
             .config(State::State1)
             .permit(Trigger::Trig, State::State2);
-
-        builder.on_transitioned(move |_t| {
-            let mut data = count1.lock().unwrap();
-            *data += 1
-        });
-
+        builder
+            .config(State::State2)
+            .permit_if(
+                Trigger::Trig,
+                State::State1,
+                crate::Guard::new("ready", |_| false),
+            )
+            .permit(Trigger::Trig2, State::State1);
         let mut machine = builder.build(())?;
+        let clock = crate::ManualClock::new();
+        machine.set_clock(clock.clone());
         machine.fire(Trigger::Trig)?;
+        clock.advance(Duration::from_millis(1500));
+        machine.pause(crate::PauseMode::Queue);
+        machine.fire(Trigger::Trig2)?;
 
-        assert_eq!(*count.lock().unwrap(), 1);
+        assert_eq!(
+            machine.describe(),
+            "state: State2
+time in state: 1.5s
+lifecycle: Running
+paused: Queue, 1 queued
+triggers:
+  Trig: trigger Trig is valid for State2 but guard conditions are not met: [\"ready\"]
+  Trig2: permitted
+last transition: State1 --Trig--> State2
+"
+        );
         Ok(())
     }
 
     #[test]
-    fn internal_transition_fires() -> eyre::Result<()> {
-        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+    fn on_fired_by_sees_only_its_triggers() -> eyre::Result<()> {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let fired1 = Arc::clone(&fired);
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
         builder
             .config(State::State1)
-            .internal_transition(Trigger::Trig, |_t, o| *o += 1);
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .internal_transition(Trigger::Trig, |_, _| ())
+            .permit(Trigger::Trig2, State::State1);
+        builder.on_fired_by([Trigger::Trig2], move |t| {
+            fired1.lock().unwrap().push(t.trigger)
+        });
 
-        let mut machine = builder.build(0)?;
+        let mut machine = builder.build(())?;
         machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
 
-        assert_eq!(*machine.object(), 1);
+        assert_eq!(*fired.lock().unwrap(), vec![Some(Trigger::Trig2)]);
         Ok(())
     }
 
     #[test]
-    fn internal_transition_does_not_fire_on_entry() -> eyre::Result<()> {
-        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+    fn state_entered_and_exited_skip_internal_transitions() -> eyre::Result<()> {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let entered = Arc::clone(&seen);
+        let exited = Arc::clone(&seen);
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
         builder
             .config(State::State1)
             .permit(Trigger::Trig, State::State2);
-
         builder
             .config(State::State2)
-            .internal_transition(Trigger::Trig, |_t, o| *o += 1);
+            .internal_transition(Trigger::Trig, |_, _| ())
+            .permit(Trigger::Trig2, State::State2);
+        builder.on_state_exited(State::State2, move |t| {
+            exited.lock().unwrap().push(("exited", t.destination))
+        });
+        builder.on_state_entered(State::State2, move |t| {
+            entered.lock().unwrap().push(("entered", t.source))
+        });
 
-        let mut machine = builder.build(0)?;
-        machine.fire(Trigger::Trig)?; // send to state2
-        assert_eq!(machine.state(), State::State2);
-        assert_eq!(*machine.object(), 0, "internal not fired");
-        machine.fire(Trigger::Trig)?; // re-enter to state2
-        assert_eq!(machine.state(), State::State2);
-        assert_eq!(*machine.object(), 1, "internal has fired");
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("entered", State::State1),
+                ("exited", State::State2),
+                ("entered", State::State2),
+            ]
+        );
         Ok(())
     }
 
     #[test]
-    fn entry_action_does_not_fire_on_internal_transition() -> eyre::Result<()> {
-        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+    fn builder_handler_can_be_removed_from_machine() -> eyre::Result<()> {
+        let count = Arc::new(Mutex::new(0));
+        let count1 = Arc::clone(&count);
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
         builder
             .config(State::State1)
             .permit(Trigger::Trig, State::State2);
+        let id = builder.on_transitioned(move |_t| *count1.lock().unwrap() += 1);
 
-        builder
-            .config(State::State2)
-            .on_entry(|_t, o| *o += 1)
-            .internal_transition(Trigger::Trig, |_, _| ());
+        let mut machine = builder.build(())?;
+        assert!(machine.remove_transitioned(id));
+        machine.fire(Trigger::Trig)?;
 
-        let mut machine = builder.build(0)?;
-        machine.fire(Trigger::Trig)?; // send to state2
-        assert_eq!(machine.state(), State::State2);
-        assert_eq!(*machine.object(), 1, "entry has fired");
-        machine.fire(Trigger::Trig)?; // re-enter to state2
-        assert_eq!(machine.state(), State::State2);
-        assert_eq!(*machine.object(), 1, "entry not fired");
+        assert_eq!(*count.lock().unwrap(), 0);
         Ok(())
     }
 }
@@ -0,0 +1,311 @@
+use alloc::string::String;
+use alloc::vec::{IntoIter, Vec};
+use core::mem;
+use strum::IntoEnumIterator;
+
+use crate::action::ActionInfo;
+use crate::complexity::ComplexityReport;
+use crate::config_visitor::ConfigVisitor;
+use crate::log_violation::LogViolation;
+use crate::metadata::Metadata;
+
+/// A description of everything configured on a machine, from
+/// [`crate::StateMachine::info`], for exports, tooling and custom
+/// validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateMachineInfo<S, T> {
+    pub initial_state: S,
+    /// Every state the machine can be in, in enum order
+    pub states: Vec<StateInfo<S, T>>,
+}
+
+impl<S: PartialEq, T> StateMachineInfo<S, T> {
+    pub fn state(&self, state: S) -> Option<&StateInfo<S, T>> {
+        self.states.iter().find(|info| info.state == state)
+    }
+}
+
+impl<S: Copy + PartialEq, T: Copy + PartialEq> StateMachineInfo<S, T> {
+    /// Every state the machine can be in, in enum order
+    pub fn states(&self) -> impl Iterator<Item = S> + '_ {
+        self.states.iter().map(|info| info.state)
+    }
+
+    /// Every trigger with a behaviour in some state, once each, in state
+    /// order and then the order they were configured in
+    pub fn triggers(&self) -> IntoIter<T> {
+        unique(self.states.iter().flat_map(|info| &info.transitions))
+    }
+
+    /// The triggers with a behaviour in `state`, once each, in the order
+    /// they were configured in.  Empty if the machine can't be in `state`.
+    pub fn configured_triggers(&self, state: S) -> IntoIter<T> {
+        unique(
+            self.state(state)
+                .into_iter()
+                .flat_map(|info| &info.transitions),
+        )
+    }
+}
+
+impl<S: Copy + PartialEq, T: Copy + PartialEq> StateMachineInfo<S, T> {
+    /// Check that a recorded sequence of `(source, trigger, destination)`
+    /// steps, such as history migrated from another workflow engine, could
+    /// have been made by this configuration: each step starts where the
+    /// previous one ended and is a configured transition, internal
+    /// transitions staying in their state.  Guards and entry guards are not
+    /// evaluated, since the log doesn't say what they would have seen.
+    ///
+    /// ```
+    /// # use stateless_rs::{LogViolation, StateMachineBuilder};
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Open, Closed, Locked }
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+    /// # enum Trigger { Close, Lock }
+    /// let mut builder = StateMachineBuilder::<_, _, ()>::new(State::Open);
+    /// builder.config(State::Open).permit(Trigger::Close, State::Closed);
+    /// builder.config(State::Closed).permit(Trigger::Lock, State::Locked);
+    /// let info = builder.build(()).unwrap().info();
+    ///
+    /// let legacy = [
+    ///     (State::Open, Trigger::Close, State::Closed),
+    ///     (State::Closed, Trigger::Close, State::Closed),
+    /// ];
+    /// assert_eq!(
+    ///     info.verify_log(legacy),
+    ///     Err(LogViolation::NotPermitted {
+    ///         step: 1,
+    ///         source: State::Closed,
+    ///         trigger: Trigger::Close,
+    ///     })
+    /// );
+    /// ```
+    pub fn verify_log<I>(&self, log: I) -> Result<(), LogViolation<S, T>>
+    where
+        I: IntoIterator<Item = (S, T, S)>,
+    {
+        let mut previous: Option<S> = None;
+        for (step, (source, trigger, destination)) in log.into_iter().enumerate() {
+            if let Some(expected) = previous.filter(|expected| *expected != source) {
+                return Err(LogViolation::Discontinuous {
+                    step,
+                    expected,
+                    source,
+                });
+            }
+            let permitted: Vec<S> = self
+                .state(source)
+                .into_iter()
+                .flat_map(|info| &info.transitions)
+                .filter(|transition| transition.trigger == trigger)
+                .map(|transition| transition.destination_from(source))
+                .collect();
+            if permitted.is_empty() {
+                return Err(LogViolation::NotPermitted {
+                    step,
+                    source,
+                    trigger,
+                });
+            }
+            if !permitted.contains(&destination) {
+                return Err(LogViolation::WrongDestination {
+                    step,
+                    source,
+                    trigger,
+                    destination,
+                    permitted,
+                });
+            }
+            previous = Some(destination);
+        }
+        Ok(())
+    }
+}
+
+impl<S: Copy + PartialEq, T: PartialEq> StateMachineInfo<S, T> {
+    /// Size and branching metrics, for flagging machines which have grown
+    /// hard to maintain, see [`ComplexityReport`]
+    pub fn complexity(&self) -> ComplexityReport {
+        ComplexityReport::of(self)
+    }
+}
+
+impl<S, T: IntoEnumIterator> StateMachineInfo<S, T> {
+    /// Trigger variants with no behaviour in any state, which are usually
+    /// typos or left over from a refactor.  Variants carrying data count as
+    /// used if any value of the variant is.
+    ///
+    /// ```
+    /// # use stateless_rs::StateMachineBuilder;
+    /// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// # enum State { Off, On }
+    /// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+    /// enum Trigger { Switch, Swtich }
+    ///
+    /// let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::Off);
+    /// builder.config(State::Off).permit(Trigger::Switch, State::On);
+    /// let info = builder.build(()).unwrap().info();
+    /// assert_eq!(info.unused_triggers(), vec![Trigger::Swtich]);
+    /// ```
+    pub fn unused_triggers(&self) -> Vec<T> {
+        T::iter()
+            .filter(|trigger| {
+                !self
+                    .states
+                    .iter()
+                    .flat_map(|info| &info.transitions)
+                    .any(|t| mem::discriminant(&t.trigger) == mem::discriminant(trigger))
+            })
+            .collect()
+    }
+}
+
+/// The triggers of `transitions`, dropping repeats from guarded
+/// alternatives
+fn unique<'i, S: 'i, T: Copy + PartialEq + 'i>(
+    transitions: impl Iterator<Item = &'i TransitionInfo<S, T>>,
+) -> IntoIter<T> {
+    let mut triggers = Vec::new();
+    for transition in transitions {
+        if !triggers.contains(&transition.trigger) {
+            triggers.push(transition.trigger);
+        }
+    }
+    triggers.into_iter()
+}
+
+impl<S: Copy, T: Copy> StateMachineInfo<S, T> {
+    /// Walk every state, action and transition with `visitor`
+    pub fn accept<V: ConfigVisitor<S, T> + ?Sized>(&self, visitor: &mut V) {
+        for state in &self.states {
+            visitor.visit_state(state);
+            for action in &state.entry_actions {
+                visitor.visit_entry_action(state.state, action);
+            }
+            for action in &state.exit_actions {
+                visitor.visit_exit_action(state.state, action);
+            }
+            for transition in &state.transitions {
+                visitor.visit_transition(state.state, transition);
+                for action in &transition.actions {
+                    visitor.visit_transition_action(state.state, transition.trigger, action);
+                }
+            }
+        }
+    }
+}
+
+/// One state of a [`StateMachineInfo`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateInfo<S, T> {
+    pub state: S,
+    pub metadata: Metadata,
+    /// Set with [`crate::StateConfig::region`]
+    pub region: Option<String>,
+    /// Set with [`crate::StateConfig::step`]
+    pub step: Option<u16>,
+    /// In the order they run
+    pub entry_actions: Vec<ActionInfo>,
+    /// In the order they run
+    pub exit_actions: Vec<ActionInfo>,
+    /// In the order they were configured
+    pub transitions: Vec<TransitionInfo<S, T>>,
+}
+
+/// One trigger behaviour of a state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionInfo<S, T> {
+    pub trigger: T,
+    /// `None` for an internal transition
+    pub destination: Option<S>,
+    pub guard_description: Option<String>,
+    /// How many guards the guard was combined from, 0 without one
+    pub guard_conditions: usize,
+    /// The actions of an internal transition, in the order they run
+    pub actions: Vec<ActionInfo>,
+    pub metadata: Metadata,
+    /// Set with [`crate::StateConfig::label`]
+    pub label: Option<String>,
+}
+
+impl<S: Copy, T> TransitionInfo<S, T> {
+    /// Where the machine ends up when this is taken from `source`
+    pub fn destination_from(&self, source: S) -> S {
+        self.destination.unwrap_or(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{State, Trigger};
+    use crate::{LogViolation, StateMachineBuilder};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn lists_states_and_triggers() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(Trigger::Trig2, State::State2, |o: &i32| *o > 0)
+            .permit_if(Trigger::Trig2, State::State1, |o: &i32| *o <= 0)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1);
+        let info = builder.build(0)?.info();
+
+        assert_eq!(
+            info.states().collect::<Vec<_>>(),
+            [State::State1, State::State2]
+        );
+        assert_eq!(
+            info.triggers().collect::<Vec<_>>(),
+            [Trigger::Trig2, Trigger::Trig]
+        );
+        assert_eq!(
+            info.configured_triggers(State::State2).collect::<Vec<_>>(),
+            [Trigger::Trig]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn verify_log_reports_the_first_violating_step() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, i32>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(Trigger::Trig, State::State2, |o: &i32| *o > 0)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1);
+        let info = builder.build(0)?.info();
+
+        let valid = [
+            (State::State1, Trigger::Trig2, State::State1),
+            (State::State1, Trigger::Trig, State::State2),
+            (State::State2, Trigger::Trig, State::State1),
+        ];
+        assert_eq!(info.verify_log(valid), Ok(()));
+        assert_eq!(
+            info.verify_log([
+                (State::State1, Trigger::Trig, State::State2),
+                (State::State1, Trigger::Trig, State::State2),
+            ]),
+            Err(LogViolation::Discontinuous {
+                step: 1,
+                expected: State::State2,
+                source: State::State1,
+            })
+        );
+        let wrong = info
+            .verify_log([(State::State1, Trigger::Trig2, State::State2)])
+            .unwrap_err();
+        assert_eq!(wrong.step(), 0);
+        assert_eq!(
+            wrong.to_string(),
+            "step 0: trigger Trig2 takes State1 to [State1], not State2"
+        );
+        Ok(())
+    }
+}
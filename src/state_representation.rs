@@ -1,43 +1,129 @@
-use crate::transition::Transition;
-use crate::trigger_behaviour::TriggerBehaviour;
+use crate::action::{ActionInfo, EntryAction, ExitAction};
+use crate::command::Command;
+use crate::guard::Guard;
+use crate::intern::Interner;
+use crate::memory_stats::MemoryStats;
+use crate::metadata::Metadata;
+use crate::small_vec::SmallVec;
+use crate::state_machine_info::{StateInfo, TransitionInfo};
+use crate::transition::{Payload, Transition};
+use crate::trigger_behaviour::{BehaviourKind, TriggerBehaviour};
+use crate::trigger_map::{TriggerKey, TriggerMap, TriggerMatching};
 use crate::StateMachineError;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::mem;
+use core::ops::FnOnce;
 use derivative::Derivative;
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::hash::Hash;
-use std::ops::FnOnce;
-use std::sync::{Arc, Mutex};
 
-type Action<S, T, O> = Box<dyn FnMut(&Transition<S, T>, &mut O)>;
+/// Actions are shared so [`crate::StateMachine::reconfigure`] can change a
+/// copy of a state and keep the original if it fails
+type Action<'a, S, T, O> = Rc<RefCell<dyn FnMut(&Transition<S, T>, &mut O) + 'a>>;
+
+type CommandAction<'a, S, T, O> =
+    Rc<RefCell<dyn FnMut(&Transition<S, T>, &O) -> Vec<Command<T>> + 'a>>;
+
+/// Most states have no more than a couple of actions of each kind
+type ActionList<A> = SmallVec<A, 2>;
 
 #[derive(Derivative)]
-#[derivative(Debug)]
-pub struct StateRepresentation<S, T, O> {
+#[derivative(Clone(bound = ""))]
+pub(crate) struct OrderedAction<'a, S, T, O> {
+    info: ActionInfo,
+    action: Action<'a, S, T, O>,
+}
+
+/// Insert after every action with the same or a lower priority, so equal
+/// priorities keep registration order
+fn insert_ordered<'a, S, T, O>(
+    actions: &mut ActionList<OrderedAction<'a, S, T, O>>,
+    info: ActionInfo,
+    action: Action<'a, S, T, O>,
+) {
+    let index = actions.partition_point(|a| a.info.priority <= info.priority);
+    actions.insert(index, OrderedAction { info, action });
+}
+
+/// A guard on entering a state, with where to go instead when it fails
+#[derive(Derivative)]
+#[derivative(Clone(bound = "S: Clone"))]
+pub(crate) struct EntryGuard<'a, S, O> {
+    pub(crate) guard: Guard<'a, O>,
+    pub(crate) fallback: S,
+    /// Filled in at build
+    pub(crate) fallback_index: u16,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug, Clone(bound = "S: Clone, T: Clone"))]
+pub struct StateRepresentation<'a, S, T, O> {
     state: S,
-    trigger_behaviours: HashMap<T, TriggerBehaviour<S, T>>,
+    pub(crate) metadata: Metadata,
+    trigger_behaviours: TriggerMap<T, Vec<TriggerBehaviour<'a, S, T, O>>>,
+    /// Position in `trigger_behaviours` by interned trigger id, filled in at
+    /// build
+    trigger_slots: Vec<Option<u16>>,
+    last_added_trigger: Option<T>,
+    /// Set with [`crate::StateConfig::region`]
+    pub(crate) region: Option<String>,
+    /// Set with [`crate::StateConfig::step`]
+    pub(crate) step: Option<u16>,
+    /// [`crate::StateConfig::otherwise`] was called before any transition
+    /// was added, which fails the build
+    pub(crate) stray_otherwise: bool,
+    #[derivative(Debug = "ignore")]
+    pub(crate) entry_guard: Option<EntryGuard<'a, S, O>>,
     #[derivative(Debug = "ignore")]
-    pub(crate) entry_actions: Vec<Action<S, T, O>>,
+    pub(crate) entry_actions: ActionList<OrderedAction<'a, S, T, O>>,
     #[derivative(Debug = "ignore")]
-    pub(crate) exit_actions: Vec<Action<S, T, O>>,
+    /// Each of these does nothing after its first call
+    pub(crate) first_entry_actions: ActionList<Action<'a, S, T, O>>,
     #[derivative(Debug = "ignore")]
-    pub(crate) internal_actions: HashMap<T, Vec<Action<S, T, O>>>,
+    pub(crate) exit_actions: ActionList<OrderedAction<'a, S, T, O>>,
+    #[derivative(Debug = "ignore")]
+    pub(crate) internal_actions: TriggerMap<T, ActionList<OrderedAction<'a, S, T, O>>>,
+    /// Run after the entry actions, see [`crate::StateConfig::on_entry_commands`]
+    #[derivative(Debug = "ignore")]
+    command_actions: ActionList<CommandAction<'a, S, T, O>>,
+    /// Undo entering the state, see [`crate::StateMachine::compensate_to`]
+    #[derivative(Debug = "ignore")]
+    compensate_actions: ActionList<Action<'a, S, T, O>>,
     // activate_actions: Vec<()>,
     // deactivate_actions: Vec<()>,
     // substates: Vec<Self>,
 }
 
-impl<S, T, O> StateRepresentation<S, T, O>
+impl<'a, S, T, O> StateRepresentation<'a, S, T, O>
 where
     S: Copy + Debug,
     T: Eq + Hash + Debug + Copy,
 {
+    #[cfg(test)]
     pub fn new(state: S) -> Self {
+        Self::with_trigger_matching(state, TriggerMatching::Value)
+    }
+
+    pub(crate) fn with_trigger_matching(state: S, matching: TriggerMatching) -> Self {
         Self {
             state,
-            trigger_behaviours: HashMap::new(),
-            entry_actions: Vec::new(),
-            exit_actions: Vec::new(),
-            internal_actions: HashMap::new(),
+            metadata: Metadata::default(),
+            trigger_behaviours: TriggerMap::with_matching(matching),
+            trigger_slots: Vec::new(),
+            last_added_trigger: None,
+            region: None,
+            step: None,
+            stray_otherwise: false,
+            entry_guard: None,
+            entry_actions: ActionList::new(),
+            first_entry_actions: ActionList::new(),
+            exit_actions: ActionList::new(),
+            internal_actions: TriggerMap::with_matching(matching),
+            command_actions: ActionList::new(),
+            compensate_actions: ActionList::new(),
         }
     }
 
@@ -45,73 +131,489 @@ where
         self.state
     }
 
-    pub(crate) fn add_trigger_behaviour(&mut self, trigger: T, behaviour: TriggerBehaviour<S, T>) {
-        self.trigger_behaviours.insert(trigger, behaviour);
+    /// Add `behaviour` alongside those already added for `trigger`.  An
+    /// unguarded behaviour replaces any earlier unguarded one, so the last
+    /// plain `permit` for a trigger wins.
+    pub(crate) fn add_trigger_behaviour(
+        &mut self,
+        trigger: T,
+        behaviour: TriggerBehaviour<'a, S, T, O>,
+    ) {
+        let behaviours = self.trigger_behaviours.get_or_default(trigger);
+        if behaviour.guard().is_none() {
+            behaviours.retain(|b| b.fallback || b.guard().is_some());
+        }
+        behaviours.push(behaviour);
+        self.last_added_trigger = Some(trigger);
+    }
+
+    /// Make `behaviour` the fallback for the most recently added trigger
+    pub(crate) fn add_fallback_behaviour(&mut self, mut behaviour: TriggerBehaviour<'a, S, T, O>) {
+        let Some(trigger) = self.last_added_trigger else {
+            self.stray_otherwise = true;
+            return;
+        };
+        behaviour.fallback = true;
+        self.trigger_behaviours
+            .get_or_default(trigger)
+            .push(behaviour);
+    }
+
+    /// The metadata of the most recently added trigger behaviour
+    pub(crate) fn last_behaviour_metadata(&mut self) -> Option<&mut Metadata> {
+        Some(&mut self.last_behaviour()?.metadata)
+    }
+
+    /// The most recently added trigger behaviour
+    pub(crate) fn last_behaviour(&mut self) -> Option<&mut TriggerBehaviour<'a, S, T, O>> {
+        let trigger = self.last_added_trigger?;
+        self.trigger_behaviours.get_mut(&trigger)?.last_mut()
+    }
+
+    /// The metadata of every behaviour configured for `trigger`
+    pub(crate) fn behaviour_metadata(&self, trigger: T) -> Vec<Metadata> {
+        self.trigger_behaviours
+            .get(&trigger)
+            .map(|behaviours| behaviours.iter().map(|b| b.metadata.clone()).collect())
+            .unwrap_or_default()
     }
 
     pub fn add_entry_action<F>(&mut self, f: F)
     where
-        F: FnMut(&Transition<S, T>, &mut O) + 'static,
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
     {
-        self.entry_actions.push(Box::new(f));
+        self.add_entry_action_with_priority(0, f);
+    }
+
+    /// Entry actions run in ascending `priority`, then registration order
+    pub fn add_entry_action_with_priority<F>(&mut self, priority: i32, f: F)
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        let info = ActionInfo::new("action", priority);
+        let action = Rc::new(RefCell::new(f));
+        insert_ordered(&mut self.entry_actions, info, action);
+    }
+
+    pub fn add_entry_action_obj<A>(&mut self, mut action: A)
+    where
+        A: EntryAction<S, T, O> + 'a,
+    {
+        let info = ActionInfo::new(action.description(), 0);
+        let f = move |t: &Transition<S, T>, o: &mut O| action.on_entry(t, o);
+        let action = Rc::new(RefCell::new(f));
+        insert_ordered(&mut self.entry_actions, info, action);
+    }
+
+    pub fn add_first_entry_action<F>(&mut self, f: F)
+    where
+        F: FnOnce(&Transition<S, T>, &mut O) + 'a,
+    {
+        let mut f = Some(f);
+        let action = Rc::new(RefCell::new(move |t: &Transition<S, T>, o: &mut O| {
+            if let Some(f) = f.take() {
+                f(t, o)
+            }
+        }));
+        self.first_entry_actions.push(action);
     }
 
     pub fn add_exit_action<F>(&mut self, f: F)
     where
-        F: FnMut(&Transition<S, T>, &mut O) + 'static,
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        self.add_exit_action_with_priority(0, f);
+    }
+
+    /// Exit actions run in ascending `priority`, then registration order
+    pub fn add_exit_action_with_priority<F>(&mut self, priority: i32, f: F)
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
     {
-        self.exit_actions.push(Box::new(f));
+        let info = ActionInfo::new("action", priority);
+        let action = Rc::new(RefCell::new(f));
+        insert_ordered(&mut self.exit_actions, info, action);
+    }
+
+    pub fn add_exit_action_obj<A>(&mut self, mut action: A)
+    where
+        A: ExitAction<S, T, O> + 'a,
+    {
+        let info = ActionInfo::new(action.description(), 0);
+        let f = move |t: &Transition<S, T>, o: &mut O| action.on_exit(t, o);
+        let action = Rc::new(RefCell::new(f));
+        insert_ordered(&mut self.exit_actions, info, action);
+    }
+
+    pub fn add_compensate_action<F>(&mut self, f: F)
+    where
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
+    {
+        let action = Rc::new(RefCell::new(f));
+        self.compensate_actions.push(action);
+    }
+
+    pub fn add_command_action<F>(&mut self, f: F)
+    where
+        F: FnMut(&Transition<S, T>, &O) -> Vec<Command<T>> + 'a,
+    {
+        self.command_actions.push(Rc::new(RefCell::new(f)));
+    }
+
+    /// The commands returned by every command action for `transition`, in
+    /// registration order
+    pub(crate) fn commands(
+        &mut self,
+        transition: &Transition<S, T>,
+        object: &O,
+    ) -> Vec<Command<T>> {
+        self.command_actions
+            .iter_mut()
+            .flat_map(|action| (*action.borrow_mut())(transition, object))
+            .collect()
+    }
+
+    /// Remove every behaviour and internal action for `trigger`
+    pub(crate) fn forget(&mut self, trigger: T) {
+        self.trigger_behaviours.remove(&trigger);
+        self.internal_actions.remove(&trigger);
+        if self.last_added_trigger == Some(trigger) {
+            self.last_added_trigger = None;
+        }
+    }
+
+    /// Remove every entry, exit, command and compensate action.  Internal
+    /// actions belong to their transition and go with [`Self::forget`].
+    pub(crate) fn clear_actions(&mut self) {
+        self.entry_actions = ActionList::new();
+        self.first_entry_actions = ActionList::new();
+        self.exit_actions = ActionList::new();
+        self.command_actions = ActionList::new();
+        self.compensate_actions = ActionList::new();
+    }
+
+    pub(crate) fn has_compensate_actions(&self) -> bool {
+        !self.compensate_actions.is_empty()
+    }
+
+    /// Entry actions in the order they will run
+    pub(crate) fn entry_action_infos(&self) -> Vec<ActionInfo> {
+        self.entry_actions.iter().map(|a| a.info.clone()).collect()
+    }
+
+    /// Exit actions in the order they will run
+    pub(crate) fn exit_action_infos(&self) -> Vec<ActionInfo> {
+        self.exit_actions.iter().map(|a| a.info.clone()).collect()
     }
 
     pub fn add_internal_action<F>(&mut self, trigger: T, f: F)
     where
-        F: FnMut(&Transition<S, T>, &mut O) + 'static,
+        F: FnMut(&Transition<S, T>, &mut O) + 'a,
     {
+        let info = ActionInfo::new("action", 0);
+        let action = Rc::new(RefCell::new(f));
         self.internal_actions
-            .entry(trigger)
-            .or_default()
-            .push(Box::new(f));
+            .get_or_default(trigger)
+            .push(OrderedAction { info, action });
     }
 
+    /// Internal actions for `trigger` in the order they will run
+    pub(crate) fn internal_action_infos(&self, trigger: &T) -> Vec<ActionInfo> {
+        self.internal_actions
+            .get(trigger)
+            .map(|actions| actions.iter().map(|a| a.info.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// What this representation and everything it owns takes up
+    pub(crate) fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            representations: 1,
+            representation_bytes: mem::size_of::<Self>()
+                + self.metadata.heap_bytes()
+                + self.trigger_behaviours.heap_bytes()
+                + self.trigger_slots.capacity() * mem::size_of::<Option<u16>>()
+                + self.internal_actions.heap_bytes(),
+            ..MemoryStats::default()
+        };
+        for (_, behaviours) in self.trigger_behaviours.iter() {
+            stats.behaviours += behaviours.len();
+            stats.behaviour_bytes += behaviours.capacity()
+                * mem::size_of::<TriggerBehaviour<'a, S, T, O>>()
+                + behaviours.iter().map(|b| b.heap_bytes()).sum::<usize>();
+        }
+        for ordered in self.entry_actions.iter().chain(self.exit_actions.iter()) {
+            stats.actions += 1;
+            stats.action_bytes +=
+                mem::size_of_val(&*ordered.action) + ordered.info.description.capacity();
+        }
+        for action in self
+            .first_entry_actions
+            .iter()
+            .chain(self.compensate_actions.iter())
+        {
+            stats.actions += 1;
+            stats.action_bytes += mem::size_of_val(&**action);
+        }
+        for (_, actions) in self.internal_actions.iter() {
+            stats.actions += actions.len();
+            stats.action_bytes += actions.heap_bytes()
+                + actions
+                    .iter()
+                    .map(|a| mem::size_of_val(&*a.action) + a.info.description.capacity())
+                    .sum::<usize>();
+        }
+        stats.action_bytes += self.entry_actions.heap_bytes()
+            + self.first_entry_actions.heap_bytes()
+            + self.exit_actions.heap_bytes()
+            + self.compensate_actions.heap_bytes();
+        stats
+    }
+
+    /// Record the index of each transition's destination so firing doesn't
+    /// have to look it up
+    pub(crate) fn resolve_destinations(&mut self, index_of: impl Fn(S) -> u16) {
+        if let Some(entry_guard) = &mut self.entry_guard {
+            entry_guard.fallback_index = index_of(entry_guard.fallback);
+        }
+        for behaviours in self.trigger_behaviours.values_mut() {
+            for behaviour in behaviours.iter_mut() {
+                match &mut behaviour.kind {
+                    BehaviourKind::Transitioning(t) => t.resolve(index_of(t.destination())),
+                    BehaviourKind::Dynamic(d) => d.resolve_destinations(&index_of),
+                    BehaviourKind::Internal(_) => {}
+                }
+            }
+        }
+    }
+
+    /// The destination of every transition out of this state
+    pub(crate) fn destinations(&self) -> impl Iterator<Item = S> + '_ {
+        self.trigger_behaviours
+            .iter()
+            .flat_map(|(_, behaviours)| behaviours)
+            .flat_map(|behaviour| match &behaviour.kind {
+                BehaviourKind::Transitioning(t) => Vec::from([t.destination()]),
+                BehaviourKind::Dynamic(d) => d.destinations().collect(),
+                BehaviourKind::Internal(_) => Vec::new(),
+            })
+            .chain(self.entry_guard.as_ref().map(|g| g.fallback))
+    }
+
+    pub(crate) fn info(&self) -> StateInfo<S, T> {
+        let transitions = self
+            .trigger_behaviours
+            .iter()
+            .flat_map(|(trigger, behaviours)| behaviours.iter().map(move |b| (*trigger, b)))
+            .flat_map(|(trigger, behaviour)| {
+                // A dynamic transition is described as one transition per
+                // possible destination
+                let destinations: Vec<_> = match &behaviour.kind {
+                    BehaviourKind::Transitioning(t) => Vec::from([Some(t.destination())]),
+                    BehaviourKind::Dynamic(d) => d.destinations().map(Some).collect(),
+                    BehaviourKind::Internal(_) => Vec::from([None]),
+                };
+                destinations
+                    .into_iter()
+                    .map(move |destination| TransitionInfo {
+                        trigger,
+                        destination,
+                        guard_description: match behaviour.guard() {
+                            Some(guard) => Some(guard.description().to_string()),
+                            None => behaviour.fallback.then(|| String::from("otherwise")),
+                        },
+                        guard_conditions: behaviour.guard().map_or(0, Guard::conditions),
+                        actions: match destination {
+                            Some(_) => Vec::new(),
+                            None => self.internal_action_infos(&trigger),
+                        },
+                        metadata: behaviour.metadata.clone(),
+                        label: behaviour.label.as_deref().map(String::from),
+                    })
+            })
+            .collect();
+        StateInfo {
+            state: self.state,
+            metadata: self.metadata.clone(),
+            region: self.region.clone(),
+            step: self.step,
+            entry_actions: self.entry_action_infos(),
+            exit_actions: self.exit_action_infos(),
+            transitions,
+        }
+    }
+
+    /// Every trigger with a behaviour in this state
+    pub(crate) fn triggers(&self) -> impl Iterator<Item = &T> {
+        self.trigger_behaviours.keys()
+    }
+
+    /// Index the behaviours by interned trigger id so
+    /// [`Self::get_interned_behaviour`] doesn't have to scan for them
+    pub(crate) fn intern_triggers<H>(&mut self, triggers: &Interner<TriggerKey<T>, H>) {
+        self.trigger_slots = triggers
+            .keys()
+            .iter()
+            .map(|key| {
+                self.trigger_behaviours
+                    .position_of_key(key)
+                    .map(|index| u16::try_from(index).expect("at most 65536 triggers"))
+            })
+            .collect();
+    }
+
+    /// Triggers which would select a behaviour for `object`, in the order
+    /// they were first configured
+    pub(crate) fn permitted_triggers(&self, object: &O) -> Vec<T> {
+        self.trigger_behaviours
+            .iter()
+            .filter(|(trigger, behaviours)| {
+                self.select_behaviour(Some(behaviours), **trigger, object, None, |_, _| ())
+                    .is_ok()
+            })
+            .map(|(trigger, _)| *trigger)
+            .collect()
+    }
+
+    /// Find the single behaviour for `trigger` whose guard is met by `object`
+    #[cfg(test)]
     pub(crate) fn get_behaviour(
         &self,
         trigger: T,
-    ) -> Result<TriggerBehaviour<S, T>, StateMachineError<S, T>> {
-        let b = self.trigger_behaviours.get(&trigger).ok_or(
-            StateMachineError::TriggerNotPermitted {
+        object: &O,
+    ) -> Result<&TriggerBehaviour<'a, S, T, O>, StateMachineError<S, T>> {
+        self.select_behaviour(
+            self.trigger_behaviours.get(&trigger),
+            trigger,
+            object,
+            None,
+            |_, _| (),
+        )
+    }
+
+    /// Like [`Self::get_behaviour`] but looked up by the id `trigger` was
+    /// interned as.  Only valid after [`Self::intern_triggers`].  Guards see
+    /// `payload`, and `observe` is called with every guard evaluated and
+    /// whether it was met.
+    pub(crate) fn get_interned_behaviour(
+        &self,
+        trigger_id: u16,
+        trigger: T,
+        object: &O,
+        payload: Option<&Payload>,
+        observe: impl FnMut(&Guard<'a, O>, bool),
+    ) -> Result<&TriggerBehaviour<'a, S, T, O>, StateMachineError<S, T>> {
+        let behaviours = self
+            .trigger_slots
+            .get(usize::from(trigger_id))
+            .copied()
+            .flatten()
+            .map(|slot| self.trigger_behaviours.get_index(usize::from(slot)));
+        self.select_behaviour(behaviours, trigger, object, payload, observe)
+    }
+
+    fn select_behaviour<'r>(
+        &self,
+        behaviours: Option<&'r Vec<TriggerBehaviour<'a, S, T, O>>>,
+        trigger: T,
+        object: &O,
+        payload: Option<&Payload>,
+        mut observe: impl FnMut(&Guard<'a, O>, bool),
+    ) -> Result<&'r TriggerBehaviour<'a, S, T, O>, StateMachineError<S, T>> {
+        let behaviours = behaviours.ok_or(StateMachineError::TriggerNotPermitted {
+            state: self.state,
+            trigger,
+        })?;
+        let guard_met = |b: &&TriggerBehaviour<'a, S, T, O>| {
+            b.guard().is_none_or(|guard| {
+                let met = guard.evaluate_with(object, payload);
+                observe(guard, met);
+                met
+            })
+        };
+        let fallback = behaviours.iter().find(|b| b.fallback);
+        let mut met = behaviours.iter().filter(|b| !b.fallback).filter(guard_met);
+        match (met.next(), met.next()) {
+            (Some(b), None) => Ok(b),
+            (Some(first), Some(second)) => Err(StateMachineError::AmbiguousTransition {
+                state: self.state,
+                trigger,
+                labels: [first, second]
+                    .into_iter()
+                    .chain(met)
+                    .filter_map(|b| b.label.as_deref().map(String::from))
+                    .collect(),
+            }),
+            (None, _) => fallback.ok_or_else(|| StateMachineError::GuardConditionNotMet {
                 state: self.state,
                 trigger,
-            },
-        )?;
-        Ok(b.clone())
+                guard_descriptions: behaviours
+                    .iter()
+                    .filter_map(|b| b.guard())
+                    .map(|g| g.description().to_string())
+                    .collect(),
+            }),
+        }
     }
 
-    pub fn enter(&mut self, transition: &Transition<S, T>, state_object: Arc<Mutex<O>>) {
-        for action in self.entry_actions.iter_mut() {
-            let mut object = state_object.lock().unwrap();
-            action(transition, &mut *object);
+    /// Run the entry actions.  First entry actions run before the others and
+    /// are consumed, so they only ever run once.  `ran` is called after each
+    /// of the others, here and in the other action runners.
+    pub fn enter(
+        &mut self,
+        transition: &Transition<S, T>,
+        object: &mut O,
+        ran: impl FnMut(&ActionInfo),
+    ) {
+        for action in self.first_entry_actions.drain() {
+            (*action.borrow_mut())(transition, object);
         }
+        run_ordered(&mut self.entry_actions, transition, object, ran);
+    }
+
+    pub fn exit(
+        &mut self,
+        transition: &Transition<S, T>,
+        object: &mut O,
+        ran: impl FnMut(&ActionInfo),
+    ) {
+        run_ordered(&mut self.exit_actions, transition, object, ran);
     }
 
-    pub fn exit(&mut self, transition: &Transition<S, T>, state_object: Arc<Mutex<O>>) {
-        for action in self.exit_actions.iter_mut() {
-            let mut object = state_object.lock().unwrap();
-            action(transition, &mut *object);
+    /// Run the compensations in registration order.  `transition` is the
+    /// one which entered the state.
+    pub fn compensate(&mut self, transition: &Transition<S, T>, object: &mut O) {
+        for action in self.compensate_actions.iter_mut() {
+            (*action.borrow_mut())(transition, object);
         }
     }
 
     pub fn fire_internal_actions(
         &mut self,
         transition: &Transition<S, T>,
-        state_object: Arc<Mutex<O>>,
+        object: &mut O,
+        ran: impl FnMut(&ActionInfo),
     ) {
-        let Some(actions) = self.internal_actions.get_mut(&transition.trigger) else {
+        let Some(actions) = transition
+            .trigger
+            .as_ref()
+            .and_then(|trigger| self.internal_actions.get_mut(trigger))
+        else {
             return;
         };
-        for action in actions.iter_mut() {
-            let mut object = state_object.lock().unwrap();
-            action(transition, &mut *object);
-        }
+        run_ordered(actions, transition, object, ran);
+    }
+}
+
+fn run_ordered<S, T, O>(
+    actions: &mut ActionList<OrderedAction<'_, S, T, O>>,
+    transition: &Transition<S, T>,
+    object: &mut O,
+    mut ran: impl FnMut(&ActionInfo),
+) {
+    for action in actions.iter_mut() {
+        (*action.action.borrow_mut())(transition, object);
+        ran(&action.info);
     }
 }
 
@@ -119,14 +621,15 @@ where
 mod tests {
     use super::*;
     use crate::{
+        guard::Guard,
         tests::{State, Trigger},
-        transition,
     };
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn unconfigured_trigger_errors() {
         let rep = StateRepresentation::<_, _, ()>::new(State::State1);
-        let result = rep.get_behaviour(Trigger::Trig);
+        let result = rep.get_behaviour(Trigger::Trig, &());
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err(),
@@ -137,11 +640,117 @@ mod tests {
         );
     }
 
+    #[test]
+    fn interned_lookup_finds_behaviour_by_id() {
+        let mut rep = StateRepresentation::<_, _, ()>::new(State::State1);
+        rep.add_trigger_behaviour(
+            Trigger::Trig2,
+            TriggerBehaviour::transitioning(State::State2, None),
+        );
+        // Trig is interned by another state
+        let triggers: Interner<_> = [Trigger::Trig, Trigger::Trig2]
+            .iter()
+            .map(|t| TriggerMatching::Value.key(t))
+            .collect();
+        rep.intern_triggers(&triggers);
+
+        assert!(rep
+            .get_interned_behaviour(1, Trigger::Trig2, &(), None, |_, _| ())
+            .is_ok());
+        assert_eq!(
+            rep.get_interned_behaviour(0, Trigger::Trig, &(), None, |_, _| ())
+                .unwrap_err(),
+            StateMachineError::TriggerNotPermitted {
+                state: State::State1,
+                trigger: Trigger::Trig
+            }
+        );
+    }
+
+    #[test]
+    fn failing_guard_reports_description() {
+        let mut rep = StateRepresentation::<_, _, i32>::new(State::State1);
+        rep.add_trigger_behaviour(
+            Trigger::Trig,
+            TriggerBehaviour::transitioning(
+                State::State2,
+                Some(Guard::new("is positive", |o: &i32| *o > 0)),
+            ),
+        );
+        assert!(rep.get_behaviour(Trigger::Trig, &1).is_ok());
+        assert_eq!(
+            rep.get_behaviour(Trigger::Trig, &0).unwrap_err(),
+            StateMachineError::GuardConditionNotMet {
+                state: State::State1,
+                trigger: Trigger::Trig,
+                guard_descriptions: vec!["is positive".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn later_unguarded_behaviour_replaces_earlier() {
+        let mut rep = StateRepresentation::<_, _, ()>::new(State::State1);
+        for destination in [State::State1, State::State2] {
+            rep.add_trigger_behaviour(
+                Trigger::Trig,
+                TriggerBehaviour::transitioning(destination, None),
+            );
+        }
+        assert!(rep.get_behaviour(Trigger::Trig, &()).is_ok());
+        assert_eq!(rep.destinations().collect::<Vec<_>>(), [State::State2]);
+    }
+
+    #[test]
+    fn two_met_guards_are_ambiguous() {
+        let mut rep = StateRepresentation::<_, _, ()>::new(State::State1);
+        for destination in [State::State1, State::State2] {
+            rep.add_trigger_behaviour(
+                Trigger::Trig,
+                TriggerBehaviour::transitioning(
+                    destination,
+                    Some(Guard::new("always", |_: &()| true)),
+                ),
+            );
+        }
+        assert_eq!(
+            rep.get_behaviour(Trigger::Trig, &()).unwrap_err(),
+            StateMachineError::AmbiguousTransition {
+                state: State::State1,
+                trigger: Trigger::Trig,
+                labels: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn entry_actions_run_in_priority_order() {
+        let mut rep = StateRepresentation::<_, _, Vec<i32>>::new(State::State1);
+        rep.add_entry_action_with_priority(5, |_, o| o.push(5));
+        rep.add_entry_action(|_, o| o.push(0));
+        rep.add_entry_action_with_priority(-1, |_, o| o.push(-1));
+        rep.add_entry_action_with_priority(5, |_, o| o.push(6));
+
+        let priorities: Vec<i32> = rep
+            .entry_action_infos()
+            .iter()
+            .map(|a| a.priority)
+            .collect();
+        assert_eq!(priorities, vec![-1, 0, 5, 5]);
+
+        let mut object = Vec::new();
+        rep.enter(
+            &Transition::new(State::State2, Trigger::Trig, State::State1),
+            &mut object,
+            |_| (),
+        );
+        assert_eq!(object, vec![-1, 0, 5, 6]);
+    }
+
     #[test]
     fn internal_actions_fire_for_correct_trigger() -> eyre::Result<()> {
         let trig_fired = Arc::new(Mutex::new(false));
         let trig_fired_clone = Arc::clone(&trig_fired);
-        let state = Arc::new(Mutex::new(()));
         let mut rep = StateRepresentation::<_, _, ()>::new(State::State1);
         rep.add_internal_action(Trigger::Trig, move |_, _| {
             *trig_fired_clone.lock().unwrap() = true
@@ -149,7 +758,8 @@ mod tests {
         rep.add_internal_action(Trigger::Trig2, |_, _| panic!("trig2 should not have fired"));
         rep.fire_internal_actions(
             &Transition::new(State::State1, Trigger::Trig, State::State1),
-            Arc::clone(&state),
+            &mut (),
+            |_| (),
         );
         assert!(*trig_fired.lock().unwrap(), "trig should have fired");
         Ok(())
@@ -160,13 +770,13 @@ mod tests {
         let count = Arc::new(Mutex::new(0));
         let c1 = Arc::clone(&count);
         let c2 = Arc::clone(&count);
-        let state = Arc::new(Mutex::new(()));
         let mut rep = StateRepresentation::<_, _, ()>::new(State::State1);
         rep.add_internal_action(Trigger::Trig, move |_, _| *c1.lock().unwrap() += 1);
         rep.add_internal_action(Trigger::Trig, move |_, _| *c2.lock().unwrap() += 1);
         rep.fire_internal_actions(
             &Transition::new(State::State1, Trigger::Trig, State::State1),
-            Arc::clone(&state),
+            &mut (),
+            |_| (),
         );
         assert_eq!(*count.lock().unwrap(), 2, "trig should have fired twice");
         Ok(())
@@ -1,14 +1,160 @@
-use std::fmt::Debug;
-use thiserror::Error;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
 
-#[derive(Error, Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StateMachineError<S, T> {
-    #[error("state {state:?} not configured")]
-    StateNotConfigured { state: S },
-    #[error("trigger {trigger:?} not permitted for {state:?}")]
-    TriggerNotPermitted { state: S, trigger: T },
-    #[error("StateConfig for {state:?} still in use in Builder")]
-    ConfigStillInUse { state: S },
-    #[error("unknown StateMachine error")]
+    StateNotConfigured {
+        state: S,
+    },
+    TriggerNotPermitted {
+        state: S,
+        trigger: T,
+    },
+    GuardConditionNotMet {
+        state: S,
+        trigger: T,
+        guard_descriptions: Vec<String>,
+    },
+    AmbiguousTransition {
+        state: S,
+        trigger: T,
+        /// The labels of the met transitions which have one
+        labels: Vec<String>,
+    },
+    Rejected {
+        trigger: T,
+        reason: String,
+    },
+    ConfigStillInUse {
+        state: S,
+    },
+    /// The state isn't one of those given to
+    /// [`crate::StateMachineBuilder::with_state_universe`]
+    UnknownState {
+        state: S,
+    },
+    /// The trigger was fired without a payload of the type declared with
+    /// [`crate::StateMachineBuilder::set_trigger_parameters`]
+    InvalidTriggerParameters {
+        trigger: T,
+        expected: &'static str,
+    },
+    /// [`crate::StateMachine::fire_by_name`] was given a name which is not a
+    /// trigger
+    UnknownTrigger {
+        name: String,
+        /// Configured triggers with names close to `name`, closest first
+        suggestions: Vec<String>,
+    },
+    /// The machine was paused with [`crate::PauseMode::Reject`].  `trigger`
+    /// is `None` for [`crate::StateMachine::reenter`] and
+    /// [`crate::StateMachine::compensate_to`].
+    MachinePaused {
+        state: S,
+        trigger: Option<T>,
+    },
+    /// The machine was stopped with [`crate::StateMachine::stop`].  `trigger`
+    /// is `None` as for [`Self::MachinePaused`].
+    MachineStopped {
+        state: S,
+        trigger: Option<T>,
+    },
+    /// [`crate::StateMachine::compensate_to`] was asked to unwind to a state
+    /// the machine hasn't been in
+    NotInHistory {
+        state: S,
+    },
+    /// [`crate::StateConfig::otherwise`] was called for `state` before any
+    /// transition was added to it
+    OtherwiseWithoutTransition {
+        state: S,
+    },
+    /// Entry guards added with [`crate::StateConfig::enter_if`] redirect
+    /// a transition into `state` round in a cycle
+    EntryGuardCycle {
+        state: S,
+    },
     Unknown,
 }
+
+impl<S: Debug, T: Debug> Display for StateMachineError<S, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::StateNotConfigured { state } => write!(f, "state {state:?} not configured"),
+            Self::TriggerNotPermitted { state, trigger } => {
+                write!(f, "trigger {trigger:?} not permitted for {state:?}")
+            }
+            Self::GuardConditionNotMet {
+                state,
+                trigger,
+                guard_descriptions,
+            } => write!(
+                f,
+                "trigger {trigger:?} is valid for {state:?} but guard conditions are not met: {guard_descriptions:?}"
+            ),
+            Self::AmbiguousTransition {
+                state,
+                trigger,
+                labels,
+            } => {
+                write!(
+                    f,
+                    "multiple permitted transitions are configured for trigger {trigger:?} in {state:?}"
+                )?;
+                if !labels.is_empty() {
+                    write!(f, ": {}", labels.join(", "))?;
+                }
+                Ok(())
+            }
+            Self::Rejected { trigger, reason } => {
+                write!(f, "trigger {trigger:?} rejected: {reason}")
+            }
+            Self::ConfigStillInUse { state } => {
+                write!(f, "StateConfig for {state:?} still in use in Builder")
+            }
+            Self::UnknownState { state } => {
+                write!(f, "state {state:?} is not in the state universe")
+            }
+            Self::InvalidTriggerParameters { trigger, expected } => {
+                write!(f, "trigger {trigger:?} must be fired with parameters of type {expected}")
+            }
+            Self::UnknownTrigger { name, suggestions } => {
+                write!(f, "unknown trigger {name:?}")?;
+                if let Some((last, others)) = suggestions.split_last() {
+                    write!(f, "; did you mean ")?;
+                    for other in others {
+                        write!(f, "{other:?}, ")?;
+                    }
+                    if !others.is_empty() {
+                        write!(f, "or ")?;
+                    }
+                    write!(f, "{last:?}?")?;
+                }
+                Ok(())
+            }
+            Self::MachinePaused { state, trigger } => {
+                if let Some(trigger) = trigger {
+                    write!(f, "trigger {trigger:?} ")?;
+                }
+                write!(f, "rejected in {state:?}: machine is paused")
+            }
+            Self::MachineStopped { state, trigger } => {
+                if let Some(trigger) = trigger {
+                    write!(f, "trigger {trigger:?} ")?;
+                }
+                write!(f, "rejected in {state:?}: machine is stopped")
+            }
+            Self::NotInHistory { state } => write!(f, "{state:?} is not in the history"),
+            Self::OtherwiseWithoutTransition { state } => {
+                write!(f, "otherwise for {state:?} must follow a transition")
+            }
+            Self::EntryGuardCycle { state } => {
+                write!(f, "entry guards redirect {state:?} in a cycle")
+            }
+            Self::Unknown => write!(f, "unknown StateMachine error"),
+        }
+    }
+}
+
+impl<S: Debug, T: Debug> core::error::Error for StateMachineError<S, T> {}
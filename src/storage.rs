@@ -0,0 +1,148 @@
+use core::cell::{Ref, RefCell};
+use core::fmt::Debug;
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// How a [`crate::StateMachine`] holds its state object.
+///
+/// The machine only needs shared access for [`crate::StateMachine::object`];
+/// actions and guards are given the object through [`Storage::get_mut`]
+/// while firing, so storages which don't need locking don't pay for it.
+///
+/// | Storage           | `object()` returns  |
+/// |-------------------|---------------------|
+/// | `Arc<Mutex<O>>`   | `MutexGuard<O>`     |
+/// | `Mutex<O>`        | `MutexGuard<O>`     |
+/// | `RefCell<O>`      | `Ref<O>`            |
+/// | [`Owned<O>`]      | `&O`                |
+///
+/// The `Mutex` storages need the `std` feature.
+pub trait Storage<O> {
+    type Guard<'g>: Deref<Target = O>
+    where
+        Self: 'g;
+
+    fn wrap(object: O) -> Self;
+
+    fn get(&self) -> Self::Guard<'_>;
+
+    fn get_mut(&mut self) -> &mut O;
+}
+
+/// The storage [`crate::StateMachineBuilder::build`] uses: `Arc<Mutex<O>>`
+/// with the `std` feature and `RefCell<O>` without it
+#[cfg(feature = "std")]
+pub type DefaultStorage<O> = Arc<Mutex<O>>;
+/// The storage [`crate::StateMachineBuilder::build`] uses: `Arc<Mutex<O>>`
+/// with the `std` feature and `RefCell<O>` without it
+#[cfg(not(feature = "std"))]
+pub type DefaultStorage<O> = RefCell<O>;
+
+/// The default storage, kept for compatibility with earlier versions
+#[cfg(feature = "std")]
+impl<O> Storage<O> for Arc<Mutex<O>> {
+    type Guard<'g>
+        = MutexGuard<'g, O>
+    where
+        O: 'g;
+
+    fn wrap(object: O) -> Self {
+        Arc::new(Mutex::new(object))
+    }
+
+    fn get(&self) -> Self::Guard<'_> {
+        self.lock().unwrap()
+    }
+
+    fn get_mut(&mut self) -> &mut O {
+        Arc::get_mut(self)
+            .expect("the state object is never shared outside the machine")
+            .get_mut()
+            .unwrap()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<O> Storage<O> for Mutex<O> {
+    type Guard<'g>
+        = MutexGuard<'g, O>
+    where
+        O: 'g;
+
+    fn wrap(object: O) -> Self {
+        Mutex::new(object)
+    }
+
+    fn get(&self) -> Self::Guard<'_> {
+        self.lock().unwrap()
+    }
+
+    fn get_mut(&mut self) -> &mut O {
+        Mutex::get_mut(self).unwrap()
+    }
+}
+
+impl<O> Storage<O> for RefCell<O> {
+    type Guard<'g>
+        = Ref<'g, O>
+    where
+        O: 'g;
+
+    fn wrap(object: O) -> Self {
+        RefCell::new(object)
+    }
+
+    fn get(&self) -> Self::Guard<'_> {
+        self.borrow()
+    }
+
+    fn get_mut(&mut self) -> &mut O {
+        RefCell::get_mut(self)
+    }
+}
+
+/// Stores the object directly with no locking or reference counting
+#[derive(Debug, Default)]
+pub struct Owned<O>(pub O);
+
+impl<O> Storage<O> for Owned<O> {
+    type Guard<'g>
+        = &'g O
+    where
+        O: 'g;
+
+    fn wrap(object: O) -> Self {
+        Owned(object)
+    }
+
+    fn get(&self) -> Self::Guard<'_> {
+        &self.0
+    }
+
+    fn get_mut(&mut self) -> &mut O {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<St: Storage<i32>>() {
+        let mut storage = St::wrap(1);
+        *storage.get_mut() += 1;
+        assert_eq!(*storage.get(), 2);
+    }
+
+    #[test]
+    fn all_storages_round_trip() {
+        #[cfg(feature = "std")]
+        {
+            round_trip::<Arc<Mutex<i32>>>();
+            round_trip::<Mutex<i32>>();
+        }
+        round_trip::<RefCell<i32>>();
+        round_trip::<Owned<i32>>();
+    }
+}
@@ -0,0 +1,70 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The candidates close enough to `name` to be what was meant, closest
+/// first.  Case is ignored and at most three are returned.
+pub(crate) fn did_you_mean(
+    name: &str,
+    candidates: impl IntoIterator<Item = String>,
+) -> Vec<String> {
+    let name = name.to_lowercase();
+    let allowed = (name.chars().count() / 3).max(1);
+    let mut close: Vec<_> = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= allowed)
+        .collect();
+    close.sort_by_key(|(distance, _)| *distance);
+    close.into_iter().take(3).map(|(_, c)| c).collect()
+}
+
+/// Levenshtein distance in characters, counting swapping two neighbours
+/// as one edit since that is the commonest typo
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j] is the distance between a[..i] and b[..j]
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("switch", "switch"), 0);
+        assert_eq!(edit_distance("swtich", "switch"), 1);
+        assert_eq!(edit_distance("", "on"), 2);
+        assert_eq!(edit_distance("stop", "start"), 3);
+    }
+
+    #[test]
+    fn suggests_close_names_closest_first() {
+        let candidates = ["Start", "Stop", "Step", "Reset"].map(|c| c.to_string());
+        assert_eq!(did_you_mean("stpo", candidates.clone()), ["Stop"]);
+        assert_eq!(did_you_mean("STEP", candidates.clone()), ["Step", "Stop"]);
+        assert!(did_you_mean("launch", candidates).is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+use core::time::Duration;
+
+use crate::action::ActionInfo;
+use crate::debugger::DebugPhase;
+use crate::transition::Transition;
+use crate::StateMachineError;
+
+/// Receives what happens inside [`crate::StateMachine::fire`], for metrics,
+/// tracing and audit integrations.  Attach with
+/// [`crate::StateMachine::add_telemetry_sink`].
+///
+/// Every hook does nothing by default, so a sink only implements the ones it
+/// cares about.  Durations are measured by the machine's [`crate::Clock`]
+/// from the start of the fire.  Triggers fired with
+/// [`crate::StateMachine::process_all`] are not reported.
+pub trait TelemetrySink<S, T> {
+    /// A trigger was fired in `state`, before any middleware ran
+    fn fire_started(&mut self, _state: S, _trigger: T) {}
+
+    /// A guard was evaluated while choosing what `trigger` does in `state`
+    fn guard_evaluated(&mut self, _state: S, _trigger: T, _guard: &str, _met: bool) {}
+
+    /// An entry, exit or internal action configured on a state has run.
+    /// Static actions and first entry actions are not reported.
+    fn action_run(
+        &mut self,
+        _phase: DebugPhase,
+        _transition: &Transition<S, T>,
+        _action: &ActionInfo,
+    ) {
+    }
+
+    /// A transition, internal or not, finished with every action run
+    fn transition_committed(&mut self, _transition: &Transition<S, T>, _duration: Duration) {}
+
+    /// The trigger fired in `state` was rejected, by the machine or a
+    /// middleware
+    fn fire_rejected(
+        &mut self,
+        _state: S,
+        _trigger: T,
+        _error: &StateMachineError<S, T>,
+        _duration: Duration,
+    ) {
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    struct Recorder<'r>(&'r RefCell<Vec<String>>);
+
+    impl TelemetrySink<State, Trigger> for Recorder<'_> {
+        fn fire_started(&mut self, state: State, trigger: Trigger) {
+            self.0
+                .borrow_mut()
+                .push(format!("fire {trigger:?} in {state:?}"));
+        }
+
+        fn guard_evaluated(&mut self, _state: State, _trigger: Trigger, guard: &str, met: bool) {
+            self.0.borrow_mut().push(format!("guard {guard} {met}"));
+        }
+
+        fn action_run(
+            &mut self,
+            phase: DebugPhase,
+            _transition: &Transition<State, Trigger>,
+            _action: &ActionInfo,
+        ) {
+            self.0.borrow_mut().push(format!("{phase:?} action"));
+        }
+
+        fn transition_committed(&mut self, transition: &Transition<State, Trigger>, _: Duration) {
+            self.0
+                .borrow_mut()
+                .push(format!("committed to {:?}", transition.destination));
+        }
+
+        fn fire_rejected(
+            &mut self,
+            _state: State,
+            _trigger: Trigger,
+            error: &StateMachineError<State, Trigger>,
+            _: Duration,
+        ) {
+            self.0.borrow_mut().push(format!("rejected: {error}"));
+        }
+    }
+
+    #[test]
+    fn sinks_hear_every_step_of_a_fire() -> eyre::Result<()> {
+        let events = RefCell::new(Vec::new());
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit_if(
+                Trigger::Trig,
+                State::State2,
+                crate::Guard::new("always", |_| true),
+            )
+            .on_exit(|_, _| ());
+        builder.config(State::State2).on_entry(|_, _| ());
+        let mut machine = builder.build(())?;
+        machine.add_telemetry_sink(Recorder(&events));
+
+        machine.fire(Trigger::Trig)?;
+        assert!(machine.fire(Trigger::Trig).is_err());
+        assert!(machine.process_all([Trigger::Trig]).error.is_some());
+        drop(machine);
+
+        assert_eq!(
+            events.into_inner(),
+            [
+                "fire Trig in State1",
+                "guard always true",
+                "Exit action",
+                "Entry action",
+                "committed to State2",
+                "fire Trig in State2",
+                "rejected: trigger Trig not permitted for State2",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,401 @@
+//! Helpers for property-based tests of a machine, behind the `testing`
+//! feature.
+//!
+//! [`random_walk`] fires a random sequence of triggers the machine accepts
+//! and checks an invariant after every step.  A failure reports the
+//! triggers fired so far, which [`check_invariant`] replays.
+//!
+//! [`fire_from_bytes`] drives a machine from fuzzer input.
+//!
+//! [`assert_path!`] and [`assert_rejected!`] check expected behaviour with
+//! failure messages that show where the machine went instead.
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+use core::hash::{BuildHasher, Hash};
+
+use crate::action::StaticActions;
+use crate::storage::Storage;
+use crate::StateMachine;
+use crate::StateMachineError;
+
+pub use crate::{assert_path, assert_rejected};
+
+/// Why [`random_walk`] or [`check_invariant`] stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolation<S, T> {
+    /// Every trigger fired, including the one which broke the invariant
+    pub triggers: Vec<T>,
+    pub state: S,
+    /// Set if the last trigger was rejected rather than breaking the
+    /// invariant
+    pub rejection: Option<StateMachineError<S, T>>,
+}
+
+impl<S: Debug, T: Debug> Display for InvariantViolation<S, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.rejection {
+            Some(rejection) => write!(f, "{rejection}")?,
+            None => write!(f, "invariant broken in {:?}", self.state)?,
+        }
+        write!(f, " after triggers {:?}", self.triggers)
+    }
+}
+
+/// SplitMix64, which is plenty for picking triggers and keeps walks
+/// reproducible from their seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Fire up to `steps` triggers, each picked from
+/// [`StateMachine::permitted_triggers`] using `seed`, and call `invariant`
+/// with the state and object after every one.
+///
+/// Stops early once no trigger is permitted.  Returns the triggers fired,
+/// or the first violation.
+pub fn random_walk<S, T, O, St, A, H, F>(
+    machine: &mut StateMachine<'_, S, T, O, St, A, H>,
+    seed: u64,
+    steps: usize,
+    mut invariant: F,
+) -> Result<Vec<T>, InvariantViolation<S, T>>
+where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+    F: FnMut(S, &O) -> bool,
+{
+    let mut rng = Rng(seed);
+    let mut triggers = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        let permitted = machine.permitted_triggers();
+        if permitted.is_empty() {
+            break;
+        }
+        let trigger = permitted[rng.below(permitted.len())];
+        step(machine, trigger, &mut triggers, &mut invariant)?;
+    }
+    Ok(triggers)
+}
+
+/// Fire one permitted trigger per byte of `data`, the byte picking which,
+/// and return the triggers fired.  Stops early once no trigger is permitted.
+///
+/// This turns raw fuzzer input into trigger sequences the machine accepts,
+/// so a `cargo fuzz` target only has to build a machine and call this;
+/// panics in actions or the crate reach the fuzzer as usual.
+///
+/// ```ignore
+/// fuzz_target!(|data: &[u8]| {
+///     let mut machine = build_machine();
+///     stateless_rs::testing::fire_from_bytes(&mut machine, data);
+/// });
+/// ```
+pub fn fire_from_bytes<S, T, O, St, A, H>(
+    machine: &mut StateMachine<'_, S, T, O, St, A, H>,
+    data: &[u8],
+) -> Vec<T>
+where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+{
+    let mut fired = Vec::with_capacity(data.len());
+    for byte in data {
+        let permitted = machine.permitted_triggers();
+        if permitted.is_empty() {
+            break;
+        }
+        let trigger = permitted[usize::from(*byte) % permitted.len()];
+        // Middleware may still reject a permitted trigger, which is fine
+        if machine.fire(trigger).is_ok() {
+            fired.push(trigger);
+        }
+    }
+    fired
+}
+
+/// Fire `triggers` in order, calling `invariant` after every one, such as
+/// to replay the triggers of an [`InvariantViolation`]
+pub fn check_invariant<S, T, O, St, A, H, F>(
+    machine: &mut StateMachine<'_, S, T, O, St, A, H>,
+    triggers: impl IntoIterator<Item = T>,
+    mut invariant: F,
+) -> Result<(), InvariantViolation<S, T>>
+where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+    F: FnMut(S, &O) -> bool,
+{
+    let mut fired = Vec::new();
+    for trigger in triggers {
+        step(machine, trigger, &mut fired, &mut invariant)?;
+    }
+    Ok(())
+}
+
+fn step<S, T, O, St, A, H, F>(
+    machine: &mut StateMachine<'_, S, T, O, St, A, H>,
+    trigger: T,
+    fired: &mut Vec<T>,
+    invariant: &mut F,
+) -> Result<(), InvariantViolation<S, T>>
+where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+    F: FnMut(S, &O) -> bool,
+{
+    fired.push(trigger);
+    let rejection = machine.fire(trigger).err();
+    if rejection.is_none() && invariant(machine.state(), &machine.object()) {
+        return Ok(());
+    }
+    Err(InvariantViolation {
+        triggers: fired.clone(),
+        state: machine.state(),
+        rejection,
+    })
+}
+
+/// Fire each trigger and check the machine is then in the state paired
+/// with it.
+///
+/// ```
+/// # use stateless_rs::{assert_path, StateMachineBuilder};
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// # enum State { Off, On }
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// # enum Trigger { Switch }
+/// # let mut builder = StateMachineBuilder::new(State::Off);
+/// # builder.config(State::Off).permit(Trigger::Switch, State::On);
+/// # builder.config(State::On).permit(Trigger::Switch, State::Off);
+/// # let mut machine = builder.build(()).unwrap();
+/// assert_path!(machine, [Trigger::Switch => State::On, Trigger::Switch => State::Off]);
+/// ```
+#[macro_export]
+macro_rules! assert_path {
+    ($machine:expr, [$($trigger:expr => $state:expr),* $(,)?]) => {
+        $crate::testing::check_path(&mut $machine, &[$(($trigger, $state)),*])
+    };
+}
+
+/// Fire a trigger and check it is rejected, optionally with an error
+/// matching a pattern, and that the machine stays where it was
+///
+/// ```
+/// # use stateless_rs::{assert_rejected, StateMachineBuilder, StateMachineError};
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// # enum State { Off, On }
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// # enum Trigger { Switch, Break }
+/// # let mut builder = StateMachineBuilder::<_, _, ()>::new(State::Off);
+/// # builder.config(State::Off).permit(Trigger::Switch, State::On);
+/// # let mut machine = builder.build(()).unwrap();
+/// assert_rejected!(machine, Trigger::Break);
+/// assert_rejected!(machine, Trigger::Break, StateMachineError::TriggerNotPermitted { .. });
+/// ```
+#[macro_export]
+macro_rules! assert_rejected {
+    ($machine:expr, $trigger:expr) => {
+        $crate::testing::check_rejected(&mut $machine, $trigger, |_| true, "any error")
+    };
+    ($machine:expr, $trigger:expr, $pattern:pat) => {
+        $crate::testing::check_rejected(
+            &mut $machine,
+            $trigger,
+            |error| matches!(error, $pattern),
+            stringify!($pattern),
+        )
+    };
+}
+
+/// Fire each trigger of `path` and panic, describing the path taken so
+/// far, as soon as the machine isn't in the state paired with it.  Used by
+/// [`assert_path!`].
+#[doc(hidden)]
+#[track_caller]
+pub fn check_path<S, T, O, St, A, H>(
+    machine: &mut StateMachine<'_, S, T, O, St, A, H>,
+    path: &[(T, S)],
+) where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+{
+    let mut taken = alloc::format!("{:?}", machine.state());
+    for (step, (trigger, expected)) in path.iter().enumerate() {
+        let source = machine.state();
+        if let Err(error) = machine.fire(*trigger) {
+            panic!(
+                "path diverged at step {}: {:?} was rejected in {:?}: {}\n  expected: {:?}\n  path so far: {}",
+                step + 1,
+                trigger,
+                source,
+                error,
+                expected,
+                taken
+            );
+        }
+        let actual = machine.state();
+        taken = alloc::format!("{} --{:?}--> {:?}", taken, trigger, actual);
+        if actual != *expected {
+            panic!(
+                "path diverged at step {}: {:?} from {:?}\n  expected: {:?}\n  actual:   {:?}\n  path so far: {}",
+                step + 1,
+                trigger,
+                source,
+                expected,
+                actual,
+                taken
+            );
+        }
+    }
+}
+
+/// Fire `trigger` and panic unless it is rejected with an error `expected`
+/// accepts and the state is unchanged.  Used by [`assert_rejected!`].
+#[doc(hidden)]
+#[track_caller]
+pub fn check_rejected<S, T, O, St, A, H>(
+    machine: &mut StateMachine<'_, S, T, O, St, A, H>,
+    trigger: T,
+    expected: impl FnOnce(&StateMachineError<S, T>) -> bool,
+    description: &str,
+) where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+{
+    let source = machine.state();
+    match machine.fire(trigger) {
+        Ok(()) => panic!(
+            "expected {:?} to be rejected in {:?} but it moved to {:?}",
+            trigger,
+            source,
+            machine.state()
+        ),
+        Err(error) if !expected(&error) => panic!(
+            "{:?} was rejected in {:?} with the wrong error\n  expected: {}\n  actual:   {:?}",
+            trigger, source, description, error
+        ),
+        Err(_) if machine.state() != source => panic!(
+            "{:?} was rejected but the state changed from {:?} to {:?}",
+            trigger,
+            source,
+            machine.state()
+        ),
+        Err(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    fn build<'a>() -> StateMachine<'a, State, Trigger, u32> {
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, State::State2);
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State1)
+            .on_entry(|_, count: &mut u32| *count += 1);
+        builder.build(0).expect("the machine is valid")
+    }
+
+    #[test]
+    fn random_walk_is_reproducible_from_its_seed() {
+        let first = random_walk(&mut build(), 7, 20, |_, _| true);
+        let second = random_walk(&mut build(), 7, 20, |_, _| true);
+        assert_eq!(first, second);
+        assert_eq!(first.map(|triggers| triggers.len()), Ok(20));
+    }
+
+    #[test]
+    fn violations_can_be_replayed() {
+        let invariant = |_: State, count: &u32| *count < 3;
+        let violation = random_walk(&mut build(), 1, 100, invariant).unwrap_err();
+        assert_eq!(violation.triggers.len(), 5);
+        assert_eq!(violation.state, State::State2);
+
+        let replayed = check_invariant(&mut build(), violation.triggers.clone(), invariant);
+        assert_eq!(replayed, Err(violation));
+    }
+
+    #[test]
+    fn fire_from_bytes_only_fires_permitted_triggers() {
+        let mut machine = build();
+        let fired = fire_from_bytes(&mut machine, &[0, 255, 17]);
+        assert_eq!(fired, vec![Trigger::Trig; 3]);
+        assert_eq!(machine.state(), State::State2);
+        assert_eq!(*machine.object(), 2);
+    }
+
+    #[test]
+    fn assert_path_follows_states() {
+        let mut machine = build();
+        assert_path!(machine, [Trigger::Trig => State::State2, Trigger::Trig => State::State1]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "path diverged at step 2: Trig from State2\n  expected: State2\n  actual:   State1\n  path so far: State1 --Trig--> State2 --Trig--> State1"
+    )]
+    fn assert_path_reports_divergence() {
+        let mut machine = build();
+        assert_path!(machine, [Trigger::Trig => State::State2, Trigger::Trig => State::State2]);
+    }
+
+    #[test]
+    fn assert_rejected_matches_errors() {
+        let mut machine = build();
+        assert_rejected!(machine, Trigger::Trig2);
+        assert_rejected!(
+            machine,
+            Trigger::Trig2,
+            StateMachineError::TriggerNotPermitted { .. }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "expected Trig to be rejected in State1 but it moved to State2")]
+    fn assert_rejected_reports_accepted_triggers() {
+        let mut machine = build();
+        assert_rejected!(machine, Trigger::Trig);
+    }
+}
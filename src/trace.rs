@@ -0,0 +1,440 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::{Debug, Write};
+use core::hash::{BuildHasher, Hash};
+use core::str::FromStr;
+use core::time::Duration;
+
+use crate::action::StaticActions;
+use crate::storage::Storage;
+use crate::telemetry::TelemetrySink;
+use crate::trace_error::TraceError;
+use crate::transition::Transition;
+use crate::{StateMachine, StateMachineError};
+
+const HEADER: &str = "stateless-trace";
+const VERSION: &str = "1";
+
+/// Records every trigger a machine is fired with and what came of it, as a
+/// trace which [`TraceReplayer`] can replay against the same configuration.
+/// Attach it with [`crate::StateMachine::add_telemetry_sink`]; it is a cheap
+/// handle, so keep a clone to read the trace from.
+///
+/// A trace is text: a `stateless-trace 1` header, then one tab-separated
+/// record per line with the kind (`T` for a transition, `I` for an internal
+/// transition, `R` for a rejection), the sequence number and timestamp in
+/// microseconds by the machine's [`crate::Clock`], and the `Debug` names of
+/// the source, trigger and destination, or the error for a rejection.
+/// Rejections have `-` for the sequence number and timestamp, and
+/// transitions the machine made without a trigger, such as
+/// [`crate::StateMachine::reenter`], have `-` for the trigger.  With the
+/// tabs shown as spaces:
+///
+/// ```text
+/// stateless-trace 1
+/// T   0   0   Closed  Open    Opened
+/// R   -   -   Opened  Lock    trigger Lock not permitted for Opened
+/// ```
+///
+/// Payloads are not recorded.  Like other telemetry, triggers fired with
+/// [`crate::StateMachine::process_all`] are not seen.
+#[derive(Debug, Clone)]
+pub struct TraceRecorder {
+    trace: Rc<RefCell<String>>,
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self {
+            trace: Rc::new(RefCell::new(format!("{HEADER} {VERSION}\n"))),
+        }
+    }
+
+    /// Everything recorded so far
+    pub fn trace(&self) -> String {
+        self.trace.borrow().clone()
+    }
+
+    fn record(&self, fields: [&str; 6]) {
+        let mut trace = self.trace.borrow_mut();
+        let fields: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        let _ = writeln!(trace, "{}", fields.join("\t"));
+    }
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Debug, T: Debug> TelemetrySink<S, T> for TraceRecorder {
+    fn transition_committed(&mut self, transition: &Transition<S, T>, _duration: Duration) {
+        let kind = if transition.is_internal() { "I" } else { "T" };
+        let or_dash = |n: Option<u128>| n.map_or_else(|| String::from("-"), |n| n.to_string());
+        self.record([
+            kind,
+            &or_dash(transition.sequence().map(u128::from)),
+            &or_dash(transition.timestamp().map(|t| t.as_micros())),
+            &format!("{:?}", transition.source),
+            &transition
+                .trigger
+                .as_ref()
+                .map_or_else(|| String::from("-"), |t| format!("{t:?}")),
+            &format!("{:?}", transition.destination),
+        ]);
+    }
+
+    fn fire_rejected(
+        &mut self,
+        state: S,
+        trigger: T,
+        error: &StateMachineError<S, T>,
+        _duration: Duration,
+    ) {
+        self.record([
+            "R",
+            "-",
+            "-",
+            &format!("{state:?}"),
+            &format!("{trigger:?}"),
+            &error.to_string(),
+        ]);
+    }
+}
+
+/// What came of a recorded trigger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceOutcome {
+    Transition { destination: String },
+    Internal,
+    Rejected { error: String },
+}
+
+/// One record of a trace, with states and triggers by `Debug` name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub sequence: Option<u64>,
+    pub timestamp: Option<Duration>,
+    pub source: String,
+    /// `None` for a transition the machine made without a trigger
+    pub trigger: Option<String>,
+    pub outcome: TraceOutcome,
+}
+
+/// A trace written by [`TraceRecorder`], for replaying an incident against
+/// a machine built the same way.  [`Self::step`] fires one recorded trigger
+/// at a time, for stepping through with a diagram of the machine alongside;
+/// [`Self::replay`] fires the rest.  Triggers are parsed from their names,
+/// so the trigger type needs a [`FromStr`] such as `strum::EnumString`'s.
+///
+/// ```
+/// # use stateless_rs::{StateMachineBuilder, TraceRecorder, TraceReplayer};
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// # enum State { Off, On }
+/// #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumString)]
+/// enum Trigger { Switch }
+///
+/// let build = || {
+///     let mut builder = StateMachineBuilder::<_, _, ()>::new(State::Off);
+///     builder.config(State::Off).permit(Trigger::Switch, State::On);
+///     builder.build(()).unwrap()
+/// };
+/// let recorder = TraceRecorder::new();
+/// let mut machine = build();
+/// machine.add_telemetry_sink(recorder.clone());
+/// machine.fire(Trigger::Switch).unwrap();
+/// assert!(machine.fire(Trigger::Switch).is_err());
+///
+/// let mut replayer = TraceReplayer::parse(&recorder.trace()).unwrap();
+/// let mut replica = build();
+/// replayer.replay(&mut replica).unwrap();
+/// assert_eq!(replica.state(), State::On);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceReplayer {
+    steps: Vec<TraceStep>,
+    /// The next step to replay
+    position: usize,
+}
+
+impl TraceReplayer {
+    pub fn parse(trace: &str) -> Result<Self, TraceError> {
+        let mut lines = trace.lines();
+        let version = lines
+            .next()
+            .and_then(|header| header.strip_prefix(HEADER))
+            .and_then(|rest| rest.strip_prefix(' '))
+            .ok_or(TraceError::InvalidHeader)?;
+        if version != VERSION {
+            return Err(TraceError::UnsupportedVersion {
+                version: String::from(version),
+            });
+        }
+        let steps = lines
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(index, line)| {
+                parse_step(line).ok_or(TraceError::InvalidLine { line: index + 2 })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { steps, position: 0 })
+    }
+
+    pub fn steps(&self) -> &[TraceStep] {
+        &self.steps
+    }
+
+    /// The index of the next step [`Self::step`] replays
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Replay the next step, checking that the machine starts in the
+    /// recorded source and does what was recorded.  `None` once every step
+    /// has been replayed.
+    pub fn step<'a, S, T, O, St, A, H>(
+        &mut self,
+        machine: &mut StateMachine<'a, S, T, O, St, A, H>,
+    ) -> Option<Result<&TraceStep, TraceError>>
+    where
+        S: Copy + Eq + Hash + Debug,
+        T: Copy + Eq + Hash + Debug + FromStr,
+        O: Debug,
+        St: Storage<O>,
+        A: StaticActions<S, T, O>,
+        H: BuildHasher + Default,
+    {
+        let index = self.position;
+        let step = self.steps.get(index)?;
+        self.position += 1;
+        Some(replay_step(index, step, machine).map(|()| step))
+    }
+
+    /// Replay every remaining step, stopping at the first divergence
+    pub fn replay<'a, S, T, O, St, A, H>(
+        &mut self,
+        machine: &mut StateMachine<'a, S, T, O, St, A, H>,
+    ) -> Result<(), TraceError>
+    where
+        S: Copy + Eq + Hash + Debug,
+        T: Copy + Eq + Hash + Debug + FromStr,
+        O: Debug,
+        St: Storage<O>,
+        A: StaticActions<S, T, O>,
+        H: BuildHasher + Default,
+    {
+        while let Some(result) = self.step(machine) {
+            result?;
+        }
+        Ok(())
+    }
+}
+
+fn replay_step<S, T, O, St, A, H>(
+    index: usize,
+    step: &TraceStep,
+    machine: &mut StateMachine<'_, S, T, O, St, A, H>,
+) -> Result<(), TraceError>
+where
+    S: Copy + Eq + Hash + Debug,
+    T: Copy + Eq + Hash + Debug + FromStr,
+    O: Debug,
+    St: Storage<O>,
+    A: StaticActions<S, T, O>,
+    H: BuildHasher + Default,
+{
+    let diverged = |expected: String, actual: String| TraceError::Diverged {
+        step: index,
+        expected,
+        actual,
+    };
+    let state = format!("{:?}", machine.state());
+    if state != step.source {
+        return Err(diverged(format!("to be in {}", step.source), state));
+    }
+    let name = step
+        .trigger
+        .as_ref()
+        .ok_or(TraceError::Untriggered { step: index })?;
+    let trigger: T = name
+        .parse()
+        .map_err(|_| TraceError::UnknownTrigger { name: name.clone() })?;
+    let actual = match machine.fire(trigger) {
+        Ok(()) => match machine.last_transition() {
+            Some(transition) if transition.is_internal() => TraceOutcome::Internal,
+            Some(transition) => TraceOutcome::Transition {
+                destination: format!("{:?}", transition.destination),
+            },
+            None => TraceOutcome::Internal,
+        },
+        Err(error) => TraceOutcome::Rejected {
+            error: error.to_string(),
+        },
+    };
+    if actual != step.outcome {
+        return Err(diverged(describe(&step.outcome), describe(&actual)));
+    }
+    Ok(())
+}
+
+fn describe(outcome: &TraceOutcome) -> String {
+    match outcome {
+        TraceOutcome::Transition { destination } => format!("a transition to {destination}"),
+        TraceOutcome::Internal => String::from("an internal transition"),
+        TraceOutcome::Rejected { error } => format!("a rejection ({error})"),
+    }
+}
+
+fn parse_step(line: &str) -> Option<TraceStep> {
+    let fields: Vec<String> = line.split('\t').map(unescape).collect();
+    let [kind, sequence, timestamp, source, trigger, last] =
+        <[String; 6]>::try_from(fields).ok()?;
+    let sequence = match sequence.as_str() {
+        "-" => None,
+        n => Some(n.parse().ok()?),
+    };
+    let timestamp = match timestamp.as_str() {
+        "-" => None,
+        micros => Some(Duration::from_micros(micros.parse().ok()?)),
+    };
+    let outcome = match kind.as_str() {
+        "T" => TraceOutcome::Transition { destination: last },
+        "I" => TraceOutcome::Internal,
+        "R" => TraceOutcome::Rejected { error: last },
+        _ => return None,
+    };
+    let trigger = (trigger != "-").then_some(trigger);
+    Some(TraceStep {
+        sequence,
+        timestamp,
+        source,
+        trigger,
+        outcome,
+    })
+}
+
+/// Keep each record on one line with its fields apart
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    fn build<'a>(
+        destination: State,
+    ) -> eyre::Result<StateMachine<'a, State, Trigger, (), crate::DefaultStorage<()>>> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder
+            .config(State::State1)
+            .permit(Trigger::Trig, destination);
+        builder
+            .config(destination)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        Ok(builder.build(())?)
+    }
+
+    #[test]
+    fn records_transitions_internal_transitions_and_rejections() -> eyre::Result<()> {
+        let recorder = TraceRecorder::new();
+        let mut machine = build(State::State2)?;
+        let clock = crate::ManualClock::new();
+        machine.set_clock(clock.clone());
+        machine.add_telemetry_sink(recorder.clone());
+
+        clock.advance(Duration::from_millis(2));
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
+        assert!(machine.fire(Trigger::Trig).is_err());
+
+        assert_eq!(
+            recorder.trace(),
+            "stateless-trace 1\n\
+             T\t0\t2000\tState1\tTrig\tState2\n\
+             I\t1\t2000\tState2\tTrig2\tState2\n\
+             R\t-\t-\tState2\tTrig\ttrigger Trig not permitted for State2\n"
+        );
+        let replayer = TraceReplayer::parse(&recorder.trace())?;
+        assert_eq!(
+            replayer.steps()[0],
+            TraceStep {
+                sequence: Some(0),
+                timestamp: Some(Duration::from_millis(2)),
+                source: String::from("State1"),
+                trigger: Some(String::from("Trig")),
+                outcome: TraceOutcome::Transition {
+                    destination: String::from("State2")
+                },
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replay_reports_where_the_machine_diverges() -> eyre::Result<()> {
+        let recorder = TraceRecorder::new();
+        let mut machine = build(State::State2)?;
+        machine.add_telemetry_sink(recorder.clone());
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
+
+        let mut replayer = TraceReplayer::parse(&recorder.trace())?;
+        replayer.replay(&mut build(State::State2)?)?;
+        assert_eq!(replayer.position(), 2);
+
+        let mut replayer = TraceReplayer::parse(&recorder.trace())?;
+        assert_eq!(
+            replayer.replay(&mut build(State::State1)?),
+            Err(TraceError::Diverged {
+                step: 0,
+                expected: String::from("a transition to State2"),
+                actual: String::from("a transition to State1"),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_rejects_other_versions_and_bad_records() {
+        assert_eq!(TraceReplayer::parse("T\t0"), Err(TraceError::InvalidHeader));
+        assert_eq!(
+            TraceReplayer::parse("stateless-trace 2\n"),
+            Err(TraceError::UnsupportedVersion {
+                version: String::from("2")
+            })
+        );
+        assert_eq!(
+            TraceReplayer::parse("stateless-trace 1\nX\t-\t-\ta\tb\tc\n"),
+            Err(TraceError::InvalidLine { line: 2 })
+        );
+        assert_eq!(unescape(&escape("a\tb\\n\nc")), "a\tb\\n\nc");
+    }
+}
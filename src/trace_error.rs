@@ -0,0 +1,46 @@
+use alloc::string::String;
+use core::fmt::Display;
+
+/// Why a trace couldn't be read or replayed, see [`crate::TraceReplayer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceError {
+    /// The first line isn't a trace header
+    InvalidHeader,
+    /// Written by a newer version of the format
+    UnsupportedVersion { version: String },
+    /// Not a record, at this line number counting from 1
+    InvalidLine { line: usize },
+    /// The trigger name doesn't parse to a trigger
+    UnknownTrigger { name: String },
+    /// Step `step`, counting from 0, is a transition the machine made
+    /// without a trigger, which can't be replayed by firing one
+    Untriggered { step: usize },
+    /// Replaying step `step`, counting from 0, the machine did something
+    /// other than what was recorded
+    Diverged {
+        step: usize,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "not a trace"),
+            Self::UnsupportedVersion { version } => {
+                write!(f, "unsupported trace version {version:?}")
+            }
+            Self::InvalidLine { line } => write!(f, "invalid trace record on line {line}"),
+            Self::UnknownTrigger { name } => write!(f, "unknown trigger {name:?}"),
+            Self::Untriggered { step } => write!(f, "step {step} was not caused by a trigger"),
+            Self::Diverged {
+                step,
+                expected,
+                actual,
+            } => write!(f, "step {step} diverged: expected {expected}, got {actual}"),
+        }
+    }
+}
+
+impl core::error::Error for TraceError {}
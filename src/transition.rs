@@ -1,8 +1,58 @@
-#[derive(Debug)]
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::any::Any;
+#[cfg(feature = "alloc")]
+use core::cell::{Ref, RefCell};
+use core::time::Duration;
+use derivative::Derivative;
+
+/// The untyped payload a trigger was fired with, see
+/// [`crate::StateMachine::fire_with_any`]
+#[cfg(feature = "alloc")]
+pub(crate) type Payload = Arc<dyn Any + Send + Sync>;
+
+/// A value an action handed back with [`Transition::output`].  `Send` so
+/// transitions still are.
+#[cfg(feature = "alloc")]
+pub(crate) type Output = Box<dyn Any + Send>;
+
+/// A move from `source` to `destination` by `trigger`.  `trigger` is `None`
+/// for the moves the machine makes on its own: starting, stopping,
+/// re-entering the current state and compensating, see
+/// [`crate::StateMachine::start`].
+///
+/// Transitions compare equal when they are between the same states by the
+/// same trigger with the same annotations and payload, where payloads are
+/// only the same if they came from the same fire.  Sequence and timestamp
+/// are ignored.
+///
+/// A transition is not `Copy`, even for `Copy` states and triggers, as it
+/// carries the annotations and outputs actions add while it is being made,
+/// see [`Self::annotate`] and [`Self::output`].
+#[derive(Debug, Derivative)]
+#[derivative(Clone, PartialEq, Eq)]
 pub struct Transition<S, T> {
     pub source: S,
     pub destination: S,
-    pub trigger: T,
+    pub trigger: Option<T>,
+    internal: bool,
+    #[derivative(PartialEq = "ignore")]
+    sequence: Option<u64>,
+    #[derivative(PartialEq = "ignore")]
+    timestamp: Option<Duration>,
+    #[cfg(feature = "alloc")]
+    #[derivative(PartialEq(compare_with = "same_payload"))]
+    payload: Option<Payload>,
+    #[cfg(feature = "alloc")]
+    annotations: RefCell<Vec<String>>,
+    /// See [`crate::StateMachine::fire_with_output`].  Not cloned, so they
+    /// are only handed back once.
+    #[cfg(feature = "alloc")]
+    #[derivative(PartialEq = "ignore", Clone(clone_with = "no_outputs"))]
+    outputs: RefCell<Vec<Output>>,
+    #[cfg(feature = "alloc")]
+    label: Option<Arc<str>>,
 }
 
 impl<S, T> Transition<S, T>
@@ -10,10 +60,31 @@ where
     S: PartialEq,
 {
     pub fn new(source: S, trigger: T, destination: S) -> Self {
+        Self::with_trigger(source, Some(trigger), destination)
+    }
+
+    /// A move the machine makes without a trigger
+    #[cfg(feature = "alloc")]
+    pub(crate) fn without_trigger(source: S, destination: S) -> Self {
+        Self::with_trigger(source, None, destination)
+    }
+
+    fn with_trigger(source: S, trigger: Option<T>, destination: S) -> Self {
         Self {
             source,
             destination,
             trigger,
+            internal: false,
+            sequence: None,
+            timestamp: None,
+            #[cfg(feature = "alloc")]
+            payload: None,
+            #[cfg(feature = "alloc")]
+            annotations: RefCell::default(),
+            #[cfg(feature = "alloc")]
+            outputs: RefCell::default(),
+            #[cfg(feature = "alloc")]
+            label: None,
         }
     }
 
@@ -21,3 +92,133 @@ where
         self.source == self.destination
     }
 }
+
+impl<S, T> Transition<S, T> {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn into_internal(mut self) -> Self {
+        self.internal = true;
+        self
+    }
+
+    /// Whether this was an internal transition, which stays in its state
+    /// without running exit or entry actions
+    pub fn is_internal(&self) -> bool {
+        self.internal
+    }
+
+    #[cfg(any(feature = "alloc", test))]
+    pub(crate) fn stamped(mut self, sequence: u64, timestamp: Duration) -> Self {
+        self.sequence = Some(sequence);
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Counts up from zero with every transition a machine makes, so
+    /// handlers can order what they observe.  `None` for transitions the
+    /// machine did not make.
+    pub fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    /// When the machine started making the transition, by its
+    /// [`crate::Clock`]
+    pub fn timestamp(&self) -> Option<Duration> {
+        self.timestamp
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S, T> Transition<S, T> {
+    pub(crate) fn with_payload(mut self, payload: Option<Payload>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    pub(crate) fn with_label(mut self, label: Option<Arc<str>>) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// The label of the configured transition this was made by, see
+    /// [`crate::StateConfig::label`]
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The payload the trigger was fired with, or `None` if there was none
+    /// or it is not a `P`
+    pub fn payload_downcast<P: Any>(&self) -> Option<&P> {
+        self.payload.as_deref()?.downcast_ref()
+    }
+
+    /// Attach a short note to the transition, such as why it was allowed.
+    /// Actions can annotate the transition they run for, and the notes are
+    /// seen by later actions, event handlers and telemetry sinks.  Guards
+    /// made with [`crate::Guard::annotated`] add theirs before any action
+    /// runs.
+    pub fn annotate(&self, annotation: impl Into<String>) {
+        self.annotations.borrow_mut().push(annotation.into());
+    }
+
+    /// The annotations added so far, in the order they were added
+    pub fn annotations(&self) -> Ref<'_, [String]> {
+        Ref::map(self.annotations.borrow(), Vec::as_slice)
+    }
+
+    /// Hand `value` back to whoever fired the trigger, for actions which
+    /// produce a result such as a response to send.  Values of the type
+    /// asked for are returned by [`crate::StateMachine::fire_with_output`];
+    /// anything else is dropped.
+    pub fn output(&self, value: impl Any + Send) {
+        self.outputs.borrow_mut().push(Box::new(value));
+    }
+
+    pub(crate) fn take_outputs(&self) -> Vec<Output> {
+        self.outputs.take()
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn no_outputs(_: &RefCell<Vec<Output>>) -> RefCell<Vec<Output>> {
+    RefCell::default()
+}
+
+#[cfg(feature = "alloc")]
+fn same_payload(a: &Option<Payload>, b: &Option<Payload>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+
+    #[test]
+    fn equality_ignores_when_transitions_were_made() {
+        let transition = Transition::new(State::State1, Trigger::Trig, State::State2);
+        let stamped = transition.clone().stamped(3, Duration::from_secs(1));
+        assert_eq!(stamped, transition);
+        assert_eq!(stamped.sequence(), Some(3));
+        assert_ne!(
+            transition,
+            Transition::new(State::State1, Trigger::Trig2, State::State2)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn payloads_are_the_same_only_from_the_same_fire() {
+        let payload: Payload = alloc::sync::Arc::new(1_u32);
+        let transition = Transition::new(State::State1, Trigger::Trig, State::State2)
+            .with_payload(Some(payload));
+        let refired = Transition::new(State::State1, Trigger::Trig, State::State2)
+            .with_payload(Some(alloc::sync::Arc::new(1_u32)));
+        assert_eq!(transition.clone(), transition);
+        assert_ne!(refired, transition);
+        transition.annotate("note");
+        assert_eq!(*transition.clone().annotations(), ["note"]);
+    }
+}
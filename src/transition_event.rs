@@ -1,42 +1,145 @@
-use std::fmt::Debug;
+use alloc::boxed::Box;
+use alloc::rc::{Rc, Weak};
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt::Debug;
 
-use crate::{transition, Transition};
+use crate::Transition;
 
-type EventAction<S, T> = Box<dyn FnMut(&Transition<S, T>)>;
+type EventAction<'a, S, T> = Box<dyn FnMut(&Transition<S, T>) + 'a>;
+/// Whether the referent of a weak handler has been dropped
+type Expired<'a> = Box<dyn Fn() -> bool + 'a>;
 
-pub struct TransitionEventHandler<S, T> {
-    pub(crate) events: Vec<EventAction<S, T>>,
+/// Identifies a handler added to a [`TransitionEventHandler`] so it can be
+/// removed again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Removes its handler when dropped.  Returned by
+/// [`TransitionEventHandler::subscribe`].
+#[derive(Debug)]
+#[must_use = "the handler is removed as soon as the Subscription is dropped"]
+pub struct Subscription {
+    id: SubscriptionId,
+    active: Weak<Cell<bool>>,
+}
+
+impl Subscription {
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    /// Remove the handler now rather than waiting for the drop
+    pub fn remove(self) {}
 }
 
-impl<S, T> TransitionEventHandler<S, T> {
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(active) = self.active.upgrade() {
+            active.set(false);
+        }
+    }
+}
+
+pub(crate) struct Event<'a, S, T> {
+    id: SubscriptionId,
+    active: Rc<Cell<bool>>,
+    expired: Option<Expired<'a>>,
+    action: EventAction<'a, S, T>,
+}
+
+impl<S, T> Event<'_, S, T> {
+    fn is_live(&self) -> bool {
+        self.active.get() && !self.expired.as_ref().is_some_and(|expired| expired())
+    }
+}
+
+pub struct TransitionEventHandler<'a, S, T> {
+    pub(crate) events: Vec<Event<'a, S, T>>,
+    next_id: u64,
+}
+
+impl<'a, S, T> TransitionEventHandler<'a, S, T> {
     pub fn new() -> Self {
-        Self { events: Vec::new() }
+        Self {
+            events: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Add a handler which stays registered until [`Self::remove`] is called
+    /// with the returned id
+    pub fn add_event<F>(&mut self, f: F) -> SubscriptionId
+    where
+        F: FnMut(&Transition<S, T>) + 'a,
+    {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.events.push(Event {
+            id,
+            active: Rc::new(Cell::new(true)),
+            expired: None,
+            action: Box::new(f),
+        });
+        id
     }
 
-    pub fn add_event<F>(&mut self, f: F)
+    /// Add a handler which is called with the referent of `weak` and removed
+    /// once it has been dropped, so subscribing doesn't keep it alive
+    pub fn add_event_weak<R, F>(&mut self, weak: Weak<R>, mut f: F) -> SubscriptionId
     where
-        F: FnMut(&Transition<S, T>) + 'static,
+        R: 'a,
+        F: FnMut(&R, &Transition<S, T>) + 'a,
     {
-        self.events.push(Box::new(f));
+        let referent = weak.clone();
+        let id = self.add_event(move |transition| {
+            if let Some(referent) = referent.upgrade() {
+                f(&referent, transition);
+            }
+        });
+        let event = self.events.last_mut().expect("event was just added");
+        event.expired = Some(Box::new(move || weak.strong_count() == 0));
+        id
+    }
+
+    /// Add a handler which stays registered for as long as the returned
+    /// [`Subscription`] is alive
+    pub fn subscribe<F>(&mut self, f: F) -> Subscription
+    where
+        F: FnMut(&Transition<S, T>) + 'a,
+    {
+        let id = self.add_event(f);
+        let event = self.events.last().expect("event was just added");
+        Subscription {
+            id,
+            active: Rc::downgrade(&event.active),
+        }
+    }
+
+    /// Remove a handler.  Returns `false` if it had already been removed.
+    pub fn remove(&mut self, id: SubscriptionId) -> bool {
+        let len = self.events.len();
+        self.events.retain(|e| e.id != id);
+        self.events.len() != len
     }
 
     pub fn fire_events(&mut self, transition: &Transition<S, T>) {
+        self.events.retain(Event::is_live);
         for event in self.events.iter_mut() {
-            event(transition);
+            (event.action)(transition);
         }
     }
 }
 
-impl<S, T> Default for TransitionEventHandler<S, T> {
+impl<S, T> Default for TransitionEventHandler<'_, S, T> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<S, T> Debug for TransitionEventHandler<S, T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("TransitionEventHandler")
-            .finish()
+impl<S, T> Debug for TransitionEventHandler<'_, S, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TransitionEventHandler").finish()
     }
 }
 
@@ -77,4 +180,54 @@ mod tests {
         handler.fire_events(&transition);
         assert_eq!(*count.lock().unwrap(), 2);
     }
+
+    #[test]
+    fn removed_event_does_not_fire() {
+        let mut handler = TransitionEventHandler::<State, Trigger>::new();
+        let count = Arc::new(Mutex::new(0));
+        let count1 = Arc::clone(&count);
+        let id = handler.add_event(move |_t| *count1.lock().unwrap() += 1);
+        assert!(handler.remove(id));
+        assert!(!handler.remove(id));
+        handler.fire_events(&Transition::new(
+            State::State1,
+            Trigger::Trig,
+            State::State2,
+        ));
+        assert_eq!(*count.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn dropped_subscription_does_not_fire() {
+        let mut handler = TransitionEventHandler::<State, Trigger>::new();
+        let count = Arc::new(Mutex::new(0));
+        let count1 = Arc::clone(&count);
+        let transition = Transition::new(State::State1, Trigger::Trig, State::State2);
+
+        let subscription = handler.subscribe(move |_t| *count1.lock().unwrap() += 1);
+        handler.fire_events(&transition);
+        drop(subscription);
+        handler.fire_events(&transition);
+
+        assert_eq!(*count.lock().unwrap(), 1);
+        assert!(handler.events.is_empty());
+    }
+
+    #[test]
+    fn weak_event_is_removed_once_its_referent_drops() {
+        let mut handler = TransitionEventHandler::<State, Trigger>::new();
+        let widget = Rc::new(Cell::new(0));
+        let transition = Transition::new(State::State1, Trigger::Trig, State::State2);
+
+        handler.add_event_weak(Rc::downgrade(&widget), |widget, _t| {
+            widget.set(widget.get() + 1)
+        });
+        handler.fire_events(&transition);
+        assert_eq!(widget.get(), 1);
+        assert_eq!(Rc::strong_count(&widget), 1);
+
+        drop(widget);
+        handler.fire_events(&transition);
+        assert!(handler.events.is_empty());
+    }
 }
@@ -0,0 +1,136 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use derivative::Derivative;
+
+use crate::guard::Guard;
+use crate::metadata::Metadata;
+use crate::trigger_behaviour::TriggerBehaviour;
+
+/// A named bundle of trigger behaviours which can be applied to any number of
+/// states with [`crate::StateConfig::apply_table`].
+///
+/// ## Example
+/// ```
+/// # use stateless_rs::{StateMachineBuilder, TransitionTable};
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, strum_macros::EnumIter)]
+/// # enum State { Idle, Busy, Failed }
+/// # #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+/// # enum Trigger { Start, Crash }
+/// let common_errors = TransitionTable::new("common errors").permit(Trigger::Crash, State::Failed);
+///
+/// let mut builder = StateMachineBuilder::<State, Trigger, ()>::new(State::Idle);
+/// builder
+///     .config(State::Idle)
+///     .permit(Trigger::Start, State::Busy)
+///     .apply_table(&common_errors);
+/// builder.config(State::Busy).apply_table(&common_errors);
+/// ```
+#[derive(Derivative)]
+#[derivative(
+    Debug(bound = "S: Debug, T: Debug"),
+    Clone(bound = "S: Clone, T: Clone")
+)]
+pub struct TransitionTable<'a, S, T, O> {
+    name: String,
+    pub(crate) behaviours: Vec<(T, TriggerBehaviour<'a, S, T, O>)>,
+}
+
+impl<'a, S, T, O> TransitionTable<'a, S, T, O>
+where
+    S: Debug + Copy,
+    T: Debug + Copy,
+{
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            behaviours: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn permit(mut self, trigger: T, destination_state: S) -> Self {
+        let behaviour = TriggerBehaviour::transitioning(destination_state, None);
+        self.behaviours.push((trigger, behaviour));
+        self
+    }
+
+    pub fn permit_if<G>(mut self, trigger: T, destination_state: S, guard: G) -> Self
+    where
+        G: Into<Guard<'a, O>>,
+    {
+        let behaviour = TriggerBehaviour::transitioning(destination_state, Some(guard.into()));
+        self.behaviours.push((trigger, behaviour));
+        self
+    }
+
+    /// Describe the transition added by the previous `permit*` call
+    ///
+    /// ## Panics
+    /// If no transition has been added to the table yet
+    pub fn describe_transition(mut self, description: impl Into<String>) -> Self {
+        self.last_metadata().description = Some(description.into());
+        self
+    }
+
+    /// Tag the transition added by the previous `permit*` call
+    ///
+    /// ## Panics
+    /// If no transition has been added to the table yet
+    pub fn tag_transition(mut self, tag: impl Into<String>) -> Self {
+        self.last_metadata().add_tag(tag);
+        self
+    }
+
+    fn last_metadata(&mut self) -> &mut Metadata {
+        let (_, behaviour) = self
+            .behaviours
+            .last_mut()
+            .expect("a transition must be added before it can be described or tagged");
+        &mut behaviour.metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    #[test]
+    fn table_applies_to_multiple_states() -> eyre::Result<()> {
+        let table = TransitionTable::new("to state2").permit(Trigger::Trig, State::State2);
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder.config(State::State1).apply_table(&table);
+        builder.config(State::State2).apply_table(&table);
+
+        let mut machine = builder.build(())?;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        Ok(())
+    }
+
+    #[test]
+    fn table_guards_are_applied() -> eyre::Result<()> {
+        let table = TransitionTable::new("guarded").permit_if(
+            Trigger::Trig,
+            State::State2,
+            Guard::new("is positive", |o: &i32| *o > 0),
+        );
+        let mut builder = StateMachineBuilder::new(State::State1);
+        builder.config(State::State1).apply_table(&table);
+
+        let mut machine = builder.build(0)?;
+        assert!(machine.fire(Trigger::Trig).is_err());
+        *machine.object_mut() = 1;
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State2);
+        Ok(())
+    }
+}
@@ -1,15 +1,145 @@
-use std::{fmt::Debug, marker::PhantomData};
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::{fmt::Debug, marker::PhantomData};
 
-#[derive(Debug, Clone)]
-pub(crate) enum TriggerBehaviour<S, T> {
+use derivative::Derivative;
+
+use crate::guard::Guard;
+use crate::metadata::Metadata;
+use crate::transition::Payload;
+
+/// Chooses a dynamic destination, or `None` if the payload is not the type
+/// it expects
+type Selector<'a, S, O> = Rc<dyn Fn(&O, Option<&Payload>) -> Option<S> + 'a>;
+
+#[derive(Derivative)]
+#[derivative(
+    Debug(bound = "S: Debug, T: Debug"),
+    Clone(bound = "S: Clone, T: Clone")
+)]
+pub(crate) struct TriggerBehaviour<'a, S, T, O> {
+    pub(crate) kind: BehaviourKind<'a, S, T, O>,
+    pub(crate) guard: Option<Guard<'a, O>>,
+    pub(crate) metadata: Metadata,
+    /// Added with `otherwise`: taken when no other behaviour for the
+    /// trigger is met
+    pub(crate) fallback: bool,
+    /// Names the edge in exports, errors and the transitions it makes
+    pub(crate) label: Option<Arc<str>>,
+}
+
+/// What firing a behaviour will do, without borrowing the behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Resolution<S> {
+    Transition {
+        destination: S,
+        destination_index: u16,
+    },
+    Internal,
+    /// A dynamic transition's selector was not given the payload it expects
+    InvalidParameters {
+        expected: &'static str,
+    },
+    /// A dynamic transition chose a destination it did not declare
+    UndeclaredDestination {
+        destination: S,
+    },
+}
+
+#[derive(Derivative)]
+#[derivative(
+    Debug(bound = "S: Debug, T: Debug"),
+    Clone(bound = "S: Clone, T: Clone")
+)]
+pub(crate) enum BehaviourKind<'a, S, T, O> {
     Transitioning(Transitioning<S, T>),
     Internal(Internal<S, T>),
+    Dynamic(Dynamic<'a, S, O>),
+}
+
+impl<'a, S, T, O> TriggerBehaviour<'a, S, T, O>
+where
+    S: Copy + Debug,
+    T: Debug,
+{
+    pub(crate) fn transitioning(destination: S, guard: Option<Guard<'a, O>>) -> Self {
+        Self {
+            kind: BehaviourKind::Transitioning(Transitioning::new(destination)),
+            guard,
+            metadata: Metadata::default(),
+            fallback: false,
+            label: None,
+        }
+    }
+
+    pub(crate) fn internal(guard: Option<Guard<'a, O>>) -> Self {
+        Self {
+            kind: BehaviourKind::Internal(Internal::new()),
+            guard,
+            metadata: Metadata::default(),
+            fallback: false,
+            label: None,
+        }
+    }
+
+    pub(crate) fn dynamic(dynamic: Dynamic<'a, S, O>, guard: Option<Guard<'a, O>>) -> Self {
+        Self {
+            kind: BehaviourKind::Dynamic(dynamic),
+            guard,
+            metadata: Metadata::default(),
+            fallback: false,
+            label: None,
+        }
+    }
+}
+
+impl<'a, S, T, O> TriggerBehaviour<'a, S, T, O>
+where
+    S: Copy + PartialEq + Debug,
+    T: Debug,
+{
+    /// Where firing this behaviour from `source` goes.  Only dynamic
+    /// transitions look at `object` and `payload`.
+    pub(crate) fn resolve(
+        &self,
+        source: S,
+        object: &O,
+        payload: Option<&Payload>,
+    ) -> Resolution<S> {
+        match &self.kind {
+            BehaviourKind::Transitioning(b) => Resolution::Transition {
+                destination: b.fire(source),
+                destination_index: b.destination_index(),
+            },
+            BehaviourKind::Internal(b) => {
+                b.fire(source); // TODO: does nothing now. Maybe needed for parameters
+                Resolution::Internal
+            }
+            BehaviourKind::Dynamic(b) => b.resolve(object, payload),
+        }
+    }
+}
+
+impl<'a, S, T, O> TriggerBehaviour<'a, S, T, O> {
+    pub(crate) fn guard(&self) -> Option<&Guard<'a, O>> {
+        self.guard.as_ref()
+    }
+
+    /// Approximate bytes allocated by the guard and metadata
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.guard().map_or(0, Guard::heap_bytes)
+            + self.metadata.heap_bytes()
+            + self.label.as_deref().map_or(0, str::len)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Transitioning<S, T> {
-    trigger: T,
     destination: S,
+    /// Interned id of `destination`, set at build
+    destination_index: Option<u16>,
+    phantom: PhantomData<T>,
 }
 
 impl<S, T> Transitioning<S, T>
@@ -17,22 +147,94 @@ where
     S: Copy + Debug,
     T: Debug,
 {
-    pub fn new(trigger: T, destination: S) -> Self {
+    pub fn new(destination: S) -> Self {
         Self {
-            trigger,
             destination,
+            destination_index: None,
+            phantom: PhantomData,
         }
     }
 
     pub fn fire(&self, _source: S) -> S {
         self.destination
     }
+
+    pub fn destination(&self) -> S {
+        self.destination
+    }
+
+    pub(crate) fn resolve(&mut self, destination_index: u16) {
+        self.destination_index = Some(destination_index);
+    }
+
+    pub(crate) fn destination_index(&self) -> u16 {
+        self.destination_index
+            .expect("destinations are resolved when the machine is built")
+    }
+}
+
+/// A transition whose destination is chosen from the trigger's payload when
+/// it fires, out of destinations declared up front
+#[derive(Derivative)]
+#[derivative(Debug(bound = "S: Debug"), Clone(bound = "S: Clone"))]
+pub(crate) struct Dynamic<'a, S, O> {
+    /// Each possible destination and its interned id, set at build
+    destinations: Vec<(S, Option<u16>)>,
+    #[derivative(Debug = "ignore")]
+    selector: Selector<'a, S, O>,
+    /// The payload type name the selector expects
+    parameters: &'static str,
+}
+
+impl<'a, S: Copy, O> Dynamic<'a, S, O> {
+    pub(crate) fn new<P, F>(destinations: impl IntoIterator<Item = S>, selector: F) -> Self
+    where
+        P: core::any::Any,
+        F: Fn(&P, &O) -> S + 'a,
+    {
+        Self {
+            destinations: destinations.into_iter().map(|d| (d, None)).collect(),
+            selector: Rc::new(move |object, payload| {
+                let args = payload?.downcast_ref()?;
+                Some(selector(args, object))
+            }),
+            parameters: core::any::type_name::<P>(),
+        }
+    }
+
+    pub(crate) fn destinations(&self) -> impl Iterator<Item = S> + '_ {
+        self.destinations.iter().map(|(d, _)| *d)
+    }
+
+    pub(crate) fn resolve_destinations(&mut self, index_of: impl Fn(S) -> u16) {
+        for (destination, index) in self.destinations.iter_mut() {
+            *index = Some(index_of(*destination));
+        }
+    }
+
+    fn resolve(&self, object: &O, payload: Option<&Payload>) -> Resolution<S>
+    where
+        S: PartialEq,
+    {
+        let Some(destination) = (self.selector)(object, payload) else {
+            return Resolution::InvalidParameters {
+                expected: self.parameters,
+            };
+        };
+        match self.destinations.iter().find(|(d, _)| *d == destination) {
+            Some((_, index)) => Resolution::Transition {
+                destination,
+                destination_index: index
+                    .expect("destinations are resolved when the machine is built"),
+            },
+            None => Resolution::UndeclaredDestination { destination },
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Internal<S, T> {
-    trigger: T,
-    phantom: PhantomData<S>,
+    phantom: PhantomData<(S, T)>,
 }
 
 impl<S, T> Internal<S, T>
@@ -40,9 +242,8 @@ where
     S: Copy + Debug,
     T: Debug,
 {
-    pub fn new(trigger: T) -> Self {
+    pub fn new() -> Self {
         Self {
-            trigger,
             phantom: PhantomData,
         }
     }
@@ -59,15 +260,49 @@ mod tests {
 
     #[test]
     fn transitioning_trigger_sends_to_destination() {
-        let b = Transitioning::new(Trigger::Trig, State::State1);
+        let b = Transitioning::<_, Trigger>::new(State::State1);
         assert_eq!(State::State1, b.fire(State::State1));
         assert_eq!(State::State1, b.fire(State::State2));
     }
 
     #[test]
     fn internal_trigger_sends_to_source() {
-        let b = Internal::new(Trigger::Trig);
+        let b = Internal::<_, Trigger>::new();
         assert_eq!(State::State1, b.fire(State::State1));
         assert_eq!(State::State2, b.fire(State::State2));
     }
+
+    #[test]
+    fn behaviour_keeps_its_guard() {
+        let b = TriggerBehaviour::<_, Trigger, i32>::transitioning(State::State2, None);
+        assert!(b.guard().is_none());
+
+        let b = TriggerBehaviour::<_, Trigger, _>::transitioning(
+            State::State2,
+            Some(Guard::new("positive", |o: &i32| *o > 0)),
+        );
+        let guard = b.guard().unwrap();
+        assert!(guard.evaluate(&1));
+        assert!(!guard.evaluate(&0));
+    }
+
+    #[test]
+    fn resolve_reports_destination_index() {
+        let mut b = TriggerBehaviour::<_, Trigger, ()>::transitioning(State::State2, None);
+        if let BehaviourKind::Transitioning(t) = &mut b.kind {
+            t.resolve(1);
+        }
+        assert_eq!(
+            b.resolve(State::State1, &(), None),
+            Resolution::Transition {
+                destination: State::State2,
+                destination_index: 1
+            }
+        );
+        let internal = TriggerBehaviour::<_, Trigger, ()>::internal(None);
+        assert_eq!(
+            internal.resolve(State::State1, &(), None),
+            Resolution::Internal
+        );
+    }
 }
@@ -0,0 +1,183 @@
+use alloc::vec::Vec;
+use core::mem::{self, Discriminant};
+
+/// How triggers are told apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum TriggerMatching {
+    /// Triggers are the same when they are equal
+    #[default]
+    Value,
+    /// Triggers are the same when they are the same enum variant, whatever
+    /// data they carry
+    Discriminant,
+}
+
+/// A trigger reduced to what [`TriggerMatching`] compares
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum TriggerKey<T> {
+    Value(T),
+    Discriminant(Discriminant<T>),
+}
+
+impl TriggerMatching {
+    pub(crate) fn key<T: Copy>(self, trigger: &T) -> TriggerKey<T> {
+        match self {
+            Self::Value => TriggerKey::Value(*trigger),
+            Self::Discriminant => TriggerKey::Discriminant(mem::discriminant(trigger)),
+        }
+    }
+
+    pub(crate) fn matches<T: PartialEq>(self, a: &T, b: &T) -> bool {
+        match self {
+            Self::Value => a == b,
+            Self::Discriminant => mem::discriminant(a) == mem::discriminant(b),
+        }
+    }
+}
+
+/// A small map from trigger to `V` stored as a vector in insertion order.
+///
+/// States rarely have more than a handful of triggers, so a linear scan is
+/// cheaper than hashing and keeps iteration order deterministic.
+#[derive(Debug, Clone)]
+pub(crate) struct TriggerMap<T, V> {
+    entries: Vec<(T, V)>,
+    matching: TriggerMatching,
+}
+
+impl<T, V> TriggerMap<T, V>
+where
+    T: PartialEq,
+{
+    pub(crate) fn new() -> Self {
+        Self::with_matching(TriggerMatching::Value)
+    }
+
+    pub(crate) fn with_matching(matching: TriggerMatching) -> Self {
+        Self {
+            entries: Vec::new(),
+            matching,
+        }
+    }
+
+    pub(crate) fn get(&self, trigger: &T) -> Option<&V> {
+        self.entries
+            .iter()
+            .find(|(t, _)| self.matching.matches(t, trigger))
+            .map(|(_, v)| v)
+    }
+
+    pub(crate) fn get_mut(&mut self, trigger: &T) -> Option<&mut V> {
+        self.entries
+            .iter_mut()
+            .find(|(t, _)| self.matching.matches(t, trigger))
+            .map(|(_, v)| v)
+    }
+
+    /// Where `trigger` is stored, for use with [`Self::get_index`]
+    pub(crate) fn position(&self, trigger: &T) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(t, _)| self.matching.matches(t, trigger))
+    }
+
+    /// Where the trigger reduced to `key` is stored
+    pub(crate) fn position_of_key(&self, key: &TriggerKey<T>) -> Option<usize>
+    where
+        T: Copy + Eq,
+    {
+        self.entries
+            .iter()
+            .position(|(t, _)| self.matching.key(t) == *key)
+    }
+
+    pub(crate) fn get_index(&self, index: usize) -> &V {
+        &self.entries[index].1
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|(t, _)| t)
+    }
+
+    pub(crate) fn get_or_default(&mut self, trigger: T) -> &mut V
+    where
+        V: Default,
+    {
+        let index = match self.position(&trigger) {
+            Some(index) => index,
+            None => {
+                self.entries.push((trigger, V::default()));
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[index].1
+    }
+
+    pub(crate) fn remove(&mut self, trigger: &T) -> Option<V> {
+        let index = self.position(trigger)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&T, &V)> {
+        self.entries.iter().map(|(t, v)| (t, v))
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+
+    /// Bytes allocated for the entries themselves, not counting anything
+    /// the values allocate
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.entries.capacity() * mem::size_of::<(T, V)>()
+    }
+}
+
+impl<T, V> Default for TriggerMap<T, V>
+where
+    T: PartialEq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Trigger;
+
+    #[test]
+    fn get_or_default_inserts_once() {
+        let mut map = TriggerMap::<Trigger, Vec<i32>>::new();
+        map.get_or_default(Trigger::Trig).push(1);
+        map.get_or_default(Trigger::Trig2).push(2);
+        map.get_or_default(Trigger::Trig).push(3);
+        assert_eq!(map.iter().count(), 2);
+        assert_eq!(map.get(&Trigger::Trig), Some(&vec![1, 3]));
+        let keys: Vec<_> = map.iter().map(|(t, _)| *t).collect();
+        assert_eq!(keys, vec![Trigger::Trig, Trigger::Trig2]);
+        assert_eq!(map.position(&Trigger::Trig2), Some(1));
+        assert_eq!(map.get_index(1), &vec![2]);
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Command {
+        SetVolume(u8),
+        Mute,
+    }
+
+    #[test]
+    fn discriminant_matching_ignores_payload() {
+        let mut map = TriggerMap::<Command, Vec<i32>>::with_matching(TriggerMatching::Discriminant);
+        map.get_or_default(Command::SetVolume(0)).push(1);
+        map.get_or_default(Command::SetVolume(5)).push(2);
+        assert_eq!(map.iter().count(), 1);
+        assert_eq!(map.get(&Command::SetVolume(9)), Some(&vec![1, 2]));
+        assert_eq!(map.get(&Command::Mute), None);
+
+        let by_value = TriggerMap::<Command, Vec<i32>>::new();
+        assert!(!TriggerMatching::Value.matches(&Command::SetVolume(0), &Command::SetVolume(1)));
+        assert!(by_value.get(&Command::SetVolume(0)).is_none());
+    }
+}
@@ -0,0 +1,325 @@
+//! Reading and writing the JSON machine definitions used by XState and
+//! Stately's editor
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Write};
+
+use crate::action::ActionInfo;
+use crate::json::{self, quote, Value};
+use crate::state_machine_info::StateMachineInfo;
+use crate::XStateError;
+
+/// One entry of an imported `on` block
+pub(crate) struct XStateTransition<S, T> {
+    pub(crate) source: S,
+    pub(crate) trigger: T,
+    /// `None` for a targetless, internal transition
+    pub(crate) destination: Option<S>,
+}
+
+/// What [`read`] found, in document order
+pub(crate) struct XStateMachine<S, T> {
+    pub(crate) initial: Option<S>,
+    /// `(state, description, tags)`
+    pub(crate) states: Vec<(S, Option<String>, Vec<String>)>,
+    pub(crate) transitions: Vec<XStateTransition<S, T>>,
+}
+
+fn invalid(reason: &str) -> XStateError {
+    XStateError::InvalidMachine {
+        reason: reason.to_string(),
+    }
+}
+
+fn unsupported(feature: &str, state: &str) -> XStateError {
+    XStateError::Unsupported {
+        feature: feature.to_string(),
+        state: state.to_string(),
+    }
+}
+
+/// Read the states and transitions of an XState definition, looking names
+/// up with `state` and `trigger`
+pub(crate) fn read<S: Copy, T: Copy>(
+    text: &str,
+    state: impl Fn(&str) -> Option<S>,
+    trigger: impl Fn(&str) -> Option<T>,
+) -> Result<XStateMachine<S, T>, XStateError> {
+    let lookup_state = |name: &str| {
+        state(name).ok_or_else(|| XStateError::UnknownState {
+            name: name.to_string(),
+        })
+    };
+    let root = json::parse(text).map_err(|offset| XStateError::InvalidJson { offset })?;
+    let initial = match root.get("initial") {
+        Some(name) => Some(lookup_state(
+            name.as_str()
+                .ok_or_else(|| invalid("initial is not a string"))?,
+        )?),
+        None => None,
+    };
+    let Some(Value::Object(states)) = root.get("states") else {
+        return Err(invalid("states is missing or not an object"));
+    };
+    let mut machine = XStateMachine {
+        initial,
+        states: Vec::new(),
+        transitions: Vec::new(),
+    };
+    for (name, node) in states {
+        let source = lookup_state(name)?;
+        for feature in ["states", "after", "always", "invoke"] {
+            if node.get(feature).is_some() {
+                return Err(unsupported(feature, name));
+            }
+        }
+        if let Some(kind @ ("parallel" | "history")) = node.get("type").and_then(Value::as_str) {
+            return Err(unsupported(kind, name));
+        }
+        let description = node
+            .get("description")
+            .and_then(Value::as_str)
+            .map(String::from);
+        let tags = match node.get("tags") {
+            Some(Value::Array(tags)) => tags
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect(),
+            Some(Value::String(tag)) => alloc::vec![tag.clone()],
+            _ => Vec::new(),
+        };
+        machine.states.push((source, description, tags));
+        let Some(on) = node.get("on") else {
+            continue;
+        };
+        let Value::Object(events) = on else {
+            return Err(invalid("on is not an object"));
+        };
+        for (event, config) in events {
+            let trigger = trigger(event).ok_or_else(|| XStateError::UnknownTrigger {
+                name: event.clone(),
+            })?;
+            let configs = match config {
+                Value::Array(configs) => configs.iter().collect(),
+                config => alloc::vec![config],
+            };
+            for config in configs {
+                let target = match config {
+                    Value::String(target) => Some(target.as_str()),
+                    Value::Object(_) => {
+                        if config.get("guard").is_some() || config.get("cond").is_some() {
+                            return Err(unsupported("guard", name));
+                        }
+                        match config.get("target") {
+                            None | Some(Value::Null) => None,
+                            Some(Value::String(target)) => Some(target.as_str()),
+                            Some(Value::Array(targets)) if targets.len() == 1 => Some(
+                                targets[0]
+                                    .as_str()
+                                    .ok_or_else(|| invalid("target is not a string"))?,
+                            ),
+                            Some(_) => return Err(unsupported("multiple targets", name)),
+                        }
+                    }
+                    _ => return Err(invalid("a transition is not a string or object")),
+                };
+                let destination = match target {
+                    Some(target) => Some(lookup_state(target.trim_start_matches('.'))?),
+                    None => None,
+                };
+                machine.transitions.push(XStateTransition {
+                    source,
+                    trigger,
+                    destination,
+                });
+            }
+        }
+    }
+    Ok(machine)
+}
+
+fn names(infos: &[ActionInfo]) -> String {
+    let names: Vec<_> = infos.iter().map(|info| quote(&info.description)).collect();
+    format!("[{}]", names.join(", "))
+}
+
+impl<S, T> StateMachineInfo<S, T>
+where
+    S: Copy + PartialEq + Debug,
+    T: Copy + PartialEq + Debug,
+{
+    /// An XState machine definition with id `id`, which Stately's editor
+    /// can open.
+    ///
+    /// States and events are named by their `Debug` output.  Guards and
+    /// actions are exported by description and name, for display only.
+    pub fn to_xstate(&self, id: &str) -> String {
+        let name = |value: &dyn Debug| quote(&format!("{:?}", value));
+        let mut out = String::from("{\n");
+        let _ = writeln!(out, "  \"id\": {},", quote(id));
+        let _ = writeln!(out, "  \"initial\": {},", name(&self.initial_state));
+        out.push_str("  \"states\": {");
+        for (i, state) in self.states.iter().enumerate() {
+            let mut fields = Vec::new();
+            if let Some(description) = &state.metadata.description {
+                fields.push(format!("\"description\": {}", quote(description)));
+            }
+            if !state.metadata.tags.is_empty() {
+                let tags: Vec<_> = state.metadata.tags.iter().map(|t| quote(t)).collect();
+                fields.push(format!("\"tags\": [{}]", tags.join(", ")));
+            }
+            if !state.entry_actions.is_empty() {
+                fields.push(format!("\"entry\": {}", names(&state.entry_actions)));
+            }
+            if !state.exit_actions.is_empty() {
+                fields.push(format!("\"exit\": {}", names(&state.exit_actions)));
+            }
+            let mut events: Vec<(T, Vec<String>)> = Vec::new();
+            for transition in &state.transitions {
+                let mut config = Vec::new();
+                if let Some(destination) = transition.destination {
+                    config.push(format!("\"target\": {}", name(&destination)));
+                    if destination == state.state {
+                        config.push(String::from("\"reenter\": true"));
+                    }
+                }
+                if let Some(guard) = &transition.guard_description {
+                    config.push(format!("\"guard\": {}", quote(guard)));
+                }
+                if !transition.actions.is_empty() {
+                    config.push(format!("\"actions\": {}", names(&transition.actions)));
+                }
+                let config = format!("{{{}}}", config.join(", "));
+                match events.iter_mut().find(|(t, _)| *t == transition.trigger) {
+                    Some((_, configs)) => configs.push(config),
+                    None => events.push((transition.trigger, alloc::vec![config])),
+                }
+            }
+            if !events.is_empty() {
+                let on: Vec<_> = events
+                    .iter()
+                    .map(|(trigger, configs)| {
+                        format!("        {}: [{}]", name(trigger), configs.join(", "))
+                    })
+                    .collect();
+                fields.push(format!("\"on\": {{\n{}\n      }}", on.join(",\n")));
+            }
+            let separator = if i == 0 { "" } else { "," };
+            if fields.is_empty() {
+                let _ = write!(out, "{}\n    {}: {{}}", separator, name(&state.state));
+            } else {
+                let _ = write!(
+                    out,
+                    "{}\n    {}: {{\n      {}\n    }}",
+                    separator,
+                    name(&state.state),
+                    fields.join(",\n      ")
+                );
+            }
+        }
+        out.push_str("\n  }\n}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{State, Trigger};
+    use crate::StateMachineBuilder;
+
+    fn trigger(name: &str) -> Option<Trigger> {
+        match name {
+            "Trig" => Some(Trigger::Trig),
+            "Trig2" => Some(Trigger::Trig2),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn exports_xstate() -> eyre::Result<()> {
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder.config(State::State1).describe("first").permit_if(
+            Trigger::Trig,
+            State::State2,
+            crate::Guard::new("ready", |_| true),
+        );
+        builder
+            .config(State::State2)
+            .permit(Trigger::Trig, State::State2)
+            .internal_transition(Trigger::Trig2, |_, _| ());
+        let xstate = builder.build(())?.info().to_xstate("door");
+        assert_eq!(
+            xstate,
+            r#"{
+  "id": "door",
+  "initial": "State1",
+  "states": {
+    "State1": {
+      "description": "first",
+      "on": {
+        "Trig": [{"target": "State2", "guard": "ready"}]
+      }
+    },
+    "State2": {
+      "on": {
+        "Trig": [{"target": "State2", "reenter": true}],
+        "Trig2": [{"actions": ["action"]}]
+      }
+    }
+  }
+}
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_through_the_builder() -> eyre::Result<()> {
+        let json = r#"{
+            "id": "door",
+            "initial": "State1",
+            "states": {
+                "State1": {"tags": ["closed"], "on": {"Trig": "State2"}},
+                "State2": {"on": {"Trig": {"target": "State1"}, "Trig2": {"actions": ["log"]}}}
+            }
+        }"#;
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder.apply_xstate(json, trigger)?;
+        let mut machine = builder.build(())?;
+        assert!(machine.current_state_has_tag("closed"));
+        machine.fire(Trigger::Trig)?;
+        machine.fire(Trigger::Trig2)?;
+        assert_eq!(machine.state(), State::State2);
+        machine.fire(Trigger::Trig)?;
+        assert_eq!(machine.state(), State::State1);
+
+        let mut builder = StateMachineBuilder::<_, _, ()>::new(State::State1);
+        builder.apply_xstate(&machine.info().to_xstate("door"), trigger)?;
+        let reread = builder.build(())?.info();
+        assert_eq!(reread.equivalent_to(&machine.info()), Ok(()));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_represent() {
+        let read = |json| read(json, |_| Some(State::State1), trigger).err();
+        assert_eq!(
+            read(r#"{"states": {"State1": {"on": {"Trig": {"target": "State1", "guard": "g"}}}}}"#),
+            Some(unsupported("guard", "State1"))
+        );
+        assert_eq!(
+            read(r#"{"states": {"State1": {"states": {}}}}"#),
+            Some(unsupported("states", "State1"))
+        );
+        assert_eq!(
+            read(r#"{"states": {"State1": {"on": {"Nope": "State1"}}}}"#),
+            Some(XStateError::UnknownTrigger {
+                name: "Nope".into()
+            })
+        );
+        assert_eq!(read("{"), Some(XStateError::InvalidJson { offset: 1 }));
+    }
+}
@@ -0,0 +1,51 @@
+use alloc::string::String;
+use core::fmt::Display;
+
+/// Why [`crate::StateMachineBuilder::apply_xstate`] couldn't read a
+/// definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XStateError {
+    /// Not valid JSON, at this byte offset
+    InvalidJson {
+        offset: usize,
+    },
+    /// Valid JSON, but not the shape of an XState machine
+    InvalidMachine {
+        reason: String,
+    },
+    UnknownState {
+        name: String,
+    },
+    UnknownTrigger {
+        name: String,
+    },
+    /// `initial` names a different state than the builder was created with
+    InitialStateMismatch {
+        name: String,
+    },
+    /// A feature of XState this crate can't represent, such as guards or
+    /// nested states
+    Unsupported {
+        feature: String,
+        state: String,
+    },
+}
+
+impl Display for XStateError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidJson { offset } => write!(f, "invalid JSON at byte {offset}"),
+            Self::InvalidMachine { reason } => write!(f, "invalid XState machine: {reason}"),
+            Self::UnknownState { name } => write!(f, "unknown state {name:?}"),
+            Self::UnknownTrigger { name } => write!(f, "unknown trigger {name:?}"),
+            Self::InitialStateMismatch { name } => {
+                write!(f, "initial state {name:?} differs from the builder's")
+            }
+            Self::Unsupported { feature, state } => {
+                write!(f, "{feature} in state {state:?} is not supported")
+            }
+        }
+    }
+}
+
+impl core::error::Error for XStateError {}